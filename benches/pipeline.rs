@@ -0,0 +1,41 @@
+//! Compares warm-cache load times with and without `pipeline(true)` on a synthetic dataset the size of CIFAR-10.
+//! Run with `cargo bench --bench pipeline`
+
+use cifar_ten::{testing, Cifar10};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 7;
+
+/// Median wall time of building the dataset `RUNS` times, after one warm-up build to fill the page cache
+fn median_build_time(config: &Cifar10) -> Duration {
+    config.clone().build().expect("Synthetic dataset loads");
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            config.clone().build().expect("Synthetic dataset loads");
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn main() {
+    let base_path = std::env::temp_dir().join("cifar_ten_bench_pipeline");
+    let config = testing::write_dataset(&base_path, &[10_000; 5], 10_000)
+        .expect("Synthetic dataset is written");
+
+    for (name, encode_one_hot) in [("one-hot labels", true), ("class indices", false)] {
+        let config = config.clone().encode_one_hot(encode_one_hot);
+        let sequential = median_build_time(&config);
+        let pipelined = median_build_time(&config.clone().pipeline(true));
+        println!(
+            "{:<15} sequential {:>8.1} ms   pipelined {:>8.1} ms   speedup {:.2}x",
+            name,
+            sequential.as_secs_f64() * 1000.0,
+            pipelined.as_secs_f64() * 1000.0,
+            sequential.as_secs_f64() / pipelined.as_secs_f64()
+        );
+    }
+    std::fs::remove_dir_all(&base_path).expect("Synthetic dataset is removed");
+}
@@ -0,0 +1,59 @@
+//! Compares the plain pixel conversion loop with its copy compiled for AVX2, which the parser picks at runtime
+//! when the CPU supports it. Run with `cargo bench --bench simd`
+
+use cifar_ten::testing;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 15;
+
+/// Median wall time of converting `pixels` record by record `RUNS` times, after one warm-up run, or `None` if the
+/// AVX2 copy was asked for and the CPU doesn't support it. With `in_cache` set each record overwrites the last,
+/// measuring the conversion alone; otherwise the records fill a full output buffer as when parsing
+fn median_time(pixels: &[u8], avx2: bool, in_cache: bool) -> Option<Duration> {
+    let mut converted = Vec::with_capacity(pixels.len());
+    let mut times = Vec::with_capacity(RUNS);
+    for run in 0..=RUNS {
+        converted.clear();
+        let start = Instant::now();
+        for record in pixels.chunks_exact(3072) {
+            if in_cache {
+                converted.clear();
+            }
+            if !testing::convert_pixels(&mut converted, record, avx2) {
+                return None;
+            }
+        }
+        std::hint::black_box(&converted);
+        if run > 0 {
+            times.push(start.elapsed());
+        }
+    }
+    times.sort();
+    Some(times[RUNS / 2])
+}
+
+fn main() {
+    // As many pixels as one binary holds
+    let pixels: Vec<u8> = (0..10_000).flat_map(testing::pixels).collect();
+    for (name, in_cache) in [("in cache", true), ("full output", false)] {
+        let scalar = median_time(&pixels, false, in_cache).expect("The plain loop always runs");
+        match median_time(&pixels, true, in_cache) {
+            Some(avx2) => println!(
+                "{:<12} plain loop {:>7.2} ms   AVX2 {:>7.2} ms   speedup {:.2}x",
+                name,
+                scalar.as_secs_f64() * 1000.0,
+                avx2.as_secs_f64() * 1000.0,
+                scalar.as_secs_f64() / avx2.as_secs_f64()
+            ),
+            None => println!(
+                "{:<12} plain loop {:>7.2} ms   AVX2 not supported by this CPU",
+                name,
+                scalar.as_secs_f64() * 1000.0
+            ),
+        }
+    }
+    match testing::convert_pixels(&mut vec![], &[], true) {
+        true => println!("The parser takes the AVX2 path on this CPU"),
+        false => println!("The parser takes the plain loop on this CPU"),
+    }
+}
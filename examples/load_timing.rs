@@ -0,0 +1,20 @@
+// Compares load times with and without the pipelined reader, using the binaries already in "./data/"
+// $ cargo run --release --example load_timing
+use cifar_ten::*;
+use std::time::Instant;
+
+fn main() {
+    for &pipeline in &[false, true, false, true] {
+        let start = Instant::now();
+        let result = Cifar10::default()
+            .pipeline(pipeline)
+            .build()
+            .expect("Failed to build CIFAR-10 data");
+        println!(
+            "pipeline({}): loaded {} records in {:?}",
+            pipeline,
+            (result.0.len() + result.2.len()) / 3072,
+            start.elapsed()
+        );
+    }
+}
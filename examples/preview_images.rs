@@ -24,6 +24,8 @@ use show_image::{
     BoxImage, ImageInfo, WindowOptions,
 };
 use std::error::Error;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 #[show_image::main]
 fn main() {
@@ -50,10 +52,31 @@ fn main() {
         .into_shape(10)
         .unwrap();
     println!("The image is of a: {}", return_label_from_one_hot(label));
-    display_img(&img);
+    // The window is shown on a background thread, so the program could keep working here until it is closed
+    let viewer = spawn_viewer(img, show_timeout());
+    viewer.join().unwrap().unwrap();
 }
 
-pub fn display_img(img_arr: &Array3<u8>) -> Result<(), Box<dyn Error>> {
+/// Seconds to keep the window open for when `CIFAR_SHOW_TIMEOUT` is set, so that runs in CI don't hang waiting
+/// for a key press
+fn show_timeout() -> Option<Duration> {
+    std::env::var("CIFAR_SHOW_TIMEOUT")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs_f64)
+}
+
+/// Displays the image on a background thread and returns immediately; join the handle to wait for the window
+/// to be closed
+pub fn spawn_viewer(
+    img_arr: Array3<u8>,
+    timeout: Option<Duration>,
+) -> JoinHandle<Result<(), String>> {
+    std::thread::spawn(move || display_img(&img_arr, timeout).map_err(|e| e.to_string()))
+}
+
+/// Displays the image until ESC is pressed, or until `timeout` has passed if one is given
+pub fn display_img(img_arr: &Array3<u8>, timeout: Option<Duration>) -> Result<(), Box<dyn Error>> {
     let test_result_img = convert_to_image(img_arr);
 
     // let boxed_image = BoxImage::new(
@@ -74,7 +97,19 @@ pub fn display_img(img_arr: &Array3<u8>) -> Result<(), Box<dyn Error>> {
     let window = create_window("cifar-10", Default::default())?;
     window.set_image("test_result", test_result_img).unwrap();
 
-    for event in window.event_channel()? {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let events = window.event_channel()?;
+    loop {
+        let event = match deadline {
+            Some(deadline) => events
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok(),
+            None => events.recv().ok(),
+        };
+        let event = match event {
+            Some(event) => event,
+            None => break,
+        };
         if let WindowEvent::KeyboardInput(WindowKeyboardInputEvent { input, .. }) = event {
             if input.key_code == Some(show_image::event::VirtualKeyCode::Escape) {
                 break;
@@ -86,18 +121,11 @@ pub fn display_img(img_arr: &Array3<u8>) -> Result<(), Box<dyn Error>> {
 }
 
 fn convert_to_image(array: &Array3<u8>) -> RgbImage {
-    // println!("- Converting to image!");
+    // `array` is a single `3 x 32 x 32` record in the CIFAR-10 layout
+    let record = array.as_slice().expect("Records are contiguous");
     let mut img: RgbImage = ImageBuffer::new(32, 32);
-    let (_d, w, h) = (array.shape()[0], array.shape()[1], array.shape()[2]);
-    // println!("(d,w,h) = ({},{},{})",d,w,h);
-    for y in 0..h {
-        for x in 0..w {
-            let r = array[[2, x, y]];
-            let g = array[[1, x, y]];
-            let b = array[[0, x, y]];
-            img.put_pixel(y as u32, x as u32, Rgb([b, g, r]));
-        }
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        *pixel = Rgb(PixelPlaneOrder::CIFAR.pixel(record, y as usize, x as usize));
     }
-
     img
 }
@@ -0,0 +1,52 @@
+use crate::Cifar10;
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+use tar::Archive;
+
+/// File name of the compressed CIFAR-10 binaries
+pub(crate) const ARCHIVE: &str = "cifar-10-binary.tar.gz";
+
+/// Reads the given binaries straight out of the compressed archive, without extracting anything to disk
+pub(crate) fn read_archive(
+    archive: impl Read,
+    cifar_data_path: &str,
+    bin_paths: &[&String],
+) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
+    let mut archive = Archive::new(GzDecoder::new(archive));
+    let mut contents = HashMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if let Some(bin) = bin_paths
+            .iter()
+            .find(|bin| path == Path::new(cifar_data_path).join(bin))
+        {
+            let mut buffer = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut buffer)?;
+            contents.insert(bin.to_string(), buffer);
+        }
+    }
+
+    Ok(contents)
+}
+
+/// Concatenates the given binaries, as read by `read_archive`, spot checking each of them
+pub(crate) fn read_archived(
+    config: &Cifar10,
+    archive: &HashMap<String, Vec<u8>>,
+    bin_paths: &[String],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buffer: Vec<u8> = Vec::new();
+    for bin in bin_paths {
+        let contents = archive
+            .get(bin)
+            .ok_or_else(|| format!("{} was not found in {}", bin, ARCHIVE))?;
+        crate::spot_check::check_binary(config, bin, contents)?;
+        buffer.extend(contents);
+    }
+    Ok(buffer)
+}
@@ -0,0 +1,64 @@
+use crate::subset::{class_index, label_width};
+use crate::CifarResult;
+use arrow_array::{
+    ArrayRef, FixedSizeListArray, RecordBatch, StringArray, UInt32Array, UInt8Array,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema};
+use std::error::Error;
+use std::io::Write;
+use std::sync::Arc;
+
+impl CifarResult {
+    /// Writes the training and then the testing records to `writer` in the Arrow IPC stream format, as record
+    /// batches of at most `batch_size` rows with the columns `split` (utf8), `index` (uint32), `label` (uint8)
+    /// and `pixels` (fixed-size list of 3072 uint8). Fails if `batch_size` is zero
+    pub fn stream_batches_arrow<W: Write>(
+        &self,
+        writer: W,
+        batch_size: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        if batch_size == 0 {
+            return Err("Record batches must hold at least one row".into());
+        }
+        let pixel_field = Arc::new(Field::new("item", DataType::UInt8, false));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("split", DataType::Utf8, false),
+            Field::new("index", DataType::UInt32, false),
+            Field::new("label", DataType::UInt8, false),
+            Field::new(
+                "pixels",
+                DataType::FixedSizeList(pixel_field.clone(), 3072),
+                false,
+            ),
+        ]));
+
+        let mut writer = StreamWriter::try_new(writer, &schema)?;
+        for (split, data, labels) in [("train", &self.0, &self.1), ("test", &self.2, &self.3)] {
+            let num_records = data.len() / 3072;
+            let width = label_width(data, labels);
+            for start in (0..num_records).step_by(batch_size) {
+                let end = (start + batch_size).min(num_records);
+                let pixels = FixedSizeListArray::try_new(
+                    pixel_field.clone(),
+                    3072,
+                    Arc::new(UInt8Array::from(data[start * 3072..end * 3072].to_vec())),
+                    None,
+                )?;
+                let columns: Vec<ArrayRef> = vec![
+                    Arc::new(StringArray::from(vec![split; end - start])),
+                    Arc::new(UInt32Array::from_iter_values(
+                        (start..end).map(|i| i as u32),
+                    )),
+                    Arc::new(UInt8Array::from_iter_values(
+                        (start..end).map(|i| class_index(labels, width, i)),
+                    )),
+                    Arc::new(pixels),
+                ];
+                writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+            }
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
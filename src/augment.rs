@@ -0,0 +1,862 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// Element types that transforms operate on: `u8` pixels as stored, or `f32` pixels for pipelines that
+/// normalize first, which are taken to be scaled to `[0, 1]`
+pub trait Pixel: Copy + Default + PartialEq + Send + Sync + 'static {
+    /// The brightest value, 255 for `u8` and 1 for `f32`
+    const MAX: f32;
+
+    fn to_f32(self) -> f32;
+
+    /// Converts back from `f32`, rounding and clamping into range for `u8`
+    fn from_f32(x: f32) -> Self;
+}
+
+impl Pixel for u8 {
+    const MAX: f32 = 255.0;
+
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    fn from_f32(x: f32) -> Self {
+        x.round().clamp(0.0, 255.0) as u8
+    }
+}
+
+impl Pixel for f32 {
+    const MAX: f32 = 1.0;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(x: f32) -> Self {
+        x
+    }
+}
+
+/// Blends `other` into `record` with weight `lambda` for `record`, as in mixup. With `f32` records this composes
+/// with the other transforms without rounding in between; the labels should be blended with the same weight
+pub fn mixup<P: Pixel>(record: &mut [P], other: &[P], lambda: f32) {
+    for (x, &y) in record.iter_mut().zip(other) {
+        *x = P::from_f32(lambda * x.to_f32() + (1.0 - lambda) * y.to_f32());
+    }
+}
+
+/// Mirrors a square `3 x side x side` record left-to-right in place
+pub fn flip_horizontal<P: Pixel>(record: &mut [P]) {
+    let side = side_of(record.len());
+    for row in record.chunks_exact_mut(side) {
+        row.reverse();
+    }
+}
+
+/// Mirrors a square `3 x side x side` record top-to-bottom in place
+pub fn flip_vertical<P: Pixel>(record: &mut [P]) {
+    let side = side_of(record.len());
+    for plane in record.chunks_exact_mut(side * side) {
+        for y in 0..side / 2 {
+            let (top, bottom) = plane.split_at_mut((side - 1 - y) * side);
+            top[y * side..(y + 1) * side].swap_with_slice(&mut bottom[..side]);
+        }
+    }
+}
+
+/// Rotates a square `3 x side x side` record by `k` quarter turns counter-clockwise in place, matching
+/// `numpy.rot90`. Negative `k` turns clockwise
+pub fn rot90<P: Pixel>(record: &mut [P], k: i32) {
+    let side = side_of(record.len());
+    match k.rem_euclid(4) {
+        0 => {}
+        2 => {
+            flip_horizontal(record);
+            flip_vertical(record);
+        }
+        turns => {
+            for plane in record.chunks_exact_mut(side * side) {
+                let source = plane.to_vec();
+                for y in 0..side {
+                    for x in 0..side {
+                        plane[y * side + x] = match turns {
+                            1 => source[x * side + side - 1 - y],
+                            _ => source[(side - 1 - x) * side + y],
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Same as `flip_horizontal`, as a view of a `[3, side, side]` array without copying
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub fn flip_horizontal_view<T>(
+    mut image: crate::ndarray::ArrayView3<'_, T>,
+) -> crate::ndarray::ArrayView3<'_, T> {
+    image.invert_axis(crate::ndarray::Axis(2));
+    image
+}
+
+/// Same as `flip_vertical`, as a view of a `[3, side, side]` array without copying
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub fn flip_vertical_view<T>(
+    mut image: crate::ndarray::ArrayView3<'_, T>,
+) -> crate::ndarray::ArrayView3<'_, T> {
+    image.invert_axis(crate::ndarray::Axis(1));
+    image
+}
+
+/// Same as `rot90`, as a view of a `[3, side, side]` array without copying. The result is not in standard
+/// layout unless `k` is a multiple of 4
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub fn rot90_view<T>(
+    mut image: crate::ndarray::ArrayView3<'_, T>,
+    k: i32,
+) -> crate::ndarray::ArrayView3<'_, T> {
+    use crate::ndarray::Axis;
+    match k.rem_euclid(4) {
+        0 => {}
+        1 => {
+            image.invert_axis(Axis(2));
+            image.swap_axes(1, 2);
+        }
+        2 => {
+            image.invert_axis(Axis(1));
+            image.invert_axis(Axis(2));
+        }
+        _ => {
+            image.swap_axes(1, 2);
+            image.invert_axis(Axis(2));
+        }
+    }
+    image
+}
+
+/// Same as `Transform::GaussianBlur` with a fixed `sigma`, in place on a `[3, side, side]` array of any layout,
+/// such as a view returned by `rot90_view`
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub fn gaussian_blur_view<P: Pixel>(mut image: crate::ndarray::ArrayViewMut3<'_, P>, sigma: f32) {
+    let side = image.shape()[1];
+    assert_eq!(
+        image.shape(),
+        [3, side, side],
+        "Images must be square with 3 channels"
+    );
+    match image.as_slice_mut() {
+        Some(record) => gaussian_blur(record, side, sigma),
+        None => {
+            let mut record: Vec<P> = image.iter().copied().collect();
+            gaussian_blur(&mut record, side, sigma);
+            for (x, y) in image.iter_mut().zip(record) {
+                *x = y;
+            }
+        }
+    }
+}
+
+/// Same as `Transform::Solarize`, in place on an array of any shape and layout
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub fn solarize_view<P: Pixel>(mut image: crate::ndarray::ArrayViewMut3<'_, P>, threshold: f32) {
+    for x in image.iter_mut() {
+        solarize(x, threshold);
+    }
+}
+
+/// A single image transform, applied in place to a `3 x 32 x 32` record (or any other square `3 x side x side`
+/// record)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Transform {
+    /// Mirrors the image left-to-right with probability `p`
+    HorizontalFlip { p: f32 },
+    /// Mirrors the image top-to-bottom with probability `p`
+    VerticalFlip { p: f32 },
+    /// Rotates the image by `k` quarter turns counter-clockwise, always; useful for deterministic test-time
+    /// augmentation
+    Rot90 { k: i32 },
+    /// Pads every side of the image with `padding` zero pixels, then crops a random `32 x 32` window
+    RandomCrop { padding: usize },
+    /// Scales brightness, contrast and saturation by random factors drawn from `[1 - x, 1 + x]` and rotates the hue
+    /// by up to `hue` of a turn (at most 0.5), applying the four adjustments in random order as torchvision's
+    /// `ColorJitter` does. Zero leaves that property unchanged, and values saturate at black and white
+    ColorJitter {
+        brightness: f32,
+        contrast: f32,
+        saturation: f32,
+        hue: f32,
+    },
+    /// Crops a random window covering a fraction `scale` of the image area with an aspect ratio (width over height)
+    /// in `ratio`, and bilinearly resizes it to `3 x size x size`, following torchvision's `RandomResizedCrop`
+    /// (typically `scale: (0.08, 1.0), ratio: (0.75, 1.333)`). Applied in place, `size` must match the record's
+    /// size, which `Augmentation::try_new` checks; `Augmentation::apply_resized` allows changing it
+    RandomResizedCrop {
+        scale: (f32, f32),
+        ratio: (f32, f32),
+        size: usize,
+    },
+    /// Blurs the image with a Gaussian kernel whose standard deviation, in pixels, is drawn uniformly from
+    /// `sigma_range`, as in BYOL and SimCLR. The kernel is separable and covers three standard deviations, with
+    /// edge pixels repeated past the border. The range must be finite, non-negative and ascending, which
+    /// `Augmentation::try_new` checks
+    GaussianBlur { sigma_range: (f32, f32) },
+    /// Equalizes the histogram of the image, spreading its values over the full range, as an ablation for
+    /// low-contrast images. Always applied
+    HistogramEqualize { mode: EqualizeMode },
+    /// Inverts every value at or above `threshold`, given as a fraction of the brightest value (so 0.5 matches
+    /// torchvision's threshold of 128 on `u8` images)
+    Solarize { threshold: f32 },
+    /// Applies `transform` with probability `p`, and otherwise leaves the image unchanged
+    RandomApply { p: f32, transform: Box<Transform> },
+}
+
+/// What `Transform::HistogramEqualize` equalizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EqualizeMode {
+    /// Each channel separately, which can shift colors
+    PerChannel,
+    /// The luminance, scaling the three channels of each pixel together so hues are kept
+    Luminance,
+}
+
+/// Side length of a square `3 x side x side` record
+fn side_of(len: usize) -> usize {
+    let side = ((len / 3) as f64).sqrt().round() as usize;
+    assert_eq!(
+        3 * side * side,
+        len,
+        "Records must be square 3-channel images"
+    );
+    side
+}
+
+impl Transform {
+    /// Applies the transform to a single record, of either `u8` or `f32` pixels
+    pub fn apply<P: Pixel, R: Rng + ?Sized>(&self, record: &mut [P], rng: &mut R) {
+        let side = side_of(record.len());
+        match *self {
+            Transform::HorizontalFlip { p } => {
+                if rng.gen::<f32>() < p {
+                    flip_horizontal(record);
+                }
+            }
+            Transform::VerticalFlip { p } => {
+                if rng.gen::<f32>() < p {
+                    flip_vertical(record);
+                }
+            }
+            Transform::Rot90 { k } => rot90(record, k),
+            Transform::RandomCrop { padding } => {
+                let dy = rng.gen_range(0..=2 * padding) as isize - padding as isize;
+                let dx = rng.gen_range(0..=2 * padding) as isize - padding as isize;
+                let source = record.to_vec();
+                let bounds = 0..side as isize;
+                for (c, plane) in record.chunks_exact_mut(side * side).enumerate() {
+                    for y in 0..side {
+                        for x in 0..side {
+                            let (sy, sx) = (y as isize + dy, x as isize + dx);
+                            plane[y * side + x] = if bounds.contains(&sy) && bounds.contains(&sx) {
+                                source[(c * side + sy as usize) * side + sx as usize]
+                            } else {
+                                P::default()
+                            };
+                        }
+                    }
+                }
+            }
+            Transform::ColorJitter {
+                brightness,
+                contrast,
+                saturation,
+                hue,
+            } => {
+                let mut factor = |x: f32| rng.gen_range((1.0 - x).max(0.0)..=1.0 + x);
+                let adjustments = [
+                    (brightness > 0.0).then(|| Jitter::Brightness(factor(brightness))),
+                    (contrast > 0.0).then(|| Jitter::Contrast(factor(contrast))),
+                    (saturation > 0.0).then(|| Jitter::Saturation(factor(saturation))),
+                    (hue > 0.0).then(|| Jitter::Hue(rng.gen_range(-hue.min(0.5)..=hue.min(0.5)))),
+                ];
+                let mut adjustments: Vec<Jitter> = adjustments.iter().flatten().copied().collect();
+                adjustments.shuffle(rng);
+
+                let mut image: Vec<f32> = record.iter().map(|x| x.to_f32() / P::MAX).collect();
+                for adjustment in adjustments {
+                    adjustment.apply(&mut image);
+                }
+                for (x, y) in record.iter_mut().zip(image) {
+                    *x = P::from_f32(y.clamp(0.0, 1.0) * P::MAX);
+                }
+            }
+            Transform::GaussianBlur { sigma_range } => {
+                let sigma = rng.gen_range(sigma_range.0..=sigma_range.1);
+                gaussian_blur(record, side, sigma);
+            }
+            Transform::HistogramEqualize { mode } => {
+                let mut image: Vec<f32> = record.iter().map(|x| x.to_f32() / P::MAX).collect();
+                let plane = side * side;
+                match mode {
+                    EqualizeMode::PerChannel => {
+                        for channel in image.chunks_exact_mut(plane) {
+                            let equalized = equalize(channel);
+                            channel.copy_from_slice(&equalized);
+                        }
+                    }
+                    EqualizeMode::Luminance => {
+                        let gray: Vec<f32> = (0..plane)
+                            .map(|i| {
+                                0.299 * image[i]
+                                    + 0.587 * image[plane + i]
+                                    + 0.114 * image[2 * plane + i]
+                            })
+                            .collect();
+                        for (i, (&before, after)) in gray.iter().zip(equalize(&gray)).enumerate() {
+                            for c in 0..3 {
+                                let x = &mut image[c * plane + i];
+                                *x = match before > 0.0 {
+                                    true => *x * after / before,
+                                    false => after,
+                                };
+                            }
+                        }
+                    }
+                }
+                for (x, y) in record.iter_mut().zip(image) {
+                    *x = P::from_f32(y.clamp(0.0, 1.0) * P::MAX);
+                }
+            }
+            Transform::Solarize { threshold } => {
+                for x in record.iter_mut() {
+                    solarize(x, threshold);
+                }
+            }
+            Transform::RandomApply { p, ref transform } => {
+                if rng.gen::<f32>() < p {
+                    transform.apply(record, rng);
+                }
+            }
+            Transform::RandomResizedCrop { size, .. } => {
+                assert_eq!(
+                    size, side,
+                    "RandomResizedCrop can only change the image size through Augmentation::apply_resized"
+                );
+                let crop = self.resized(record, rng);
+                record.copy_from_slice(&crop);
+            }
+        }
+    }
+
+    /// Checks that the transform's parameters are valid and that it can be applied in place to `3 x side x side`
+    /// records
+    fn check(&self, side: usize) -> Result<(), Box<dyn Error>> {
+        match *self {
+            Transform::GaussianBlur {
+                sigma_range: (low, high),
+            } if !(0.0 <= low && low <= high && high.is_finite()) => Err(format!(
+                "GaussianBlur sigma range ({}, {}) must be finite, non-negative and in ascending order",
+                low, high
+            )
+            .into()),
+            Transform::RandomResizedCrop { size, .. } if size != side => Err(format!(
+                "RandomResizedCrop to {0} x {0} can't be applied in place to {1} x {1} records, only through \
+                 Augmentation::apply_resized",
+                size, side
+            )
+            .into()),
+            Transform::RandomApply { ref transform, .. } => transform.check(side),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies the transform to a copy of a record, which `RandomResizedCrop` may resize
+    pub fn resized<P: Pixel, R: Rng + ?Sized>(&self, record: &[P], rng: &mut R) -> Vec<P> {
+        match *self {
+            Transform::RandomResizedCrop { scale, ratio, size } => {
+                let side = side_of(record.len());
+                resized_crop(record, side, sample_crop(rng, side, scale, ratio), size)
+            }
+            Transform::RandomApply { p, ref transform } => match rng.gen::<f32>() < p {
+                true => transform.resized(record, rng),
+                false => record.to_vec(),
+            },
+            _ => {
+                let mut output = record.to_vec();
+                self.apply(&mut output, rng);
+                output
+            }
+        }
+    }
+}
+
+/// SplitMix64 finalizer, which spreads nearby inputs over unrelated outputs so they can seed independent streams
+pub(crate) fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Random number generator for the record at `index` in epoch `epoch`, as used by `Augmentation::apply_epoch`
+fn epoch_rng(seed: u64, epoch: u64, index: usize) -> StdRng {
+    let stream = splitmix64(seed ^ epoch.wrapping_mul(0xd1b5_4a32_d192_ed03));
+    StdRng::seed_from_u64(splitmix64(
+        stream ^ (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15),
+    ))
+}
+
+/// Maps values in `[0, 1]` through the cumulative distribution of their 256-bin histogram, so the darkest value
+/// becomes 0 and the rest spread evenly up to 1. Images of a single value are left unchanged
+fn equalize(values: &[f32]) -> Vec<f32> {
+    let bin = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as usize;
+    let mut cdf = [0usize; 256];
+    for &x in values {
+        cdf[bin(x)] += 1;
+    }
+    for i in 1..256 {
+        cdf[i] += cdf[i - 1];
+    }
+    let lowest = cdf.iter().copied().find(|&count| count > 0).unwrap_or(0);
+    if values.len() == lowest {
+        return values.to_vec();
+    }
+    values
+        .iter()
+        .map(|&x| (cdf[bin(x)] - lowest) as f32 / (values.len() - lowest) as f32)
+        .collect()
+}
+
+/// One of the adjustments made by `Transform::ColorJitter`, with its randomly drawn strength
+#[derive(Clone, Copy)]
+enum Jitter {
+    Brightness(f32),
+    Contrast(f32),
+    Saturation(f32),
+    Hue(f32),
+}
+
+impl Jitter {
+    /// Adjusts a `3 x side x side` image with values in `[0, 1]`, keeping them in range
+    fn apply(self, image: &mut [f32]) {
+        let plane = image.len() / 3;
+        let gray: Vec<f32> = (0..plane)
+            .map(|i| 0.299 * image[i] + 0.587 * image[plane + i] + 0.114 * image[2 * plane + i])
+            .collect();
+        match self {
+            Jitter::Brightness(factor) => {
+                for x in image.iter_mut() {
+                    *x = (*x * factor).clamp(0.0, 1.0);
+                }
+            }
+            Jitter::Contrast(factor) => {
+                let mean = gray.iter().sum::<f32>() / plane as f32;
+                for x in image.iter_mut() {
+                    *x = (mean + factor * (*x - mean)).clamp(0.0, 1.0);
+                }
+            }
+            Jitter::Saturation(factor) => {
+                for channel in image.chunks_exact_mut(plane) {
+                    for (x, &g) in channel.iter_mut().zip(&gray) {
+                        *x = (g + factor * (*x - g)).clamp(0.0, 1.0);
+                    }
+                }
+            }
+            Jitter::Hue(shift) => {
+                for i in 0..plane {
+                    let (h, s, v) = rgb_to_hsv(image[i], image[plane + i], image[2 * plane + i]);
+                    let (r, g, b) = hsv_to_rgb((h + shift).rem_euclid(1.0), s, v);
+                    image[i] = r;
+                    image[plane + i] = g;
+                    image[2 * plane + i] = b;
+                }
+            }
+        }
+    }
+}
+
+/// Inverts `x` if it is at or above `threshold` as a fraction of the brightest value
+fn solarize<P: Pixel>(x: &mut P, threshold: f32) {
+    if x.to_f32() >= threshold * P::MAX {
+        *x = P::from_f32(P::MAX - x.to_f32());
+    }
+}
+
+/// Blurs each plane of a `3 x side x side` record with a separable Gaussian kernel of standard deviation `sigma`,
+/// first along rows and then along columns
+fn gaussian_blur<P: Pixel>(record: &mut [P], side: usize, sigma: f32) {
+    if sigma <= 0.0 {
+        return;
+    }
+    let radius = ((3.0 * sigma).ceil() as usize).min(side.saturating_sub(1));
+    let kernel: Vec<f32> = (0..=2 * radius)
+        .map(|i| (-((i as f32 - radius as f32).powi(2)) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = kernel.iter().sum();
+    let kernel: Vec<f32> = kernel.into_iter().map(|k| k / total).collect();
+
+    let tap = |i: usize, k: usize| (i + k).saturating_sub(radius).min(side - 1);
+    let mut rows = vec![0f32; side * side];
+    for plane in record.chunks_exact_mut(side * side) {
+        for y in 0..side {
+            for x in 0..side {
+                rows[y * side + x] = kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, w)| w * plane[y * side + tap(x, k)].to_f32())
+                    .sum();
+            }
+        }
+        for y in 0..side {
+            for x in 0..side {
+                let value: f32 = kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, w)| w * rows[tap(y, k) * side + x])
+                    .sum();
+                plane[y * side + x] = P::from_f32(value);
+            }
+        }
+    }
+}
+
+/// Converts a color with channels in `[0, 1]` to hue (in turns), saturation and value
+fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+    (h, s, max)
+}
+
+/// Converts hue (in turns), saturation and value back to a color with channels in `[0, 1]`
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+    let h = h * 6.0;
+    let c = v * s;
+    let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    (r + m, g + m, b + m)
+}
+
+/// Samples a crop window `(top, left, height, width)` of a `side x side` image covering a random fraction `scale` of
+/// its area with a random aspect ratio in `ratio`, following the semantics of torchvision's `RandomResizedCrop`
+pub(crate) fn sample_crop<R: Rng + ?Sized>(
+    rng: &mut R,
+    side: usize,
+    scale: (f32, f32),
+    ratio: (f32, f32),
+) -> (usize, usize, usize, usize) {
+    let area = (side * side) as f32;
+    let log_ratio = (ratio.0.ln(), ratio.1.ln());
+    for _ in 0..10 {
+        let target_area = area * rng.gen_range(scale.0..=scale.1);
+        let aspect_ratio = rng.gen_range(log_ratio.0..=log_ratio.1).exp();
+        let w = (target_area * aspect_ratio).sqrt().round() as usize;
+        let h = (target_area / aspect_ratio).sqrt().round() as usize;
+        if 0 < w && w <= side && 0 < h && h <= side {
+            return (
+                rng.gen_range(0..=side - h),
+                rng.gen_range(0..=side - w),
+                h,
+                w,
+            );
+        }
+    }
+
+    // Fall back to a central crop, clamping the aspect ratio into range
+    let (w, h) = if 1.0 < ratio.0 {
+        (side, (side as f32 / ratio.0).round() as usize)
+    } else if 1.0 > ratio.1 {
+        ((side as f32 * ratio.1).round() as usize, side)
+    } else {
+        (side, side)
+    };
+    ((side - h) / 2, (side - w) / 2, h, w)
+}
+
+/// Bilinearly resizes the window `(top, left, height, width)` of a `3 x side x side` record to `3 x size x size`
+pub(crate) fn resized_crop<P: Pixel>(
+    record: &[P],
+    side: usize,
+    window: (usize, usize, usize, usize),
+    size: usize,
+) -> Vec<P> {
+    let (top, left, height, width) = window;
+    let mut output = vec![P::default(); 3 * size * size];
+    for c in 0..3 {
+        let plane = &record[c * side * side..(c + 1) * side * side];
+        for y in 0..size {
+            let sy = ((y as f32 + 0.5) * height as f32 / size as f32 - 0.5).max(0.0) + top as f32;
+            let (y0, fy) = (sy.floor() as usize, sy.fract());
+            let y1 = (y0 + 1).min(top + height - 1);
+            for x in 0..size {
+                let sx =
+                    ((x as f32 + 0.5) * width as f32 / size as f32 - 0.5).max(0.0) + left as f32;
+                let (x0, fx) = (sx.floor() as usize, sx.fract());
+                let x1 = (x0 + 1).min(left + width - 1);
+                let p = |y: usize, x: usize| plane[y * side + x].to_f32();
+                let value = p(y0, x0) * (1.0 - fy) * (1.0 - fx)
+                    + p(y0, x1) * (1.0 - fy) * fx
+                    + p(y1, x0) * fy * (1.0 - fx)
+                    + p(y1, x1) * fy * fx;
+                output[c * size * size + y * size + x] = P::from_f32(value);
+            }
+        }
+    }
+    output
+}
+
+/// SwAV/DINO-style multi-crop settings: each image yields `global_crops` large crops followed by `local_crops`
+/// small crops, each resized to `3 x size x size`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MultiCrop {
+    pub global_crops: usize,
+    pub global_size: usize,
+    pub global_scale: (f32, f32),
+    pub local_crops: usize,
+    pub local_size: usize,
+    pub local_scale: (f32, f32),
+}
+
+impl Default for MultiCrop {
+    /// Two `32 x 32` global crops covering 40-100% of the image and six `16 x 16` local crops covering 5-40%
+    fn default() -> Self {
+        MultiCrop {
+            global_crops: 2,
+            global_size: 32,
+            global_scale: (0.4, 1.0),
+            local_crops: 6,
+            local_size: 16,
+            local_scale: (0.05, 0.4),
+        }
+    }
+}
+
+impl MultiCrop {
+    /// Returns the crops of a single record, global crops first, each as `3 x size x size` pixels
+    pub fn crops<R: Rng + ?Sized>(&self, record: &[u8], rng: &mut R) -> Vec<Vec<u8>> {
+        let ratio = (3.0 / 4.0, 4.0 / 3.0);
+        let mut crops = Vec::with_capacity(self.global_crops + self.local_crops);
+        for &(count, size, scale) in &[
+            (self.global_crops, self.global_size, self.global_scale),
+            (self.local_crops, self.local_size, self.local_scale),
+        ] {
+            for _ in 0..count {
+                crops.push(resized_crop(
+                    record,
+                    32,
+                    sample_crop(rng, 32, scale, ratio),
+                    size,
+                ));
+            }
+        }
+        crops
+    }
+
+    /// Same as `crops`, with each crop as a `[3, size, size]` array
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn crops_ndarray<R: Rng + ?Sized>(
+        &self,
+        record: &[u8],
+        rng: &mut R,
+    ) -> Vec<crate::ndarray::Array3<u8>> {
+        self.crops(record, rng)
+            .into_iter()
+            .enumerate()
+            .map(|(i, crop)| {
+                let size = match i < self.global_crops {
+                    true => self.global_size,
+                    false => self.local_size,
+                };
+                crate::nd::array((3, size, size), crop)
+                    .expect("Crops always have 3 x size x size pixels")
+            })
+            .collect()
+    }
+}
+
+/// An ordered list of transforms, applied one after another. With the `serde` feature it can be loaded from an
+/// experiment config file, e.g. `{"transforms": [{"HorizontalFlip": {"p": 0.5}}, {"RandomCrop": {"padding": 4}}]}`
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Augmentation {
+    pub transforms: Vec<Transform>,
+}
+
+impl Augmentation {
+    pub fn new(transforms: Vec<Transform>) -> Self {
+        Augmentation { transforms }
+    }
+
+    /// Same as `new`, but returns an error for transforms with invalid parameters, such as an inverted
+    /// `GaussianBlur` sigma range, or that can't be applied in place to `32 x 32` records with `apply`, `apply_all`
+    /// or `apply_epoch`, such as a `RandomResizedCrop` to another size, which would otherwise only panic once
+    /// applied
+    pub fn try_new(transforms: Vec<Transform>) -> Result<Self, Box<dyn Error>> {
+        let augmentation = Augmentation { transforms };
+        augmentation.validate(32)?;
+        Ok(augmentation)
+    }
+
+    /// Checks that every transform has valid parameters and can be applied in place to `3 x side x side` records,
+    /// e.g. for an augmentation loaded from a config file
+    pub fn validate(&self, side: usize) -> Result<(), Box<dyn Error>> {
+        for transform in &self.transforms {
+            transform.check(side)?;
+        }
+        Ok(())
+    }
+
+    /// Applies every transform to a single record, in order
+    pub fn apply<P: Pixel, R: Rng + ?Sized>(&self, record: &mut [P], rng: &mut R) {
+        for transform in &self.transforms {
+            transform.apply(record, rng);
+        }
+    }
+
+    /// Returns the source record followed by its state after each successive transform
+    pub fn stages<P: Pixel, R: Rng + ?Sized>(&self, record: &[P], rng: &mut R) -> Vec<Vec<P>> {
+        let mut stages = vec![record.to_vec()];
+        for transform in &self.transforms {
+            let mut next = stages[stages.len() - 1].clone();
+            transform.apply(&mut next, rng);
+            stages.push(next);
+        }
+        stages
+    }
+
+    /// Applies every transform to a copy of a record, in order, letting `RandomResizedCrop` change the image size
+    /// along the way
+    pub fn apply_resized<P: Pixel, R: Rng + ?Sized>(&self, record: &[P], rng: &mut R) -> Vec<P> {
+        let mut output = record.to_vec();
+        for transform in &self.transforms {
+            output = transform.resized(&output, rng);
+        }
+        output
+    }
+
+    /// Renders a grid for visually checking the transform parameters. Each row starts from the source image and
+    /// shows it after each successive transform, and each of the `rows` rows uses a different random draw
+    #[cfg(feature = "export")]
+    pub fn preview(&self, record: &[u8], rows: u32, seed: u64) -> image::RgbImage {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let cols = self.transforms.len() as u32 + 1;
+        let mut grid = image::RgbImage::new(cols * 34, rows * 34);
+        for row in 0..rows {
+            for (col, stage) in self.stages(record, &mut rng).iter().enumerate() {
+                let img = crate::record_to_image(stage);
+                image::imageops::replace(&mut grid, &img, col as i64 * 34 + 1, row as i64 * 34 + 1);
+            }
+        }
+        image::imageops::resize(
+            &grid,
+            grid.width() * 4,
+            grid.height() * 4,
+            image::imageops::FilterType::Nearest,
+        )
+    }
+
+    /// Saves the output of `preview` as a PNG
+    #[cfg(feature = "export")]
+    pub fn save_preview(
+        &self,
+        record: &[u8],
+        rows: u32,
+        seed: u64,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.preview(record, rows, seed).save(path)?;
+        Ok(())
+    }
+
+    /// Applies the transforms to every record of `data`, seeding the random number generator with `seed`
+    pub fn apply_all<P: Pixel>(&self, data: &mut [P], seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for record in data.chunks_exact_mut(3072) {
+            self.apply(record, &mut rng);
+        }
+    }
+
+    /// Applies the transforms to every record of `data` for one epoch of training, with the augmentation of each
+    /// record determined only by `seed`, `epoch` and its index in `data`. Unlike `apply_all`, any augmented
+    /// sample can be regenerated afterwards with `epoch_sample`, and with the `rayon` feature the records are
+    /// augmented in parallel with the same results
+    pub fn apply_epoch<P: Pixel>(&self, data: &mut [P], seed: u64, epoch: u64) {
+        let augment = |(index, record): (usize, &mut [P])| {
+            self.apply(record, &mut epoch_rng(seed, epoch, index));
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            data.par_chunks_exact_mut(3072)
+                .enumerate()
+                .for_each(augment);
+        }
+        #[cfg(not(feature = "rayon"))]
+        data.chunks_exact_mut(3072).enumerate().for_each(augment);
+    }
+
+    /// Regenerates the augmented form of the record at `index` as produced by `apply_epoch` with the same `seed`
+    /// and `epoch`, e.g. to inspect an input blamed for a loss spike
+    pub fn epoch_sample<P: Pixel>(
+        &self,
+        record: &[P],
+        seed: u64,
+        epoch: u64,
+        index: usize,
+    ) -> Vec<P> {
+        let mut output = record.to_vec();
+        self.apply(&mut output, &mut epoch_rng(seed, epoch, index));
+        output
+    }
+}
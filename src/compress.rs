@@ -0,0 +1,79 @@
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+
+/// Uncompressed size of each independently compressed frame, small enough that a 150MB cache splits evenly
+/// across threads
+const FRAME_SIZE: usize = 1 << 20;
+
+/// Compresses `data` with zstd at `level` as a sequence of independent frames, preceded by the frame count and
+/// each frame's compressed length, so that `decompress` can spread the frames across threads
+pub(crate) fn compress(data: &[u8], level: i32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let chunks: Vec<&[u8]> = data.chunks(FRAME_SIZE).collect();
+    let frames = in_parallel(&chunks, |chunk| {
+        zstd::bulk::compress(chunk, level).map_err(|e| e.to_string())
+    })?;
+
+    let mut output =
+        Vec::with_capacity(4 + frames.len() * 8 + frames.iter().map(Vec::len).sum::<usize>());
+    output.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+    for frame in &frames {
+        output.extend_from_slice(&(frame.len() as u64).to_le_bytes());
+    }
+    for frame in &frames {
+        output.extend_from_slice(frame);
+    }
+    Ok(output)
+}
+
+/// Decompresses the output of `compress`, which must hold exactly `len` bytes, decompressing frames in parallel
+pub(crate) fn decompress(data: &[u8], len: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let count = u32::from_le_bytes(data.get(..4).ok_or("Truncated cache")?.try_into()?) as usize;
+    if count != len.div_ceil(FRAME_SIZE) {
+        return Err("Cache holds the wrong number of frames".into());
+    }
+    let mut frames = Vec::with_capacity(count);
+    let mut offset = 4 + count * 8;
+    for i in 0..count {
+        let header = data.get(4 + i * 8..12 + i * 8).ok_or("Truncated cache")?;
+        // Sizes come from the file, so a corrupt one must not overflow the offset
+        let end = usize::try_from(u64::from_le_bytes(header.try_into()?))
+            .ok()
+            .and_then(|size| offset.checked_add(size))
+            .ok_or("Corrupt frame size in cache")?;
+        frames.push(data.get(offset..end).ok_or("Truncated cache")?);
+        offset = end;
+    }
+
+    let chunks = in_parallel(&frames, |frame| {
+        let chunk = zstd::bulk::decompress(frame, FRAME_SIZE).map_err(|e| e.to_string())?;
+        Ok(chunk)
+    })?;
+    let output = chunks.concat();
+    if output.len() != len {
+        return Err("Cache holds the wrong number of bytes".into());
+    }
+    Ok(output)
+}
+
+/// Maps `f` over `items` on scoped threads, one contiguous group of items per available core
+fn in_parallel<T: Sync, R: Send>(
+    items: &[T],
+    f: impl Fn(&T) -> Result<R, String> + Sync,
+) -> Result<Vec<R>, Box<dyn Error>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let group = items.len().div_ceil(threads).max(1);
+    let f = &f;
+    let results: Result<Vec<Vec<R>>, String> = std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(group)
+            .map(|group| {
+                scope.spawn(move || group.iter().map(&f).collect::<Result<Vec<R>, String>>())
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Compression threads don't panic"))
+            .collect()
+    });
+    Ok(results?.into_iter().flatten().collect())
+}
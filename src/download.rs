@@ -1,87 +1,388 @@
-use curl::easy::Easy;
-use dir_lock::DirLock;
 use filesize::PathExt;
-use pbr::ProgressBar;
-use std::convert::TryInto;
+use fs2::FileExt;
+use pbr::{ProgressBar, Units};
 use std::error::Error;
+use std::fmt;
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tar::Archive;
 
-const ARCHIVE: &str = "cifar-10-binary.tar.gz";
+use crate::archive::ARCHIVE;
+use crate::http::Transport;
+use crate::Cifar10;
 const ARCHIVE_DOWNLOAD_SIZE: usize = 170052171;
+/// Size of a single binary of 10000 records, as fetched on its own with `Cifar10::test_batch_url`
+const BINARY_SIZE: u64 = 10000 * 3073;
+/// Size of the extracted `cifar-10-batches-bin` directory: six 30730000 byte binaries plus the metadata and readme
+const EXTRACTED_SIZE: u64 = 6 * 30730000 + 149;
+
+/// Progress of a download (the archive, or the testing binary on its own), passed to the callback set with
+/// `Cifar10::download_progress` about twice a second and once more when the download completes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadProgress {
+    /// Bytes received so far
+    pub bytes: u64,
+    /// Expected size of the archive
+    pub total: u64,
+    /// Time since the download started
+    pub elapsed: Duration,
+    /// Average transfer speed so far
+    pub bytes_per_sec: f64,
+    /// Estimated time until the download completes, once any data has arrived
+    pub eta: Option<Duration>,
+    /// Whether the download has completed, making this the final summary
+    pub done: bool,
+}
+
+impl DownloadProgress {
+    fn new(bytes: u64, total: u64, elapsed: Duration, done: bool) -> Self {
+        let bytes_per_sec = match elapsed.as_secs_f64() {
+            secs if secs > 0.0 => bytes as f64 / secs,
+            _ => 0.0,
+        };
+        let eta = match done {
+            true => Some(Duration::ZERO),
+            false if bytes_per_sec > 0.0 => Some(Duration::from_secs_f64(
+                total.saturating_sub(bytes) as f64 / bytes_per_sec,
+            )),
+            false => None,
+        };
+        DownloadProgress {
+            bytes,
+            total,
+            elapsed,
+            bytes_per_sec,
+            eta,
+            done,
+        }
+    }
+}
+
+impl fmt::Display for DownloadProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mb = |bytes: f64| bytes / 1e6;
+        if self.done {
+            return write!(
+                f,
+                "Downloaded {:.1} MB in {:.1} s ({:.2} MB/s)",
+                mb(self.bytes as f64),
+                self.elapsed.as_secs_f64(),
+                mb(self.bytes_per_sec)
+            );
+        }
+        write!(
+            f,
+            "{:.1} of {:.1} MB at {:.2} MB/s",
+            mb(self.bytes as f64),
+            mb(self.total as f64),
+            mb(self.bytes_per_sec)
+        )?;
+        match self.eta {
+            Some(eta) => write!(f, ", {} s left", eta.as_secs()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Callback set with `Cifar10::download_progress`
+#[derive(Clone)]
+pub struct ProgressCallback(pub(crate) Arc<dyn Fn(&DownloadProgress) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Returned by a build that would download or extract the dataset onto a filesystem without enough free space for
+/// it, before anything is written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsufficientSpace {
+    /// Directory the dataset would be written to
+    pub path: PathBuf,
+    /// Bytes the download and extraction need
+    pub required: u64,
+    /// Bytes free on the filesystem holding `path`
+    pub available: u64,
+}
+
+impl fmt::Display for InsufficientSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Not enough free space in {} to download CIFAR-10: {:.1} MB required, {:.1} MB available",
+            self.path.display(),
+            self.required as f64 / 1e6,
+            self.available as f64 / 1e6
+        )
+    }
+}
+
+impl Error for InsufficientSpace {}
+
+/// Takes an advisory lock on a download directory, blocking while another process holds it. The lock is released
+/// when the returned file is dropped
+fn lock_dir(dir: &Path) -> Result<File, Box<dyn Error>> {
+    let lock = File::create(dir.join(".cifar-ten.lock"))?;
+    if lock.try_lock_exclusive().is_err() {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            "waiting for another process downloading into {}",
+            dir.display()
+        );
+        lock.lock_exclusive()?;
+    }
+    Ok(lock)
+}
+
+/// Checks that the filesystem holding `download_dir` can fit whatever of the archive and its extracted contents
+/// isn't there yet
+fn check_space(download_dir: &Path, extract_to_disk: bool) -> Result<(), InsufficientSpace> {
+    let mut required = 0;
+    if !download_dir.join(ARCHIVE).exists() {
+        required += ARCHIVE_DOWNLOAD_SIZE as u64;
+    }
+    if extract_to_disk && !download_dir.join("cifar-10-batches-bin").exists() {
+        required += EXTRACTED_SIZE;
+    }
+    // If the free space can't be determined, fall through and let the write itself report the problem
+    match fs2::available_space(download_dir) {
+        Ok(available) if available < required => Err(InsufficientSpace {
+            path: download_dir.to_owned(),
+            required,
+            available,
+        }),
+        _ => Ok(()),
+    }
+}
 
 pub(super) fn download_and_extract(
     download_url: String,
     base_path: impl Into<PathBuf>,
-) -> Result<(), Box<dyn Error>> {
+    extract_to_disk: bool,
+    progress: Option<&ProgressCallback>,
+    transport: Transport<'_>,
+) -> Result<(Duration, Duration), Box<dyn Error>> {
     let download_dir = base_path.into();
     if !download_dir.exists() {
-        println!(
-            "Download directory {} does not exists. Creating....",
-            download_dir.display()
-        );
+        #[cfg(feature = "tracing")]
+        tracing::info!("creating download directory {}", download_dir.display());
         fs::create_dir_all(&download_dir)?;
     }
-    let _dir_lock = DirLock::new(&download_dir);
-    println!("Attempting to download and extract {}...", ARCHIVE);
-    download(download_url, &download_dir)?;
-    extract(&ARCHIVE, &download_dir)?;
+    // Concurrent builds into the same directory would otherwise race on the archive and the extraction
+    let _lock = lock_dir(&download_dir)?;
+    check_space(&download_dir, extract_to_disk)?;
+    let start = Instant::now();
+    if extract_to_disk {
+        #[cfg(feature = "tracing")]
+        tracing::info!("downloading and extracting {}", ARCHIVE);
+        download(download_url, &download_dir, progress, transport)?;
+        let downloaded = start.elapsed();
+        extract(ARCHIVE, &download_dir)?;
+        Ok((downloaded, start.elapsed() - downloaded))
+    } else {
+        #[cfg(feature = "tracing")]
+        tracing::info!("downloading {}", ARCHIVE);
+        download(download_url, &download_dir, progress, transport)?;
+        Ok((start.elapsed(), Duration::ZERO))
+    }
+}
 
-    Ok(())
+impl Cifar10 {
+    /// Downloads the compressed archive from the download URL into the base path, unless it is already there, and
+    /// returns its path without extracting or parsing anything. Downloads take the same directory lock and free
+    /// space check as a build with `download_and_extract(true)`
+    pub fn download_archive_only(&self) -> Result<PathBuf, Box<dyn Error>> {
+        if self.is_remote() {
+            return Err("download_archive_only requires a local base path".into());
+        }
+        let base_path = self.resolved_base_path();
+        download_and_extract(
+            self.download_url.clone(),
+            base_path.clone(),
+            false,
+            self.download_progress.as_ref(),
+            self.transport(),
+        )?;
+        Ok(base_path.join(ARCHIVE))
+    }
+
+    /// Downloads the archive like `download_archive_only` and returns a reader over its decompressed tar stream,
+    /// which can be passed to `tar::Archive` for custom processing such as re-sharding
+    pub fn archive_reader(&self) -> Result<impl Read, Box<dyn Error>> {
+        let archive = File::open(self.download_archive_only()?)?;
+        Ok(flate2::read::GzDecoder::new(archive))
+    }
 }
 
-fn download(url: String, download_dir: impl Into<PathBuf>) -> Result<(), Box<dyn Error>> {
-    let mut easy = Easy::new();
+/// Downloads a single file to `path` unless it already exists, returning how long the download took. The lock is
+/// taken on `download_dir`, the same directory `download_and_extract` locks, so the two can't race. Progress is
+/// reported as for the archive, against the size of one binary
+pub(super) fn download_file(
+    url: &str,
+    path: &Path,
+    download_dir: &Path,
+    progress: Option<&ProgressCallback>,
+    transport: Transport<'_>,
+) -> Result<Duration, Box<dyn Error>> {
+    fs::create_dir_all(path.parent().unwrap_or(download_dir))?;
+    let _lock = lock_dir(download_dir)?;
+    if path.exists() {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{} already exists, skipping the download", path.display());
+        return Ok(Duration::ZERO);
+    }
+    #[cfg(any(feature = "s3", feature = "gcs"))]
+    let url = &crate::remote::object_url(url)?;
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("download", url = %url).entered();
+    #[cfg(feature = "tracing")]
+    tracing::info!("downloading {} to {}", url, path.display());
+
+    let start = Instant::now();
+    // Written under a temporary name first, so an interrupted download isn't mistaken for a complete one
+    let partial = path.with_extension("part");
+    let mut file = File::create(&partial)?;
+    if let Err(e) = fetch_with_progress(url, &partial, &mut file, BINARY_SIZE, progress, transport)
+    {
+        // Don't leave the partial file behind
+        drop(file);
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, path)?;
+    Ok(start.elapsed())
+}
+
+fn download(
+    url: String,
+    download_dir: impl Into<PathBuf>,
+    progress: Option<&ProgressCallback>,
+    transport: Transport<'_>,
+) -> Result<(), Box<dyn Error>> {
+    #[cfg(any(feature = "s3", feature = "gcs"))]
+    let url = crate::remote::object_url(&url)?;
 
     let file_name = download_dir.into().join(ARCHIVE); //.clone();
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("download", url = %url).entered();
     if Path::new(&file_name).exists() {
-        println!(
-            "  File {:?} already exists, skipping downloading.",
-            file_name
-        );
-    } else {
-        println!(
-            "- Downloading from file from {} and saving to file as: {}",
-            url,
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            "{} already exists, skipping the download",
             file_name.display()
         );
+    } else {
+        #[cfg(feature = "tracing")]
+        tracing::info!("downloading {} to {}", url, file_name.display());
 
-        let mut file = File::create(file_name.clone()).unwrap();
+        // As for single binaries, a failed download mustn't leave an archive behind that later builds would take
+        // as complete
+        let partial = file_name.with_extension("part");
+        let mut file = File::create(&partial)?;
+        if let Err(e) = fetch_with_progress(
+            &url,
+            &partial,
+            &mut file,
+            ARCHIVE_DOWNLOAD_SIZE as u64,
+            progress,
+            transport,
+        ) {
+            drop(file);
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        fs::rename(&partial, &file_name)?;
+    }
 
-        let full_size = ARCHIVE_DOWNLOAD_SIZE;
+    Ok(())
+}
 
-        let pb_thread = thread::spawn(move || {
-            let mut pb = ProgressBar::new(full_size.try_into().unwrap());
-            pb.format("╢=> ╟");
+/// Fetches `url` into `file`, found at `path`, while reporting how much of the expected `full_size` has arrived:
+/// to the callback if one is set and otherwise on a progress bar, as well as to tracing. A final summary goes to
+/// the callback and tracing once the fetch succeeds
+fn fetch_with_progress(
+    url: &str,
+    path: &Path,
+    file: &mut File,
+    full_size: u64,
+    progress: Option<&ProgressCallback>,
+    transport: Transport<'_>,
+) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let finished = Arc::new(AtomicBool::new(false));
 
-            let mut current_size = 0;
-            while current_size < full_size {
-                current_size = file_name
-                    .size_on_disk()
-                    .expect(&format!("Couldn't get metadata on {:?}", file_name))
-                    as usize;
-                pb.set(current_size.try_into().unwrap());
+    let pb_thread = {
+        let finished = finished.clone();
+        let progress = progress.cloned();
+        let path = path.to_owned();
+        thread::spawn(move || {
+            // The bar is only drawn when no callback takes over the reporting
+            let mut pb = match progress {
+                Some(_) => None,
+                None => {
+                    let mut pb = ProgressBar::new(full_size);
+                    pb.format("╢=> ╟");
+                    pb.set_units(Units::Bytes);
+                    Some(pb)
+                }
+            };
+            let mut last_report = Instant::now();
+            while !finished.load(Ordering::Relaxed) {
+                let current_size = path.size_on_disk().unwrap_or(0).min(full_size);
+                if let Some(pb) = &mut pb {
+                    pb.set(current_size);
+                }
+                if last_report.elapsed() >= Duration::from_millis(500) {
+                    last_report = Instant::now();
+                    let update =
+                        DownloadProgress::new(current_size, full_size, start.elapsed(), false);
+                    if let Some(progress) = &progress {
+                        (progress.0)(&update);
+                    }
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        bytes = update.bytes,
+                        total = update.total,
+                        bytes_per_sec = update.bytes_per_sec,
+                        eta_secs = update.eta.map(|eta| eta.as_secs_f64()),
+                        "downloading {}",
+                        path.display()
+                    );
+                }
                 thread::sleep(Duration::from_millis(10));
             }
-            pb.finish_println(" ");
-        });
-
-        easy.url(&url).unwrap();
-        easy.write_function(move |data| {
-            file.write_all(data).unwrap();
-            Ok(data.len())
+            if let Some(mut pb) = pb {
+                pb.set(full_size);
+                pb.finish_println(" ");
+            }
         })
-        .unwrap();
-        easy.perform().unwrap();
+    };
 
-        pb_thread.join().unwrap();
-    }
+    let fetched = transport.fetch(url, file);
+    finished.store(true, Ordering::Relaxed);
+    pb_thread.join().unwrap();
+    fetched?;
 
+    let bytes = file.metadata()?.len();
+    let summary = DownloadProgress::new(bytes, full_size, start.elapsed(), true);
+    if let Some(progress) = progress {
+        (progress.0)(&summary);
+    }
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        monotonic_counter.bytes_downloaded = bytes,
+        duration_secs = summary.elapsed.as_secs_f64(),
+        bytes_per_sec = summary.bytes_per_sec,
+        "{}",
+        summary
+    );
     Ok(())
 }
 
@@ -90,13 +391,21 @@ fn extract(archive_name: &str, download_dir: &Path) -> Result<(), Box<dyn Error>
     let archive = download_dir.to_owned().join(archive_name);
 
     let extract_to = download_dir.to_owned().join("cifar-10-batches-bin");
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("extract", archive = %archive.display()).entered();
     if Path::new(&extract_to).exists() {
-        println!(
-            "  Extracted file {:?} already exists, skipping extraction.",
-            extract_to
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            "{} already exists, skipping the extraction",
+            extract_to.display()
         );
     } else {
-        println!("Beginning extraction of {:?} to {:?}", archive, extract_to);
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            "extracting {} to {}",
+            archive.display(),
+            extract_to.display()
+        );
         use flate2::read::GzDecoder;
         let tar_gz = File::open(archive)?;
         let tar = GzDecoder::new(tar_gz);
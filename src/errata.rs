@@ -0,0 +1,118 @@
+use crate::subset::{class_index, label_width};
+use crate::{CifarResult, Split, CLASS_NAMES};
+use std::error::Error;
+use std::path::Path;
+
+/// A list of label corrections to records of the published binaries, such as those found by cleanlab
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Errata {
+    /// `(split, index within the split, corrected class)` for each corrected record
+    pub corrections: Vec<(Split, usize, u8)>,
+}
+
+/// Class indices of a split before and after applying `Errata`, as returned by `CifarResult::apply_errata`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrectedLabels {
+    pub original: Vec<u8>,
+    pub corrected: Vec<u8>,
+    /// Indices of the records whose label the errata changed
+    pub changed: Vec<usize>,
+}
+
+impl Errata {
+    /// Loads corrections from a CSV file with a header naming the columns `split` (`train` or `test`), `index`
+    /// and `label` (a class index or name, such as `cat`). Other columns, such as a note on each correction, are
+    /// ignored. No list is bundled with the crate, as published errata disagree with one another
+    pub fn from_csv(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        let header: Vec<&str> = lines
+            .next()
+            .ok_or("The errata file is empty")?
+            .split(',')
+            .map(|name| name.trim())
+            .collect();
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|&h| h == name)
+                .ok_or_else(|| format!("The errata file has no {} column", name))
+        };
+        let (split_col, index_col, label_col) =
+            (column("split")?, column("index")?, column("label")?);
+
+        let mut corrections = Vec::new();
+        for (row, line) in lines.filter(|line| !line.trim().is_empty()).enumerate() {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let field = |col: usize| {
+                fields
+                    .get(col)
+                    .copied()
+                    .ok_or_else(|| format!("Row {} of the errata file is too short", row))
+            };
+            let split = match field(split_col)? {
+                "train" => Split::Train,
+                "test" => Split::Test,
+                other => return Err(format!("Row {}: Unknown split {}", row, other).into()),
+            };
+            let label = field(label_col)?;
+            let label = match CLASS_NAMES.iter().position(|&name| name == label) {
+                Some(class) => class as u8,
+                None => label.parse()?,
+            };
+            if label > 9 {
+                return Err(format!(
+                    "Row {}: Label is {}, which is inconsistent with the CIFAR-10 scheme",
+                    row, label
+                )
+                .into());
+            }
+            corrections.push((split, field(index_col)?.parse()?, label));
+        }
+        Ok(Errata { corrections })
+    }
+}
+
+impl CifarResult {
+    /// Applies label corrections to both splits, returning the original and corrected class indices of the
+    /// training split and then the testing split. The result must hold every record of the published binaries in
+    /// their original order, as the errata refer to records by position
+    pub fn apply_errata(
+        &self,
+        errata: &Errata,
+    ) -> Result<(CorrectedLabels, CorrectedLabels), Box<dyn Error>> {
+        let labels = |data: &[u8], labels: &[u8]| -> CorrectedLabels {
+            let width = label_width(data, labels);
+            let original: Vec<u8> = (0..data.len() / 3072)
+                .map(|i| class_index(labels, width, i))
+                .collect();
+            CorrectedLabels {
+                corrected: original.clone(),
+                original,
+                changed: vec![],
+            }
+        };
+        let mut train = labels(&self.0, &self.1);
+        let mut test = labels(&self.2, &self.3);
+
+        for &(split, index, label) in &errata.corrections {
+            let split_labels = match split {
+                Split::Train => &mut train,
+                Split::Test => &mut test,
+            };
+            let corrected = split_labels.corrected.get_mut(index).ok_or_else(|| {
+                format!(
+                    "Errata refer to {:?} record {}, which wasn't loaded",
+                    split, index
+                )
+            })?;
+            if *corrected != label {
+                *corrected = label;
+                split_labels.changed.push(index);
+            }
+        }
+        train.changed.sort_unstable();
+        test.changed.sort_unstable();
+        Ok((train, test))
+    }
+}
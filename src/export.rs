@@ -0,0 +1,404 @@
+use crate::subset::{class_index, label_width};
+use crate::{class_name, CifarResult, PixelPlaneOrder, Split};
+use image::{Rgb, RgbImage};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+/// Converts a single `3 x 32 x 32` record (R, G and B planes in that order) into an `RgbImage`
+pub fn record_to_image(record: &[u8]) -> RgbImage {
+    let mut img = RgbImage::new(32, 32);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        *pixel = Rgb(PixelPlaneOrder::CIFAR.pixel(record, y as usize, x as usize));
+    }
+    img
+}
+
+/// Converts a `32 x 32` `RgbImage` into a single record's pixels (R, G and B planes in that order)
+pub fn image_to_record(img: &RgbImage) -> Vec<u8> {
+    PixelPlaneOrder::InterleavedRgb.convert(img.as_raw(), PixelPlaneOrder::CIFAR)
+}
+
+/// Encodes a single `3 x 32 x 32` record as a PNG. When a label is given, it is embedded as `tEXt` metadata (the
+/// class name under `Label` and the class index under `LabelIndex`), so viewers and tools can read it from the
+/// file itself rather than from its name or directory
+pub fn record_to_png(record: &[u8], label: Option<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut png = Vec::new();
+    record_to_image(record)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    if let Some(label) = label {
+        let mut chunks = text_chunk("Label", &class_name(label));
+        chunks.extend(text_chunk("LabelIndex", &label.to_string()));
+        // The signature and the IHDR chunk always come first
+        png.splice(PNG_HEADER_LEN..PNG_HEADER_LEN, chunks);
+    }
+    Ok(png)
+}
+
+/// Reads the class index embedded by `record_to_png` back out of a PNG, if it has one
+pub fn png_label(png: &[u8]) -> Option<u8> {
+    let mut rest = png.get(8..)?;
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let (kind, data) = (&rest[4..8], rest.get(8..8 + len)?);
+        match kind {
+            b"IEND" => return None,
+            b"tEXt" => {
+                if let Some(value) = data.strip_prefix(b"LabelIndex\0") {
+                    return std::str::from_utf8(value).ok()?.parse().ok();
+                }
+            }
+            _ => (),
+        }
+        rest = rest.get(12 + len..)?;
+    }
+    None
+}
+
+/// Length of the PNG signature followed by the IHDR chunk
+const PNG_HEADER_LEN: usize = 8 + 12 + 13;
+
+/// A PNG `tEXt` chunk holding `value` under `keyword`
+fn text_chunk(keyword: &str, value: &str) -> Vec<u8> {
+    let mut chunk = (keyword.len() as u32 + 1 + value.len() as u32)
+        .to_be_bytes()
+        .to_vec();
+    chunk.extend(b"tEXt");
+    chunk.extend(keyword.as_bytes());
+    chunk.push(0);
+    chunk.extend(value.as_bytes());
+    let crc = crc32(&chunk[4..]);
+    chunk.extend(crc.to_be_bytes());
+    chunk
+}
+
+/// The CRC-32 that PNG chunks end with
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Border colors of each class in `CifarResult::contact_sheet`, following the matplotlib `tab10` palette. Classes
+/// past the tenth reuse them in turn
+pub const CLASS_COLORS: [[u8; 3]; 10] = [
+    [31, 119, 180],
+    [255, 127, 14],
+    [44, 160, 44],
+    [214, 39, 40],
+    [148, 103, 189],
+    [140, 86, 75],
+    [227, 119, 194],
+    [127, 127, 127],
+    [188, 189, 34],
+    [23, 190, 207],
+];
+
+/// Width in pixels of the class-colored border drawn around each image of a contact sheet
+const SHEET_BORDER: u32 = 2;
+
+impl CifarResult {
+    /// Renders a `rows x cols` montage of images drawn at random from a split (determined by `seed`) and saves it
+    /// as a PNG at `path`, for papers and READMEs. Each image is framed in its class's color from `CLASS_COLORS`,
+    /// and cells are left white if the split has fewer images than the grid
+    pub fn contact_sheet(
+        &self,
+        path: impl AsRef<Path>,
+        rows: u32,
+        cols: u32,
+        split: Split,
+        seed: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut order: Vec<usize> = (0..self.num_images(split)).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(seed));
+        order.truncate((rows * cols) as usize);
+        self.render_sheet(split, &order, rows, cols)?.save(path)?;
+        Ok(())
+    }
+
+    /// Same as `contact_sheet`, showing the images at `indices` (such as those flagged by `channel_outliers`) in
+    /// order, `cols` to a row. Fails if an index is past the end of the split
+    pub fn contact_sheet_of(
+        &self,
+        path: impl AsRef<Path>,
+        split: Split,
+        indices: &[usize],
+        cols: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        let cols = cols.max(1);
+        let rows = (indices.len() as u32).div_ceil(cols).max(1);
+        self.render_sheet(split, indices, rows, cols)?.save(path)?;
+        Ok(())
+    }
+
+    fn num_images(&self, split: Split) -> usize {
+        match split {
+            Split::Train => self.0.len() / 3072,
+            Split::Test => self.2.len() / 3072,
+        }
+    }
+
+    /// Lays out the images at `indices` on a white `rows x cols` grid, each framed in its class's color
+    fn render_sheet(
+        &self,
+        split: Split,
+        indices: &[usize],
+        rows: u32,
+        cols: u32,
+    ) -> Result<RgbImage, Box<dyn Error>> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        let width = label_width(data, labels);
+        let num_images = self.num_images(split);
+        if let Some(&i) = indices.iter().find(|&&i| i >= num_images) {
+            return Err(format!(
+                "Image {} is out of range for the {:?} split, which has {} images",
+                i, split, num_images
+            )
+            .into());
+        }
+
+        let cell = 32 + 2 * SHEET_BORDER;
+        let mut sheet = RgbImage::from_pixel(cols * cell, rows * cell, Rgb([255, 255, 255]));
+        for (position, &i) in indices.iter().take((rows * cols) as usize).enumerate() {
+            let (x, y) = (position as u32 % cols * cell, position as u32 / cols * cell);
+            let color =
+                Rgb(CLASS_COLORS[class_index(labels, width, i) as usize % CLASS_COLORS.len()]);
+            for dy in 0..cell {
+                for dx in 0..cell {
+                    sheet.put_pixel(x + dx, y + dy, color);
+                }
+            }
+            image::imageops::replace(
+                &mut sheet,
+                &record_to_image(&data[i * 3072..(i + 1) * 3072]),
+                (x + SHEET_BORDER) as i64,
+                (y + SHEET_BORDER) as i64,
+            );
+        }
+        Ok(sheet)
+    }
+
+    /// Writes every image of a split into `dir` as `{index}.png`, embedding each one's class in the PNG metadata
+    /// (see `record_to_png`) when `embed_labels` is set
+    pub fn export_pngs(
+        &self,
+        dir: impl AsRef<Path>,
+        split: Split,
+        embed_labels: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let classes = self.label_indices(split);
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        for (i, (record, &class)) in data.chunks_exact(3072).zip(classes.iter()).enumerate() {
+            let png = record_to_png(record, Some(class).filter(|_| embed_labels))?;
+            std::fs::write(dir.join(format!("{}.png", i)), png)?;
+        }
+        Ok(())
+    }
+
+    /// Exports the test images that `predictions` (one predicted class index per test image) gets wrong into
+    /// `dir` for error analysis: each as `{index}.png` with its true class embedded in the PNG metadata, listed
+    /// with its true and predicted class in `errors.tsv` and in an `index.html` that can be browsed directly.
+    /// Returns the indices of the misclassified images
+    pub fn review_errors(
+        &self,
+        predictions: &[u8],
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let classes = self.label_indices(Split::Test);
+        if predictions.len() != classes.len() {
+            return Err(format!(
+                "Got {} predictions for {} test images",
+                predictions.len(),
+                classes.len()
+            )
+            .into());
+        }
+        let num_classes = self.num_classes();
+        if let Some(class) = predictions
+            .iter()
+            .find(|&&class| class as usize >= num_classes)
+        {
+            return Err(format!(
+                "Predicted class {} is not one of the {} classes",
+                class, num_classes
+            )
+            .into());
+        }
+
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let mut tsv = std::io::BufWriter::new(std::fs::File::create(dir.join("errors.tsv"))?);
+        let mut html = std::io::BufWriter::new(std::fs::File::create(dir.join("index.html"))?);
+        writeln!(tsv, "index\ttrue\tpredicted")?;
+        writeln!(
+            html,
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Misclassified test images</title>\
+             </head><body><table>\n<tr><th>index</th><th>image</th><th>true</th><th>predicted</th></tr>"
+        )?;
+
+        let mut errors = Vec::new();
+        for (i, (&class, &predicted)) in classes.iter().zip(predictions).enumerate() {
+            if class == predicted {
+                continue;
+            }
+            let (name, predicted_name) = (class_name(class), class_name(predicted));
+            let png = record_to_png(&self.2[i * 3072..(i + 1) * 3072], Some(class))?;
+            std::fs::write(dir.join(format!("{}.png", i)), png)?;
+            writeln!(tsv, "{}\t{}\t{}", i, name, predicted_name)?;
+            writeln!(
+                html,
+                "<tr><td>{0}</td><td><img src=\"{0}.png\" width=\"96\" height=\"96\" \
+                 style=\"image-rendering: pixelated\"></td><td>{1}</td><td>{2}</td></tr>",
+                i, name, predicted_name
+            )?;
+            errors.push(i);
+        }
+        writeln!(
+            html,
+            "</table><p>{} of {} misclassified</p></body></html>",
+            errors.len(),
+            classes.len()
+        )?;
+
+        tsv.flush()?;
+        html.flush()?;
+        Ok(errors)
+    }
+
+    /// Writes `sprite.png` and `metadata.tsv` into `dir` for the TensorBoard Embedding Projector, covering the
+    /// training images followed by the testing images. Embeddings must be given in the same order, with a
+    /// single image size of `32 x 32`
+    pub fn export_embedding_projector(&self, dir: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
+        let count = (self.0.len() + self.2.len()) / 3072;
+        let side = (count as f64).sqrt().ceil() as u32;
+        let mut sprite = RgbImage::new(side.max(1) * 32, side.max(1) * 32);
+        let mut metadata =
+            std::io::BufWriter::new(std::fs::File::create(dir.join("metadata.tsv"))?);
+        writeln!(metadata, "index\tsplit\tlabel")?;
+
+        let splits = [("train", &self.0, &self.1), ("test", &self.2, &self.3)];
+        let mut position = 0;
+        for (split, data, labels) in splits {
+            let width = label_width(data, labels);
+            for (i, record) in data.chunks_exact(3072).enumerate() {
+                let (x, y) = (position % side, position / side);
+                image::imageops::replace(
+                    &mut sprite,
+                    &record_to_image(record),
+                    x as i64 * 32,
+                    y as i64 * 32,
+                );
+                let label = class_name(class_index(labels, width, i));
+                writeln!(metadata, "{}\t{}\t{}", i, split, label)?;
+                position += 1;
+            }
+        }
+
+        metadata.flush()?;
+        sprite.save(dir.join("sprite.png"))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "export_archive")]
+mod archive {
+    use super::record_to_image;
+    use crate::subset::{class_index, label_width};
+    use crate::{class_name, CifarResult};
+    use image::codecs::jpeg::JpegEncoder;
+    use image::codecs::webp::WebPEncoder;
+    use std::error::Error;
+    use std::fs::File;
+    use std::path::Path;
+
+    /// Compressed image format used by `CifarResult::export_archive`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ExportFormat {
+        /// Lossy JPEG with a quality between 1 and 100
+        Jpeg { quality: u8 },
+        /// Lossless WebP
+        WebP,
+    }
+
+    impl ExportFormat {
+        fn extension(&self) -> &'static str {
+            match self {
+                ExportFormat::Jpeg { .. } => "jpg",
+                ExportFormat::WebP => "webp",
+            }
+        }
+
+        fn encode(&self, record: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+            let img = record_to_image(record);
+            let mut buffer = Vec::new();
+            match *self {
+                ExportFormat::Jpeg { quality } => {
+                    JpegEncoder::new_with_quality(&mut buffer, quality.clamp(1, 100))
+                        .encode_image(&img)?
+                }
+                ExportFormat::WebP => {
+                    WebPEncoder::new_lossless(&mut buffer).encode(
+                        img.as_raw(),
+                        32,
+                        32,
+                        image::ExtendedColorType::Rgb8,
+                    )?;
+                }
+            }
+            Ok(buffer)
+        }
+    }
+
+    impl CifarResult {
+        /// Writes every image into a tar archive at `path` as `{train,test}/{class}/{index}.{jpg,webp}`, for
+        /// building web demos where raw PNG export is too large
+        pub fn export_archive(
+            &self,
+            path: impl AsRef<Path>,
+            format: ExportFormat,
+        ) -> Result<(), Box<dyn Error>> {
+            let mut builder = tar::Builder::new(File::create(path)?);
+            for (split, data, labels) in [("train", &self.0, &self.1), ("test", &self.2, &self.3)] {
+                let width = label_width(data, labels);
+                for (i, record) in data.chunks_exact(3072).enumerate() {
+                    let encoded = format.encode(record)?;
+                    let mut header = tar::Header::new_gnu();
+                    header.set_size(encoded.len() as u64);
+                    header.set_mode(0o644);
+                    header.set_cksum();
+                    let name = format!(
+                        "{}/{}/{}.{}",
+                        split,
+                        class_name(class_index(labels, width, i)),
+                        i,
+                        format.extension()
+                    );
+                    builder.append_data(&mut header, name, &encoded[..])?;
+                }
+            }
+            builder.into_inner()?;
+            Ok(())
+        }
+    }
+}
+#[cfg(feature = "export_archive")]
+pub use archive::ExportFormat;
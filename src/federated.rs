@@ -0,0 +1,161 @@
+use crate::subset::{select, ShardStrategy};
+use crate::{CifarResult, Split};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// `(data, labels)` of one client's records, laid out like the split they were taken from
+pub type ClientData = (Vec<u8>, Vec<u8>);
+
+/// How `CifarResult::federated` distributes records among clients, following the setups common in federated
+/// learning papers
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FederatedPartition {
+    /// Records are shuffled and dealt out evenly, so every client sees the same label distribution
+    Iid,
+    /// Each class is split among the clients in proportions drawn from a symmetric Dirichlet distribution with
+    /// concentration `alpha`; smaller values give each client fewer classes (Hsu et al., 2019)
+    Dirichlet { alpha: f64 },
+    /// Records are sorted by label and cut into equal shards, and each client receives `shards_per_client` of
+    /// them at random, leaving most clients with only that many classes (McMahan et al., 2017)
+    Shards { shards_per_client: usize },
+}
+
+/// Draws from a Gamma distribution with shape `shape` and unit scale (Marsaglia and Tsang's method)
+fn gamma<R: Rng + ?Sized>(rng: &mut R, shape: f64) -> f64 {
+    if shape < 1.0 {
+        // Boost the shape above one, then scale back down
+        return gamma(rng, shape + 1.0) * rng.gen::<f64>().powf(1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        // Standard normal by the Box-Muller transform
+        let (u1, u2): (f64, f64) = (1.0 - rng.gen::<f64>(), rng.gen());
+        let x = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        let v = (1.0 + c * x).powi(3);
+        if v > 0.0 && (1.0 - rng.gen::<f64>()).ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+/// Cuts `indices` into consecutive parts with sizes proportional to `weights`
+fn split_by_weights(indices: &[usize], weights: &[f64]) -> Vec<Vec<usize>> {
+    let total: f64 = weights.iter().sum();
+    let mut parts = Vec::with_capacity(weights.len());
+    let (mut start, mut cumulative) = (0, 0.0);
+    for weight in weights {
+        cumulative += weight;
+        let end = match total > 0.0 {
+            true => {
+                ((cumulative / total * indices.len() as f64).round() as usize).min(indices.len())
+            }
+            false => indices.len(),
+        };
+        parts.push(indices[start..end.max(start)].to_vec());
+        start = end.max(start);
+    }
+    parts
+}
+
+impl CifarResult {
+    /// Partitions a split into `clients` disjoint sets of record indices, determined by `seed`. Every record is
+    /// assigned to exactly one client, and each client's indices are in ascending order
+    pub fn federated_indices(
+        &self,
+        split: Split,
+        clients: usize,
+        partition: FederatedPartition,
+        seed: u64,
+    ) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+        if clients == 0 {
+            return Err("Can't partition a split among zero clients".into());
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let num_records = match split {
+            Split::Train => self.0.len() / 3072,
+            Split::Test => self.2.len() / 3072,
+        };
+
+        let mut assigned: Vec<Vec<usize>> = vec![vec![]; clients];
+        match partition {
+            FederatedPartition::Iid => {
+                let mut order: Vec<usize> = (0..num_records).collect();
+                order.shuffle(&mut rng);
+                for (client, part) in assigned.iter_mut().enumerate() {
+                    let share = crate::subset::shard_indices(
+                        num_records,
+                        client,
+                        clients,
+                        ShardStrategy::Contiguous,
+                    );
+                    part.extend(share.into_iter().map(|i| order[i]));
+                }
+            }
+            FederatedPartition::Dirichlet { alpha } => {
+                if alpha.is_nan() || alpha <= 0.0 {
+                    return Err(
+                        format!("Dirichlet concentration must be positive, not {}", alpha).into(),
+                    );
+                }
+                for mut members in self.class_indices(split) {
+                    members.shuffle(&mut rng);
+                    let weights: Vec<f64> = (0..clients).map(|_| gamma(&mut rng, alpha)).collect();
+                    for (part, share) in assigned
+                        .iter_mut()
+                        .zip(split_by_weights(&members, &weights))
+                    {
+                        part.extend(share);
+                    }
+                }
+            }
+            FederatedPartition::Shards { shards_per_client } => {
+                if shards_per_client == 0 {
+                    return Err("Each client needs at least one shard".into());
+                }
+                let sorted: Vec<usize> = self.class_indices(split).concat();
+                let num_shards = clients * shards_per_client;
+                let mut shards: Vec<usize> = (0..num_shards).collect();
+                shards.shuffle(&mut rng);
+                for (k, &shard) in shards.iter().enumerate() {
+                    let range = crate::subset::shard_indices(
+                        sorted.len(),
+                        shard,
+                        num_shards,
+                        ShardStrategy::Contiguous,
+                    );
+                    assigned[k / shards_per_client].extend(range.into_iter().map(|i| sorted[i]));
+                }
+            }
+        }
+
+        for part in assigned.iter_mut() {
+            part.sort_unstable();
+        }
+        Ok(assigned)
+    }
+
+    /// Same as `federated_indices`, returning each client's records as `(data, labels)` copied out of the split
+    pub fn federated(
+        &self,
+        split: Split,
+        clients: usize,
+        partition: FederatedPartition,
+        seed: u64,
+    ) -> Result<Vec<ClientData>, Box<dyn Error>> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        Ok(self
+            .federated_indices(split, clients, partition, seed)?
+            .iter()
+            .map(|indices| select(data, labels, indices))
+            .collect())
+    }
+}
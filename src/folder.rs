@@ -0,0 +1,164 @@
+use crate::{CifarData, CifarResult, PixelPlaneOrder, ResizeFilter};
+use image::imageops::FilterType;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Loads a custom dataset from directories of PNG or JPEG images in the `ImageFolder` layout, with one
+/// subdirectory per class (`root/cat/001.png`, `root/dog/002.jpg`, ...), so it can go through the same pipeline as
+/// the CIFAR-10 binaries. Classes are numbered in the alphabetical order of their directory names, like
+/// torchvision's `ImageFolder`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageFolder {
+    train_root: PathBuf,
+    test_root: Option<PathBuf>,
+    size: u32,
+    filter: ResizeFilter,
+}
+
+impl ImageFolder {
+    /// Creates a loader for the training images under `train_root`, resizing every image to `32 x 32` by default
+    pub fn new(train_root: impl Into<PathBuf>) -> Self {
+        ImageFolder {
+            train_root: train_root.into(),
+            test_root: None,
+            size: 32,
+            filter: ResizeFilter::Bilinear,
+        }
+    }
+
+    /// Set a directory of testing images in the same layout. Its classes must all appear in the training images
+    pub fn test_root(mut self, test_root: impl Into<PathBuf>) -> Self {
+        self.test_root = Some(test_root.into());
+        self
+    }
+
+    /// Set the side length images are resized to (default 32), ignoring their aspect ratio. Every `CifarResult`
+    /// API expects `3 x 32 x 32` records, so other sizes can only be loaded with `build_typed`
+    pub fn size(mut self, size: u32) -> Self {
+        assert!(size > 0, "Images must be at least 1 x 1");
+        self.size = size;
+        self
+    }
+
+    /// Set the interpolation used when resizing (default bilinear)
+    pub fn filter(mut self, filter: ResizeFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Names of the classes, in the order of their indices
+    pub fn classes(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut classes = Vec::new();
+        for entry in std::fs::read_dir(&self.train_root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                classes.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        classes.sort();
+        if classes.len() > 256 {
+            return Err(format!("{} classes don't fit in u8 labels", classes.len()).into());
+        }
+        Ok(classes)
+    }
+
+    /// Decodes and resizes every image, returning them as flat `3 x 32 x 32` records (R, G and B planes in that
+    /// order) with class index labels, sorted by class and then file name. Without a test root the testing split is
+    /// empty. Fails before loading anything if another size was set
+    pub fn build(&self) -> Result<CifarResult, Box<dyn Error>> {
+        if self.size != 32 {
+            return Err(format!(
+                "Images are resized to {0} x {0}, which only build_typed::<{0}>() can load",
+                self.size
+            )
+            .into());
+        }
+        self.load_splits()
+    }
+
+    /// Same as `build`, with the images as `3 x N x N` arrays. Fails before loading anything if `N` doesn't match
+    /// the size set with `size` (or 32 if none was set)
+    pub fn build_typed<const N: usize>(&self) -> Result<CifarData<N>, Box<dyn Error>> {
+        if self.size as usize != N {
+            return Err(format!(
+                "Images are resized to {0} x {0}, not {1} x {1}",
+                self.size, N
+            )
+            .into());
+        }
+        let CifarResult(train_data, train_labels, test_data, test_labels) = self.load_splits()?;
+        CifarData::from_flat(&train_data, &train_labels, &test_data, &test_labels)
+    }
+
+    fn load_splits(&self) -> Result<CifarResult, Box<dyn Error>> {
+        let classes = self.classes()?;
+        let (train_data, train_labels) = self.load(&self.train_root, &classes)?;
+        let (test_data, test_labels) = match &self.test_root {
+            Some(test_root) => {
+                for entry in std::fs::read_dir(test_root)? {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if entry.file_type()?.is_dir() && !classes.contains(&name) {
+                        return Err(format!("Test class {} has no training images", name).into());
+                    }
+                }
+                self.load(test_root, &classes)?
+            }
+            None => (vec![], vec![]),
+        };
+        Ok(CifarResult(
+            train_data,
+            train_labels,
+            test_data,
+            test_labels,
+        ))
+    }
+
+    fn load(&self, root: &Path, classes: &[String]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+        let filter = match self.filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Bilinear => FilterType::Triangle,
+        };
+        let mut data = Vec::new();
+        let mut labels = Vec::new();
+        for (class, name) in classes.iter().enumerate() {
+            let dir = root.join(name);
+            if !dir.is_dir() {
+                continue;
+            }
+            let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()?;
+            paths.retain(|path| is_image(path));
+            paths.sort();
+            for path in paths {
+                let image = image::open(&path)
+                    .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?
+                    .to_rgb8();
+                let image = match image.dimensions() == (self.size, self.size) {
+                    true => image,
+                    false => image::imageops::resize(&image, self.size, self.size, filter),
+                };
+                data.extend(PixelPlaneOrder::InterleavedRgb.convert_sized(
+                    image.as_raw(),
+                    self.size as usize,
+                    PixelPlaneOrder::PlanarRgb,
+                ));
+                labels.push(class as u8);
+            }
+        }
+        Ok((data, labels))
+    }
+}
+
+/// Whether a file is one of the image formats that can be decoded, going by its extension
+fn is_image(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| {
+                ["png", "jpg", "jpeg"].contains(&extension.to_ascii_lowercase().as_str())
+            })
+            .unwrap_or(false)
+}
@@ -0,0 +1,22 @@
+use crate::{Cifar10, CifarResult};
+use std::error::Error;
+use std::sync::{Arc, OnceLock};
+
+static GLOBAL: OnceLock<Result<Arc<CifarResult>, String>> = OnceLock::new();
+
+impl Cifar10 {
+    /// Returns a handle to a process-wide copy of the dataset built with the default options, so that test
+    /// suites and benchmarks calling into several modules only load it once
+    pub fn global() -> Result<Arc<CifarResult>, Box<dyn Error>> {
+        Cifar10::default().build_global()
+    }
+
+    /// Returns a handle to the process-wide copy of the dataset, building it with these options if this is the
+    /// first call. Later calls share the first result (including a failure), whatever their options
+    pub fn build_global(self) -> Result<Arc<CifarResult>, Box<dyn Error>> {
+        GLOBAL
+            .get_or_init(|| self.build().map(Arc::new).map_err(|e| e.to_string()))
+            .clone()
+            .map_err(|e| e.into())
+    }
+}
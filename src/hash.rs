@@ -0,0 +1,142 @@
+#[cfg(feature = "export")]
+use crate::export::record_to_image;
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+use crate::ndarray::{ArrayBase, Data, Ix3};
+use crate::{CifarResult, Split};
+#[cfg(feature = "export")]
+use std::error::Error;
+#[cfg(feature = "export")]
+use std::path::Path;
+
+/// A train/test pair of images whose perceptual hashes differ by at most the requested number of bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicatePair {
+    pub train_index: usize,
+    pub test_index: usize,
+    pub distance: u32,
+}
+
+/// Computes a 64-bit average hash of a single `3 x 32 x 32` record
+pub fn perceptual_hash(record: &[u8]) -> u64 {
+    // Collapse to luminance and sum each 4x4 block into an 8x8 thumbnail
+    let mut blocks = [0u32; 64];
+    for y in 0..32 {
+        for x in 0..32 {
+            let i = y * 32 + x;
+            let luma = 299 * record[i] as u32
+                + 587 * record[1024 + i] as u32
+                + 114 * record[2048 + i] as u32;
+            blocks[(y / 4) * 8 + x / 4] += luma;
+        }
+    }
+    let mean = blocks.iter().sum::<u32>() / 64;
+
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, &block)| block > mean)
+        .fold(0u64, |hash, (i, _)| hash | 1 << i)
+}
+
+/// Images which can be checked against the dataset with `CifarResult::contains_image`
+pub trait AsRecord {
+    /// Returns the image as a record's `3 x 32 x 32` pixels (R, G and B planes in that order)
+    fn to_record(&self) -> Vec<u8>;
+}
+
+impl AsRecord for [u8] {
+    fn to_record(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl AsRecord for Vec<u8> {
+    fn to_record(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+#[cfg(feature = "export")]
+impl AsRecord for image::RgbImage {
+    fn to_record(&self) -> Vec<u8> {
+        crate::export::image_to_record(self)
+    }
+}
+
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+impl<S: Data<Elem = u8>> AsRecord for ArrayBase<S, Ix3> {
+    fn to_record(&self) -> Vec<u8> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl CifarResult {
+    /// Returns the split and index of a record whose pixels exactly match `image`, so that collected evaluation
+    /// images can be checked for accidental reuse of CIFAR-10 images
+    pub fn contains_image<I: AsRecord + ?Sized>(&self, image: &I) -> Option<(Split, usize)> {
+        let record = image.to_record();
+        if record.len() != 3072 {
+            return None;
+        }
+        let find = |data: &[u8]| data.chunks_exact(3072).position(|r| r == record.as_slice());
+
+        find(&self.0)
+            .map(|i| (Split::Train, i))
+            .or_else(|| find(&self.2).map(|i| (Split::Test, i)))
+    }
+
+    /// Finds train/test pairs whose perceptual hashes are within `max_distance` bits of each other
+    pub fn near_duplicates(&self, max_distance: u32) -> Vec<DuplicatePair> {
+        let train: Vec<u64> = self.0.chunks_exact(3072).map(perceptual_hash).collect();
+        let test: Vec<u64> = self.2.chunks_exact(3072).map(perceptual_hash).collect();
+
+        let mut pairs = Vec::new();
+        for (train_index, train_hash) in train.iter().enumerate() {
+            for (test_index, test_hash) in test.iter().enumerate() {
+                let distance = (train_hash ^ test_hash).count_ones();
+                if distance <= max_distance {
+                    pairs.push(DuplicatePair {
+                        train_index,
+                        test_index,
+                        distance,
+                    });
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Writes each pair as a side-by-side `64 x 32` PNG (train on the left, test on the right) into `dir`
+    #[cfg(feature = "export")]
+    pub fn export_duplicates(
+        &self,
+        pairs: &[DuplicatePair],
+        dir: impl AsRef<Path>,
+    ) -> Result<(), Box<dyn Error>> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        for pair in pairs {
+            let train = &self.0[pair.train_index * 3072..(pair.train_index + 1) * 3072];
+            let test = &self.2[pair.test_index * 3072..(pair.test_index + 1) * 3072];
+
+            let mut img = image::RgbImage::new(64, 32);
+            image::imageops::replace(&mut img, &record_to_image(train), 0, 0);
+            image::imageops::replace(&mut img, &record_to_image(test), 32, 0);
+            img.save(dir.join(format!(
+                "train_{}_test_{}.png",
+                pair.train_index, pair.test_index
+            )))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{CifarResult, Split, CLASS_NAMES};
+use std::error::Error;
+
+/// A grouping of the ten classes into superclasses, mirroring the coarse labels of CIFAR-100 for experiments on
+/// hierarchical classification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Superclasses {
+    coarse: [u8; 10],
+    count: usize,
+}
+
+impl Superclasses {
+    /// Groups the classes so that superclass `n` holds the classes of `groups[n]`. Every class must be in exactly
+    /// one group
+    pub fn new(groups: &[&[u8]]) -> Result<Superclasses, Box<dyn Error>> {
+        let mut coarse = [None; 10];
+        for (superclass, group) in groups.iter().enumerate() {
+            for &class in group.iter() {
+                match coarse.get_mut(class as usize) {
+                    Some(slot @ None) => *slot = Some(superclass as u8),
+                    Some(Some(_)) => {
+                        return Err(format!(
+                            "{} is in more than one group",
+                            CLASS_NAMES[class as usize]
+                        )
+                        .into())
+                    }
+                    None => return Err(format!("Class {} is not a CIFAR-10 class", class).into()),
+                }
+            }
+        }
+        let mut grouped = [0; 10];
+        for (class, superclass) in coarse.iter().enumerate() {
+            grouped[class] =
+                superclass.ok_or_else(|| format!("{} is in no group", CLASS_NAMES[class]))?;
+        }
+        Ok(Superclasses {
+            coarse: grouped,
+            count: groups.len(),
+        })
+    }
+
+    /// Vehicles (airplane, automobile, ship, truck) as superclass 0 and animals as superclass 1
+    pub fn vehicles_animals() -> Superclasses {
+        Superclasses::new(&[&[0, 1, 8, 9], &[2, 3, 4, 5, 6, 7]])
+            .expect("Every class is grouped once")
+    }
+
+    /// Number of superclasses
+    pub fn num_superclasses(&self) -> usize {
+        self.count
+    }
+
+    /// Superclass of a class
+    pub fn superclass(&self, class: u8) -> u8 {
+        self.coarse[class as usize]
+    }
+}
+
+impl CifarResult {
+    /// Returns `(coarse, fine)` one-hot labels for every record of a split: `coarse` has one row of
+    /// `superclasses.num_superclasses()` values per record and `fine` one row of 10. Fails if a label isn't one of
+    /// the ten CIFAR-10 classes, as after `Cifar10::remap_labels`
+    pub fn hierarchical_labels(
+        &self,
+        split: Split,
+        superclasses: &Superclasses,
+    ) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+        let classes = self.label_indices(split);
+        let width = superclasses.num_superclasses();
+        let mut coarse = vec![0; classes.len() * width];
+        let mut fine = vec![0; classes.len() * 10];
+        for (i, &class) in classes.iter().enumerate() {
+            check_class(class)?;
+            coarse[i * width + superclasses.superclass(class) as usize] = 1;
+            fine[i * 10 + class as usize] = 1;
+        }
+        Ok((coarse, fine))
+    }
+
+    /// Returns the indices of the records of each superclass within a split, in ascending order. Fails if a label
+    /// isn't one of the ten CIFAR-10 classes
+    pub fn superclass_indices(
+        &self,
+        split: Split,
+        superclasses: &Superclasses,
+    ) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+        let mut indices = vec![vec![]; superclasses.num_superclasses()];
+        for (class, members) in self.class_indices(split).iter().enumerate() {
+            if members.is_empty() {
+                continue;
+            }
+            check_class(class as u8)?;
+            indices[superclasses.superclass(class as u8) as usize].extend(members);
+        }
+        for members in indices.iter_mut() {
+            members.sort_unstable();
+        }
+        Ok(indices)
+    }
+}
+
+/// Superclasses only group the CIFAR-10 classes, so remapped labels past them have none
+fn check_class(class: u8) -> Result<(), Box<dyn Error>> {
+    match (class as usize) < CLASS_NAMES.len() {
+        true => Ok(()),
+        false => Err(format!("Label {} is not one of the ten CIFAR-10 classes", class).into()),
+    }
+}
@@ -0,0 +1,167 @@
+use crate::Cifar10;
+use curl::easy::{Easy, List};
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+
+/// The one operation the crate needs from an HTTP client, so applications can supply their own (with their TLS
+/// settings, auth headers for internal mirrors or middleware) through `Cifar10::http_client`
+pub trait HttpFetch: Send + Sync {
+    /// Fetches `url` with a GET request carrying the given headers (such as an `Authorization` header set with
+    /// `Cifar10::download_auth`), writing the response body to `body` as it arrives. A response with an error
+    /// status must be returned as an error
+    fn fetch(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>>;
+}
+
+impl<T: HttpFetch + ?Sized> HttpFetch for Arc<T> {
+    fn fetch(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>> {
+        (**self).fetch(url, headers, body)
+    }
+}
+
+/// The client used when none is set, making a new curl request for each fetch and following redirects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CurlFetch;
+
+impl HttpFetch for CurlFetch {
+    fn fetch(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut easy = Easy::new();
+        easy.url(url)?;
+        let mut list = List::new();
+        for (name, value) in headers {
+            list.append(&format!("{}: {}", name, value))?;
+        }
+        easy.http_headers(list)?;
+        easy.follow_location(true)?;
+        easy.fail_on_error(true)?;
+        let mut transfer = easy.transfer();
+        // Reporting fewer bytes than were received aborts the transfer
+        transfer.write_function(|data| Ok(body.write_all(data).map_or(0, |_| data.len())))?;
+        transfer.perform()?;
+        Ok(())
+    }
+}
+
+/// Client set with `Cifar10::http_client`
+#[derive(Clone)]
+pub struct HttpClient(pub(crate) Arc<dyn HttpFetch>);
+
+impl fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HttpClient")
+    }
+}
+
+/// Credentials for a protected mirror, set with `Cifar10::download_auth` and sent as an `Authorization` header
+/// with every request. They are left out of `Debug` output and aren't serialized with the configuration
+#[derive(Clone, PartialEq, Eq)]
+pub enum Auth {
+    Bearer(String),
+    Basic { user: String, pass: String },
+}
+
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::Bearer(_) => f.write_str("Bearer(..)"),
+            Auth::Basic { user, .. } => write!(f, "Basic {{ user: {:?}, .. }}", user),
+        }
+    }
+}
+
+impl Auth {
+    /// A bearer token read from the environment variable `var`
+    pub fn bearer_from_env(var: &str) -> Result<Auth, Box<dyn Error>> {
+        Ok(Auth::Bearer(env_var(var)?))
+    }
+
+    /// Basic credentials read from the environment variables `user_var` and `pass_var`
+    pub fn basic_from_env(user_var: &str, pass_var: &str) -> Result<Auth, Box<dyn Error>> {
+        Ok(Auth::Basic {
+            user: env_var(user_var)?,
+            pass: env_var(pass_var)?,
+        })
+    }
+
+    /// Value of the `Authorization` header
+    fn header_value(&self) -> String {
+        match self {
+            Auth::Bearer(token) => format!("Bearer {}", token),
+            Auth::Basic { user, pass } => {
+                format!("Basic {}", base64(format!("{}:{}", user, pass).as_bytes()))
+            }
+        }
+    }
+}
+
+fn env_var(var: &str) -> Result<String, Box<dyn Error>> {
+    std::env::var(var).map_err(|e| format!("Can't read credentials from {}: {}", var, e).into())
+}
+
+/// Standard base64 with padding, as used by basic authentication
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            encoded.push(match i <= chunk.len() {
+                true => ALPHABET[(triple >> (18 - 6 * i) & 63) as usize] as char,
+                false => '=',
+            });
+        }
+    }
+    encoded
+}
+
+/// The client and credentials a configuration fetches with
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Transport<'a> {
+    client: Option<&'a HttpClient>,
+    auth: Option<&'a Auth>,
+}
+
+impl Transport<'_> {
+    /// Fetches `url` into `body` with the configured client, or with curl if none was set
+    pub(crate) fn fetch(&self, url: &str, body: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let authorization = self.auth.map(Auth::header_value);
+        let headers: Vec<(&str, &str)> = authorization
+            .iter()
+            .map(|value| ("Authorization", value.as_str()))
+            .collect();
+        match self.client {
+            Some(client) => client.0.fetch(url, &headers, body),
+            None => CurlFetch.fetch(url, &headers, body),
+        }
+    }
+}
+
+impl Cifar10 {
+    pub(crate) fn transport(&self) -> Transport<'_> {
+        Transport {
+            client: self.http_client.as_ref(),
+            auth: self.download_auth.as_ref(),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::subset::{class_index, label_width, select};
+use crate::{class_name, Cifar10, CifarResult, Split};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+impl CifarResult {
+    /// Writes the index, split, class index and class name of every record to a CSV file, training records
+    /// first, so predictions can be joined against ground truth in spreadsheets or pandas. When the `Cifar10`
+    /// configuration the result was built from is given, each row also gets the source binary and the record's
+    /// position within it
+    pub fn export_labels_csv(
+        &self,
+        path: impl AsRef<Path>,
+        provenance: Option<&Cifar10>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(config) = provenance {
+            if config.train_indices.is_some()
+                || config.test_indices.is_some()
+                || config.filter.is_some()
+            {
+                return Err(
+                    "Provenance is only known for results built without indices or filter_records"
+                        .into(),
+                );
+            }
+        }
+
+        let mut f = std::io::BufWriter::new(std::fs::File::create(path)?);
+        write!(f, "index,split,class,name")?;
+        if provenance.is_some() {
+            write!(f, ",file,index_in_file")?;
+        }
+        writeln!(f)?;
+
+        let splits = [
+            (Split::Train, "train", &self.0, &self.1),
+            (Split::Test, "test", &self.2, &self.3),
+        ];
+        for (split, name, data, labels) in splits {
+            let width = label_width(data, labels);
+            for i in 0..data.len() / 3072 {
+                let class = class_index(labels, width, i);
+                write!(f, "{},{},{},{}", i, name, class, class_name(class))?;
+                if let Some(config) = provenance {
+                    let global_index = match split {
+                        Split::Train => i,
+                        Split::Test => config.num_records_train + i,
+                    };
+                    let record = config.provenance(global_index)?;
+                    write!(f, ",{},{}", record.file, record.index_in_file)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        f.flush()?;
+        Ok(())
+    }
+
+    /// Relabels both splits for a binary task of `class` against all the others, as for ROC and precision-recall
+    /// curves or calibration: each record's label becomes a single byte, 1 for `class` and 0 otherwise. With
+    /// `balance` set to a seed, the negatives of each split are subsampled at random to as many as its positives,
+    /// keeping the records in their original order
+    pub fn one_vs_rest(&self, class: u8, balance: Option<u64>) -> CifarResult {
+        assert!(
+            (class as usize) < self.num_classes(),
+            "Class {} is not one of the {} classes",
+            class,
+            self.num_classes()
+        );
+        let mut splits = Vec::with_capacity(2);
+        for (n, &(data, labels)) in [(&self.0, &self.1), (&self.2, &self.3)].iter().enumerate() {
+            let width = label_width(data, labels);
+            let (positives, negatives): (Vec<usize>, Vec<usize>) =
+                (0..data.len() / 3072).partition(|&i| class_index(labels, width, i) == class);
+            let indices = match balance {
+                None => (0..data.len() / 3072).collect(),
+                Some(seed) => {
+                    // Each split draws its own negatives
+                    let mut rng = StdRng::seed_from_u64(seed ^ n as u64);
+                    let count = positives.len().min(negatives.len());
+                    let mut indices: Vec<usize> =
+                        rand::seq::index::sample(&mut rng, negatives.len(), count)
+                            .into_iter()
+                            .map(|i| negatives[i])
+                            .chain(positives.iter().copied())
+                            .collect();
+                    indices.sort_unstable();
+                    indices
+                }
+            };
+            let (data, _) = select(data, labels, &indices);
+            let binary = indices
+                .iter()
+                .map(|&i| (class_index(labels, width, i) == class) as u8)
+                .collect();
+            splits.push((data, binary));
+        }
+
+        let (test_data, test_labels) = splits.pop().expect("Both splits were relabeled");
+        let (train_data, train_labels) = splits.pop().expect("Both splits were relabeled");
+        CifarResult(train_data, train_labels, test_data, test_labels)
+    }
+}
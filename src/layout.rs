@@ -0,0 +1,63 @@
+/// Order of the 3072 pixel bytes in a record. Every value is a single byte, so byte order never comes into it;
+/// what matters is how channels and positions are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelPlaneOrder {
+    /// All 1024 red values, then all green, then all blue, each plane row-major (`3 x 32 x 32`, channels
+    /// first). This is the layout of the CIFAR-10 binaries and of every record this crate returns
+    PlanarRgb,
+    /// Red, green and blue interleaved per pixel, pixels row-major (`32 x 32 x 3`, channels last), as used by
+    /// most image libraries
+    InterleavedRgb,
+}
+
+impl PixelPlaneOrder {
+    /// The layout of the CIFAR-10 binaries
+    pub const CIFAR: PixelPlaneOrder = PixelPlaneOrder::PlanarRgb;
+
+    /// Position of a channel (0 for red, 1 for green, 2 for blue) of the pixel at `(row, col)` in a record
+    pub fn index(&self, channel: usize, row: usize, col: usize) -> usize {
+        assert!(
+            channel < 3 && row < 32 && col < 32,
+            "Pixel is out of bounds"
+        );
+        self.sized_index(channel, row, col, 32)
+    }
+
+    /// The `[r, g, b]` values of the pixel at `(row, col)` in a record
+    pub fn pixel(&self, record: &[u8], row: usize, col: usize) -> [u8; 3] {
+        [0, 1, 2].map(|channel| record[self.index(channel, row, col)])
+    }
+
+    /// Rearranges a record from this layout into `to`
+    pub fn convert(&self, record: &[u8], to: PixelPlaneOrder) -> Vec<u8> {
+        self.convert_sized(record, 32, to)
+    }
+
+    /// Same as `convert` for a `side x side` record, such as those of `ImageFolder` with a custom size
+    pub fn convert_sized(&self, record: &[u8], side: usize, to: PixelPlaneOrder) -> Vec<u8> {
+        assert_eq!(
+            record.len(),
+            3 * side * side,
+            "Record is not {} x {}",
+            side,
+            side
+        );
+        let mut output = vec![0; record.len()];
+        for row in 0..side {
+            for col in 0..side {
+                for channel in 0..3 {
+                    output[to.sized_index(channel, row, col, side)] =
+                        record[self.sized_index(channel, row, col, side)];
+                }
+            }
+        }
+        output
+    }
+
+    fn sized_index(&self, channel: usize, row: usize, col: usize, side: usize) -> usize {
+        match self {
+            PixelPlaneOrder::PlanarRgb => (channel * side + row) * side + col,
+            PixelPlaneOrder::InterleavedRgb => (row * side + col) * 3 + channel,
+        }
+    }
+}
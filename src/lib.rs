@@ -33,63 +33,321 @@ fn main() {
 
 mod test;
 
+pub mod prelude;
+
 #[cfg(any(
     feature = "to_ndarray_016",
     feature = "to_ndarray_015",
     feature = "to_ndarray_014",
     feature = "to_ndarray_013"
 ))]
-pub(self) use ndarray::prelude::*;
+use ndarray::prelude::*;
 
+// Re-exported so downstream crates can use the exact `ndarray` version selected by the `to_ndarray_*` feature
 #[cfg(feature = "to_ndarray_013")]
-use ndarray_013 as ndarray;
+pub use ndarray_013 as ndarray;
 #[cfg(feature = "to_ndarray_014")]
-use ndarray_014 as ndarray;
+pub use ndarray_014 as ndarray;
 #[cfg(feature = "to_ndarray_015")]
-use ndarray_015 as ndarray;
+pub use ndarray_015 as ndarray;
 #[cfg(feature = "to_ndarray_016")]
-use ndarray_016 as ndarray;
+pub use ndarray_016 as ndarray;
+
+#[cfg(any(
+    all(feature = "to_ndarray_016", feature = "to_ndarray_015"),
+    all(feature = "to_ndarray_016", feature = "to_ndarray_014"),
+    all(feature = "to_ndarray_016", feature = "to_ndarray_013"),
+    all(feature = "to_ndarray_015", feature = "to_ndarray_014"),
+    all(feature = "to_ndarray_015", feature = "to_ndarray_013"),
+    all(feature = "to_ndarray_014", feature = "to_ndarray_013")
+))]
+compile_error!(
+    "Only one ndarray version can be enabled at a time: pick a single `to_ndarray_*` (or `ndarray-0_*`) feature"
+);
+
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+mod nd;
 
 use std::error::Error;
-use std::io::Read;
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "archive")]
+use crate::archive::{read_archive, read_archived, ARCHIVE};
+#[cfg(feature = "archive")]
+use std::collections::HashMap;
 
 #[cfg(feature = "download")]
 mod download;
 // Dependencies for download feature
 #[cfg(feature = "download")]
-use crate::download::download_and_extract;
+use crate::download::{download_and_extract, download_file};
+#[cfg(feature = "download")]
+pub use crate::download::{DownloadProgress, InsufficientSpace, ProgressCallback};
 #[cfg(feature = "download")]
 use std::fs::File;
 #[cfg(feature = "download")]
 use tar::Archive;
 
+mod simd;
+
+mod layout;
+pub use crate::layout::PixelPlaneOrder;
+
+mod hash;
+pub use crate::hash::{perceptual_hash, AsRecord, DuplicatePair};
+
+mod subset;
+pub use crate::subset::{RecordFilter, ShardStrategy};
+
+mod federated;
+pub use crate::federated::{ClientData, FederatedPartition};
+
+mod augment;
+pub use crate::augment::{
+    flip_horizontal, flip_vertical, mixup, rot90, Augmentation, EqualizeMode, MultiCrop, Pixel,
+    Transform,
+};
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub use crate::augment::{
+    flip_horizontal_view, flip_vertical_view, gaussian_blur_view, rot90_view, solarize_view,
+};
+
+mod policy;
+pub use crate::policy::{Policy, PolicyOp, PolicyStep};
+
+mod patch;
+pub use crate::patch::PatchMasks;
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub use crate::patch::{patchify, tiles};
+
+#[cfg(feature = "global")]
+mod global;
+
+#[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+mod http;
+#[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+pub use crate::http::{Auth, CurlFetch, HttpClient, HttpFetch};
+
+#[cfg(any(feature = "s3", feature = "gcs"))]
+mod remote;
+#[cfg(any(feature = "s3", feature = "gcs"))]
+use crate::remote::{fetch, is_remote};
+
+mod record;
+pub use crate::record::{records, RecordRef};
+
+mod ordering;
+
+mod spot_check;
+
+mod report;
+pub use crate::report::LoadReport;
+
+mod preprocess;
+use crate::preprocess::normalize_channels;
+pub use crate::preprocess::{Preprocess, CIFAR10_MEAN, CIFAR10_MEAN_U8, CIFAR10_STD};
+
+mod preset;
+pub use crate::preset::Preset;
+
+#[cfg(feature = "zstd")]
+mod compress;
+mod resize;
+pub use crate::resize::{ResizeFilter, ResizedResult};
+
+pub mod testing;
+
+mod typed;
+pub use crate::typed::{CifarData, Image};
+
+mod load;
+pub use crate::load::{
+    cache_dir, load, load_from, load_normalized, load_normalized_from, NormalizedData,
+};
+
+mod widen;
+pub use crate::widen::{PixelScaling, WidePixel, WideResult};
+
+mod labels;
+
+mod synthetic;
+pub use crate::synthetic::SyntheticNoise;
+
+mod ood;
+pub use crate::ood::{OodEvaluation, OodSource};
+
+mod hierarchy;
+pub use crate::hierarchy::Superclasses;
+
+mod stats;
+pub use crate::stats::{ChannelOutlier, ClassStats, SplitStats};
+
+mod noisy;
+pub use crate::noisy::NoisyLabels;
+
+mod soft;
+pub use crate::soft::SoftLabels;
+
+mod errata;
+pub use crate::errata::{CorrectedLabels, Errata};
+
+mod validate;
+pub use crate::validate::{PixelIssue, PixelIssues};
+
+mod merge;
+pub use crate::merge::{concat, ConcatInput};
+
+mod pretext;
+pub use crate::pretext::{Jigsaw, NoiseModel};
+
+mod loader;
+pub use crate::loader::{
+    ContrastiveBatch, ContrastivePairs, FlatF32Batches, ImageArray, ShapedBatches,
+};
+
+mod writer;
+#[cfg(feature = "export")]
+pub use crate::writer::from_images;
+pub use crate::writer::{Cifar10Writer, F32ImportReport, StreamFormat};
+
+#[cfg(feature = "export")]
+mod export;
+#[cfg(feature = "export_archive")]
+pub use crate::export::ExportFormat;
+#[cfg(feature = "export")]
+pub use crate::export::{image_to_record, png_label, record_to_image, record_to_png, CLASS_COLORS};
+
+#[cfg(feature = "image_folder")]
+mod folder;
+#[cfg(feature = "image_folder")]
+pub use crate::folder::ImageFolder;
+
+#[cfg(feature = "serve")]
+mod serve;
+
+#[cfg(feature = "arrow")]
+mod arrow;
+
+/// Names of the CIFAR-10 classes, indexed by label
+pub const CLASS_NAMES: [&str; 10] = [
+    "airplane",
+    "automobile",
+    "bird",
+    "cat",
+    "deer",
+    "dog",
+    "frog",
+    "horse",
+    "ship",
+    "truck",
+];
+
+/// Name of a class, which is `class_{n}` for labels past the ten CIFAR-10 classes (such as those made by
+/// `Cifar10::remap_labels` or `ImageFolder`)
+pub(crate) fn class_name(class: u8) -> std::borrow::Cow<'static, str> {
+    match CLASS_NAMES.get(class as usize) {
+        Some(name) => (*name).into(),
+        None => format!("class_{}", class).into(),
+    }
+}
+
 /// Primary data return, wrapper around tuple `(Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>)`
 pub struct CifarResult(pub Vec<u8>, pub Vec<u8>, pub Vec<u8>, pub Vec<u8>);
 
-/// Data structure used to specify where/how the CIFAR-10 binary data is parsed
-#[derive(Debug)]
+/// One of the two CIFAR-10 data splits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Split {
+    Train,
+    Test,
+}
+
+/// Where a record was read from: its split, source binary and position within that binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordProvenance {
+    pub split: Split,
+    pub file: String,
+    pub index_in_file: usize,
+}
+
+/// Data structure used to specify where/how the CIFAR-10 binary data is parsed. With the `serde` feature it can
+/// be loaded from (and logged to) a config file, where any missing field takes its default value
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Cifar10 {
     base_path: String,
+    path_root: Option<String>,
     cifar_data_path: String,
     encode_one_hot: bool,
+    label_map: Option<Vec<u8>>,
     training_bin_paths: Vec<String>,
     testing_bin_paths: Vec<String>,
     num_records_train: usize,
     num_records_test: usize,
+    train_indices: Option<Vec<usize>>,
+    test_indices: Option<Vec<usize>>,
+    train_take: Option<usize>,
+    test_take: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    filter: Option<RecordFilter>,
+    read_buffer_size: Option<usize>,
+    sequential_read_hint: bool,
+    pipeline: bool,
     as_f32: bool,
     normalize: bool,
+    subtract_mean_image: bool,
+    channel_normalization: Option<([f32; 3], [f32; 3])>,
+    preprocess: Vec<Preprocess>,
+    pixel_scaling: PixelScaling,
+    resize: Option<(usize, ResizeFilter)>,
+    resize_cache: bool,
+    cache_compression: Option<i32>,
+    spot_check: bool,
+    splits: Vec<Split>,
     download_and_extract: bool,
+    extract_to_disk: bool,
     download_url: String,
+    test_batch_url: Option<String>,
+    #[cfg(feature = "download")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    download_progress: Option<ProgressCallback>,
+    #[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    http_client: Option<HttpClient>,
+    #[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    download_auth: Option<Auth>,
 }
 
-impl Cifar10 {
+impl Default for Cifar10 {
     /// Returns the default struct, looking in the "./data/" directory with default binary names
-    pub fn default() -> Self {
+    fn default() -> Self {
         Cifar10 {
             base_path: "data/".into(),
+            path_root: None,
             cifar_data_path: "cifar-10-batches-bin/".into(),
             encode_one_hot: true,
+            label_map: None,
             training_bin_paths: vec![
                 "data_batch_1.bin".into(),
                 "data_batch_2.bin".into(),
@@ -100,19 +358,55 @@ impl Cifar10 {
             testing_bin_paths: vec!["test_batch.bin".into()],
             num_records_train: 50_000,
             num_records_test: 10_000,
+            train_indices: None,
+            test_indices: None,
+            train_take: None,
+            test_take: None,
+            filter: None,
+            read_buffer_size: None,
+            sequential_read_hint: false,
+            pipeline: false,
             as_f32: false,
             normalize: false,
+            subtract_mean_image: false,
+            channel_normalization: None,
+            preprocess: vec![],
+            pixel_scaling: PixelScaling::default(),
+            resize: None,
+            resize_cache: true,
+            cache_compression: None,
+            spot_check: true,
+            splits: vec![Split::Train, Split::Test],
             download_and_extract: false,
+            extract_to_disk: true,
             download_url: "https://www.cs.toronto.edu/~kriz/cifar-10-binary.tar.gz".to_string(),
+            test_batch_url: None,
+            #[cfg(feature = "download")]
+            download_progress: None,
+            #[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+            http_client: None,
+            #[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+            download_auth: None,
         }
     }
+}
 
-    /// Manually set the base path
+impl Cifar10 {
+    /// Manually set the base path, where a leading `~` is expanded to the home directory. With the `s3` or `gcs`
+    /// features, an `s3://bucket/prefix/` or `gs://bucket/prefix/` URL reads the binaries (or the archive, when not
+    /// extracting to disk) straight from a publicly readable bucket
     pub fn base_path(mut self, base_path: impl Into<String>) -> Self {
         self.base_path = base_path.into();
         self
     }
 
+    /// Set the directory that a relative base path is resolved against (default is the current directory), e.g.
+    /// `env!("CARGO_MANIFEST_DIR")` to resolve it relative to a crate rather than wherever it is run from
+    pub fn path_root(mut self, path_root: impl Into<String>) -> Self {
+        self.path_root = Some(path_root.into());
+        self
+    }
+
     /// Manually set the path for the CIFAR-10 data
     pub fn cifar_data_path(mut self, cifar_data_path: impl Into<String>) -> Self {
         self.cifar_data_path = cifar_data_path.into();
@@ -125,18 +419,107 @@ impl Cifar10 {
         self
     }
 
-    /// Choose a custom url from which to download the CIFAR-10 dataset
+    /// Choose whether the compressed archive is extracted to disk (default yes). When disabled, the binaries are
+    /// parsed straight out of the `tar.gz` in the base path, which suits read-only or space-constrained filesystems
+    #[cfg(feature = "archive")]
+    pub fn extract_to_disk(mut self, extract_to_disk: bool) -> Self {
+        self.extract_to_disk = extract_to_disk;
+        self
+    }
+
+    /// Choose a custom url from which to download the CIFAR-10 dataset, which may be an `s3://` or `gs://` URL
+    /// with the matching feature enabled
     pub fn download_url(mut self, download_url: impl Into<String>) -> Self {
         self.download_url = download_url.into();
         self
     }
 
-    /// Choose if the `labels` return is in one-hot format or not (default yes)
+    /// Set the URL of the testing binary on its own. When only the testing split is loaded, `download_and_extract`
+    /// fetches just this file instead of the whole 170MB archive (the archive is gzipped, so it can't be read in
+    /// ranges). Requires a single testing binary
+    #[cfg(feature = "download")]
+    pub fn test_batch_url(mut self, test_batch_url: impl Into<String>) -> Self {
+        self.test_batch_url = Some(test_batch_url.into());
+        self
+    }
+
+    /// Report the progress of downloads to `callback` instead of drawing a progress bar, including the transfer
+    /// speed, the estimated time left and a final summary once each completes. With the `tracing` feature the same
+    /// figures are also emitted as events
+    #[cfg(feature = "download")]
+    pub fn download_progress(
+        mut self,
+        callback: impl Fn(&DownloadProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.download_progress = Some(ProgressCallback(std::sync::Arc::new(callback)));
+        self
+    }
+
+    /// Make downloads and object store reads with `client` rather than a new curl handle per request, e.g. to
+    /// reuse an application's configured client with its TLS settings or auth headers
+    #[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+    pub fn http_client(mut self, client: impl HttpFetch + 'static) -> Self {
+        self.http_client = Some(HttpClient(std::sync::Arc::new(client)));
+        self
+    }
+
+    /// Authenticate downloads and object store reads, for protected mirrors of the dataset. The credentials are sent
+    /// with every request, whether made by curl or by a client set with `http_client`; use
+    /// `Auth::bearer_from_env` or `Auth::basic_from_env` to keep them out of code
+    #[cfg(any(feature = "download", feature = "s3", feature = "gcs"))]
+    pub fn download_auth(mut self, auth: Auth) -> Self {
+        self.download_auth = Some(auth);
+        self
+    }
+
+    /// Choose which splits are loaded (default both). The binaries of other splits aren't read or even required
+    /// to exist, and those splits come back empty. Downloads are only narrowed to the testing split when
+    /// `test_batch_url` is set: otherwise `download_and_extract` still fetches the whole 170MB archive, since HTTP
+    /// range requests can't pick a single binary out of a gzipped tar
+    pub fn splits(mut self, splits: &[Split]) -> Self {
+        self.splits = splits.to_vec();
+        self
+    }
+
+    /// Choose if the `labels` return is in one-hot format or not (default yes). Without it, labels are stored as
+    /// one class index per record, and `CifarResult::one_hot_labels` produces the one-hot encoding on demand
     pub fn encode_one_hot(mut self, encode_one_hot: bool) -> Self {
         self.encode_one_hot = encode_one_hot;
         self
     }
 
+    /// Relabel records as they are loaded, with each `(from, to)` pair giving class `from` the label `to`. Classes
+    /// not mentioned keep their label, and one-hot labels are as wide as the highest label plus one (building fails
+    /// if that leaves a single class). Record filters still see the original labels. Panics if a `from` class is
+    /// not one of the ten CIFAR-10 classes
+    pub fn remap_labels(mut self, pairs: &[(u8, u8)]) -> Self {
+        let mut map = self.label_map.take().unwrap_or_else(|| (0..10).collect());
+        for &(from, to) in pairs {
+            assert!(from < 10, "Class {} is not a CIFAR-10 class", from);
+            map[from as usize] = to;
+        }
+        self.label_map = Some(map);
+        self
+    }
+
+    /// Collapse groups of classes into one label each, e.g. `&[&[0, 1, 8, 9], &[2, 3, 4, 5, 6, 7]]` for vehicles
+    /// and animals. Group `n` gets label `n`, and classes in no group are numbered after the groups in their
+    /// original order. Panics if a class is not one of the ten CIFAR-10 classes
+    pub fn merge_classes(self, groups: &[&[u8]]) -> Self {
+        let mut pairs = Vec::with_capacity(10);
+        for (label, group) in groups.iter().enumerate() {
+            pairs.extend(group.iter().map(|&class| (class, label as u8)));
+        }
+        let mut next = groups.len() as u8;
+        for class in 0..10 {
+            if !groups.iter().any(|group| group.contains(&class)) {
+                pairs.push((class, next));
+                next += 1;
+            }
+        }
+        self.remap_labels(&pairs)
+    }
+
     /// Manually set the path to the training data binaries
     pub fn training_bin_paths(mut self, training_bin_paths: Vec<String>) -> Self {
         self.training_bin_paths = training_bin_paths;
@@ -149,102 +532,984 @@ impl Cifar10 {
         self
     }
 
-    /// Set the number of records in the training set (default 50_000)
+    /// Choose if the `f32` outputs are scaled from `[0, 255]` to `[0, 1]` (default no)
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Choose if the per-pixel mean image of the training split is subtracted from both splits in the `f32`
+    /// outputs, as in the original cuda-convnet preprocessing (default no)
+    pub fn subtract_mean_image(mut self, subtract_mean_image: bool) -> Self {
+        self.subtract_mean_image = subtract_mean_image;
+        self
+    }
+
+    /// Subtract a per-channel mean and divide by a per-channel standard deviation in the `f32` outputs, like
+    /// torchvision's `Normalize`, after mean subtraction and before any `preprocess` steps (default none). The
+    /// constants are usually given for images scaled to `[0, 1]`, such as `CIFAR10_MEAN` and `CIFAR10_STD`, so
+    /// `normalize` should be set
+    pub fn normalize_channels(mut self, mean: [f32; 3], std: [f32; 3]) -> Self {
+        self.channel_normalization = Some((mean, std));
+        self
+    }
+
+    /// Add a preprocessing step to the `f32` outputs, applied to each image in the order the steps were added
+    pub fn preprocess(mut self, step: Preprocess) -> Self {
+        self.preprocess.push(step);
+        self
+    }
+
+    /// Choose how pixels are widened by `build_as` (default unchanged)
+    pub fn pixel_scaling(mut self, pixel_scaling: PixelScaling) -> Self {
+        self.pixel_scaling = pixel_scaling;
+        self
+    }
+
+    /// Resize images to `3 x size x size` with the given filter in `build_resized` (default unchanged)
+    pub fn resize(mut self, size: usize, filter: ResizeFilter) -> Self {
+        self.resize = Some((size, filter));
+        self
+    }
+
+    /// Choose if resized images are cached to disk for later runs (default yes)
+    pub fn resize_cache(mut self, resize_cache: bool) -> Self {
+        self.resize_cache = resize_cache;
+        self
+    }
+
+    /// Compress cached resized images with zstd at the given level (default uncompressed). Compression and
+    /// decompression are split across all available cores
+    #[cfg(feature = "zstd")]
+    pub fn cache_compression(mut self, level: i32) -> Self {
+        self.cache_compression = Some(level);
+        self
+    }
+
+    /// Choose if each of the published binaries is checked as it is read for parsing (default yes): its size must
+    /// match, catching truncation that record counts miss, and its leading labels and pixels must match the
+    /// reference values known for it. This is a spot check rather than a hash of the contents, so corruption past
+    /// the leading records of a binary of the right size goes unnoticed. Custom datasets that reuse the published
+    /// file names should disable this
+    pub fn spot_check(mut self, spot_check: bool) -> Self {
+        self.spot_check = spot_check;
+        self
+    }
+
+    /// Set the number of records in the training binaries (default 50_000). Building fails if the binaries hold a
+    /// different number, so use `take_first` to load fewer
     pub fn num_records_train(mut self, num_records_train: usize) -> Self {
         self.num_records_train = num_records_train;
         self
     }
 
-    /// Set the number of records in the training set (default 10_000)
+    /// Set the number of records in the testing binaries (default 10_000). Building fails if the binaries hold a
+    /// different number, so use `take_first` to load fewer
     pub fn num_records_test(mut self, num_records_test: usize) -> Self {
         self.num_records_test = num_records_test;
         self
     }
 
+    /// Load only the first `n` records of a split
+    pub fn take_first(mut self, split: Split, n: usize) -> Self {
+        match split {
+            Split::Train => self.train_take = Some(n),
+            Split::Test => self.test_take = Some(n),
+        }
+        self
+    }
+
+    /// Load only the records at the given indices of a split (in that order), seeking directly to them instead
+    /// of reading the whole binaries
+    pub fn indices(mut self, split: Split, indices: &[usize]) -> Self {
+        match split {
+            Split::Train => self.train_indices = Some(indices.to_vec()),
+            Split::Test => self.test_indices = Some(indices.to_vec()),
+        }
+        self
+    }
+
+    /// Keep only the records for which `filter` returns true given their pixels and label, deciding as each record
+    /// is parsed so the rejected ones are never added to the output
+    pub fn filter_records(
+        mut self,
+        filter: impl Fn(&[u8; 3072], u8) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(RecordFilter(std::sync::Arc::new(filter)));
+        self
+    }
+
+    /// Read the binaries in chunks of `read_buffer_size` bytes rather than with a single read per file, which can
+    /// help on spinning disks and network filesystems
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = Some(read_buffer_size);
+        self
+    }
+
+    /// Advise the kernel that the binaries will be read sequentially, enlarging its read-ahead (Linux only,
+    /// default no)
+    pub fn sequential_read_hint(mut self, sequential_read_hint: bool) -> Self {
+        self.sequential_read_hint = sequential_read_hint;
+        self
+    }
+
+    /// Parse in pipelined stages on separate threads (default no): each binary is read while the previous one is
+    /// parsed, and when loading whole binaries without `filter_records`, the labels and the pixels of a binary are
+    /// handled on threads of their own. With the archive kept in memory or `indices` set, only the reading is
+    /// overlapped
+    pub fn pipeline(mut self, pipeline: bool) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
     /// Returns the array tuple using the specified options in `Array4<T>` form
     pub fn build(self) -> Result<CifarResult, Box<dyn Error>> {
+        self.build_with_report().map(|(result, _)| result)
+    }
+
+    /// Downloads the archive if requested, reads it into memory when it isn't extracted to disk, and verifies that
+    /// every binary is available. Every build variant goes through this step before parsing anything
+    #[cfg_attr(not(feature = "archive"), allow(unused_variables))]
+    fn prepare(&self, report: &mut LoadReport) -> Result<Option<ArchiveContents>, Box<dyn Error>> {
+        if self.encode_one_hot && self.num_classes() < 2 {
+            // A single one-hot byte per record would be read back as a class index
+            return Err(
+                "One-hot labels need at least two classes; disable encode_one_hot to load a single class".into(),
+            );
+        }
+
         #[cfg(feature = "download")]
-        match self.download_and_extract {
+        match self.download_and_extract && !self.is_remote() {
             false => (),
+            true if self.test_batch_url.is_some() && self.splits == [Split::Test] => {
+                let bin = match &self.testing_bin_paths[..] {
+                    [bin] => bin,
+                    _ => return Err("test_batch_url requires a single testing binary".into()),
+                };
+                let url = self.test_batch_url.as_deref().expect("Checked above");
+                report.download += download_file(
+                    url,
+                    &self.bin_path(bin),
+                    &self.resolved_base_path(),
+                    self.download_progress.as_ref(),
+                    self.transport(),
+                )?;
+            }
             true => {
-                download_and_extract(self.download_url.clone(), self.base_path.clone())?;
+                let (download, extract) = download_and_extract(
+                    self.download_url.clone(),
+                    self.resolved_base_path(),
+                    self.extract_to_disk,
+                    self.download_progress.as_ref(),
+                    self.transport(),
+                )?;
+                report.download += download;
+                report.extract += extract;
+            }
+        }
+
+        #[cfg(feature = "archive")]
+        let archive = match self.extract_to_disk {
+            true => None,
+            false => {
+                let bin_paths: Vec<&String> = self
+                    .split_bins(Split::Train)
+                    .iter()
+                    .chain(self.split_bins(Split::Test))
+                    .collect();
+                let compressed = report.read(|| {
+                    #[cfg(any(feature = "s3", feature = "gcs"))]
+                    if self.is_remote() {
+                        return fetch(&remote::join(&self.base_path, &[ARCHIVE]), self.transport());
+                    }
+                    Ok(std::fs::read(self.resolved_base_path().join(ARCHIVE))?)
+                })?;
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!("extract", bytes = compressed.len()).entered();
+                let start = std::time::Instant::now();
+                let contents = read_archive(&compressed[..], &self.cifar_data_path, &bin_paths)?;
+                report.extract += start.elapsed();
+                Some(contents)
+            }
+        };
+        #[cfg(not(feature = "archive"))]
+        let archive = None;
+
+        self.verify(archive.as_ref())?;
+        Ok(archive)
+    }
+
+    /// Checks that every binary is present, either in the archive or on disk, so that a missing file is reported
+    /// up front and the same way whichever build variant is used
+    fn verify(&self, archive: Option<&ArchiveContents>) -> Result<(), Box<dyn Error>> {
+        if self.is_remote() {
+            return Ok(());
+        }
+        let bin_paths = self
+            .split_bins(Split::Train)
+            .iter()
+            .chain(self.split_bins(Split::Test));
+        let missing: Vec<String> = match archive {
+            #[cfg(feature = "archive")]
+            Some(archive) => bin_paths
+                .filter(|bin| !archive.contains_key(*bin))
+                .map(|bin| format!("{} (in {})", bin, ARCHIVE))
+                .collect(),
+            _ => bin_paths
+                .map(|bin| self.bin_path(bin))
+                .filter(|path| !path.is_file())
+                .map(|path| path.display().to_string())
+                .collect(),
+        };
+        if !missing.is_empty() {
+            return Err(format!(
+                "Missing CIFAR-10 binaries: {}. Check the base path, or enable download_and_extract",
+                missing.join(", ")
+            )
+            .into());
+        }
+
+        let splits = [
+            (
+                Split::Train,
+                &self.training_bin_paths,
+                self.num_records_train,
+                self.train_take,
+            ),
+            (
+                Split::Test,
+                &self.testing_bin_paths,
+                self.num_records_test,
+                self.test_take,
+            ),
+        ];
+        for (split, bin_paths, num_records, take) in splits {
+            if !self.splits.contains(&split) {
+                continue;
+            }
+            let mut total = 0;
+            for bin in bin_paths {
+                let size = match archive {
+                    #[cfg(feature = "archive")]
+                    Some(archive) => archive[bin].len() as u64,
+                    _ => std::fs::metadata(self.bin_path(bin))?.len(),
+                };
+                if size % 3073 != 0 {
+                    return Err(format!(
+                        "{} is {} bytes, which is not a whole number of 3073-byte records",
+                        bin, size
+                    )
+                    .into());
+                }
+                total += size as usize / 3073;
+            }
+            if total != num_records {
+                return Err(format!(
+                    "The {:?} binaries contain {} records, but {} were expected. Use take_first to load only \
+                     the first records of a split",
+                    split, total, num_records
+                )
+                .into());
+            }
+            if let Some(take) = take.filter(|&take| take > num_records) {
+                return Err(format!(
+                    "Can't take the first {} records of the {:?} split, which only has {}",
+                    take, split, num_records
+                )
+                .into());
             }
         }
+        Ok(())
+    }
+
+    /// Returns the data in `Array2<u8>` form with each image flattened to a row of 3072 bytes, avoiding any
+    /// conversion to a wider type. Labels are `[N, 10]` when one-hot encoded and `[N, 1]` otherwise
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn build_as_flat_u8(self) -> Result<FlatResult<u8>, Box<dyn Error>> {
+        let label_width = self.label_width();
+        let CifarResult(train_data, train_labels, test_data, test_labels) = self.build()?;
 
-        let (train_data, train_labels) = get_data(&self, "train")?;
-        let (test_data, test_labels) = get_data(&self, "test")?;
-        Ok(CifarResult(
-            train_data,
-            train_labels,
-            test_data,
-            test_labels,
+        Ok((
+            nd::array((train_data.len() / 3072, 3072), train_data)?,
+            nd::array(
+                (train_labels.len() / label_width, label_width),
+                train_labels,
+            )?,
+            nd::array((test_data.len() / 3072, 3072), test_data)?,
+            nd::array((test_labels.len() / label_width, label_width), test_labels)?,
         ))
     }
-}
 
-fn get_data(config: &Cifar10, dataset: &str) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
-    let mut buffer: Vec<u8> = Vec::new();
+    /// Returns the data as reference-counted `ArcArray`s shaped `[N, 3, 32, 32]` and `[N, 10]` (or `[N, 1]`
+    /// without one-hot encoding), so that threads can clone handles to a single copy of the dataset. Mutating a
+    /// clone copies it first, leaving the others untouched
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn build_shared(self) -> Result<SharedResult, Box<dyn Error>> {
+        let label_width = self.label_width();
+        let CifarResult(train_data, train_labels, test_data, test_labels) = self.build()?;
 
-    let (bin_paths, num_records) = match dataset {
-        "train" => (config.training_bin_paths.clone(), config.num_records_train),
-        "test" => (config.testing_bin_paths.clone(), config.num_records_test),
-        _ => panic!("An unexpected value was passed for which dataset should be parsed"),
-    };
+        Ok((
+            nd::array((train_data.len() / 3072, 3, 32, 32), train_data)?.into_shared(),
+            nd::array(
+                (train_labels.len() / label_width, label_width),
+                train_labels,
+            )?
+            .into_shared(),
+            nd::array((test_data.len() / 3072, 3, 32, 32), test_data)?.into_shared(),
+            nd::array((test_labels.len() / label_width, label_width), test_labels)?.into_shared(),
+        ))
+    }
+
+    /// Returns the data in `Array2<f32>` form with each image flattened to a row of 3072 values, scaled to `[0, 1]`
+    /// if `normalize` is set, centered if `subtract_mean_image` is set, normalized per channel if
+    /// `normalize_channels` is set and then passed through any `preprocess` steps. Each binary is converted as it is
+    /// read, so peak memory stays close to the size of the returned arrays. Labels are `[N, 10]` when one-hot
+    /// encoded and `[N, 1]` otherwise
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn build_as_flat_f32(self) -> Result<FlatResult<f32>, Box<dyn Error>> {
+        self.build_as_flat_f32_with_mean().map(|(result, _)| result)
+    }
+
+    /// Same as `build_as_flat_f32`, also returning the per-pixel mean of the training images (after scaling) so
+    /// that it can be subtracted from images at inference time. The mean is computed whether or not
+    /// `subtract_mean_image` is set
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn build_as_flat_f32_with_mean(
+        self,
+    ) -> Result<(FlatResult<f32>, Array1<f32>), Box<dyn Error>> {
+        let label_width = self.label_width();
+        let ((train_data, train_labels, test_data, test_labels), mean) = self.flat_f32(true)?;
+        let mean = mean.expect("The mean was asked for");
+
+        Ok((
+            (
+                nd::array((train_data.len() / 3072, 3072), train_data)?,
+                nd::array(
+                    (train_labels.len() / label_width, label_width),
+                    train_labels,
+                )?,
+                nd::array((test_data.len() / 3072, 3072), test_data)?,
+                nd::array((test_labels.len() / label_width, label_width), test_labels)?,
+            ),
+            Array::from(mean),
+        ))
+    }
+
+    /// Parses both splits into flat `f32` images and labels, scaled, centered and preprocessed as configured, for
+    /// the `f32` builds with or without `ndarray`. Also returns the per-pixel mean of the training images (after
+    /// scaling) if `with_mean` is set
+    pub(crate) fn flat_f32(&self, with_mean: bool) -> Result<CifarF32, Box<dyn Error>> {
+        let scale = if self.normalize { 1.0 / 255.0 } else { 1.0 };
+        let report = &mut LoadReport::default();
+        let archive = self.prepare(report)?;
+        let (mut train_data, train_labels) =
+            get_data(self, Split::Train, archive.as_ref(), report, |x| {
+                x as f32 * scale
+            })?;
+        let (mut test_data, test_labels) =
+            get_data(self, Split::Test, archive.as_ref(), report, |x| {
+                x as f32 * scale
+            })?;
+
+        let mean = (with_mean || self.subtract_mean_image).then(|| mean_image(&train_data));
+        for image in train_data
+            .chunks_exact_mut(3072)
+            .chain(test_data.chunks_exact_mut(3072))
+        {
+            if let (true, Some(mean)) = (self.subtract_mean_image, &mean) {
+                for (x, m) in image.iter_mut().zip(mean) {
+                    *x -= m;
+                }
+            }
+            if let Some((mean, std)) = self.channel_normalization {
+                normalize_channels(image, mean, std);
+            }
+            for step in &self.preprocess {
+                step.apply(image);
+            }
+        }
+        Ok(((train_data, train_labels, test_data, test_labels), mean))
+    }
+
+    /// Returns the source binary and in-file position of a record, where `global_index` counts through the
+    /// training records first and then the testing records
+    pub fn provenance(&self, global_index: usize) -> Result<RecordProvenance, Box<dyn Error>> {
+        let (split, bin_paths, index) = if global_index < self.num_records_train {
+            (Split::Train, &self.training_bin_paths, global_index)
+        } else {
+            (
+                Split::Test,
+                &self.testing_bin_paths,
+                global_index - self.num_records_train,
+            )
+        };
+
+        let counts = self.record_counts(bin_paths)?;
+        if let Some((file, index_in_file)) = locate(&counts, index) {
+            return Ok(RecordProvenance {
+                split,
+                file: bin_paths[file].clone(),
+                index_in_file,
+            });
+        }
+
+        Err(format!(
+            "Record {} is not contained in any of the binaries",
+            global_index
+        )
+        .into())
+    }
+
+    /// Returns the absolute paths of every binary that will be opened, training binaries first, for checking how
+    /// the base path was resolved
+    pub fn resolved_paths(&self) -> Vec<PathBuf> {
+        self.training_bin_paths
+            .iter()
+            .chain(&self.testing_bin_paths)
+            .map(|bin| self.bin_path(bin))
+            .collect()
+    }
+
+    /// The base path with a leading `~` expanded to the home directory, and joined onto the path root (or the
+    /// current directory) if it is relative
+    fn resolved_base_path(&self) -> PathBuf {
+        let base_path = expand_home(&self.base_path);
+        if base_path.is_absolute() {
+            return base_path;
+        }
+        match &self.path_root {
+            Some(root) => expand_home(root).join(base_path),
+            None => std::env::current_dir()
+                .map(|cwd| cwd.join(&base_path))
+                .unwrap_or(base_path),
+        }
+    }
+
+    /// The binaries of a split, or none if the split isn't loaded
+    pub(crate) fn split_bins(&self, split: Split) -> &[String] {
+        if !self.splits.contains(&split) {
+            return &[];
+        }
+        match split {
+            Split::Train => &self.training_bin_paths,
+            Split::Test => &self.testing_bin_paths,
+        }
+    }
 
-    for bin in &bin_paths {
-        // let full_cifar_path = [
-        //     config.base_path.to_owned(),
-        //     config.cifar_data_path.to_owned(),
-        //     bin.into(),
-        // ]
-        // .join("");
-        let full_cifar_path = Path::new(&config.base_path)
-            .join(&config.cifar_data_path)
-            .join(bin);
-        // println!("{}", full_cifar_path.display());
-
-        let mut f = std::fs::File::open(full_cifar_path)?;
-
-        // read the whole file
-        let mut temp_buffer: Vec<u8> = Vec::new();
-        f.read_to_end(&mut temp_buffer)?;
-        buffer.extend(&temp_buffer);
-        // println!(
-        //     "{}",
-        //     format!("- Done parsing binary file {} to Vec<u8>", bin).as_str()
-        // );
-    }
-
-    let mut labels: Vec<u8> = match config.encode_one_hot {
-        false => vec![0; num_records],
-        true => vec![0; num_records * 10],
+    /// Full path to one of the CIFAR-10 binaries
+    fn bin_path(&self, bin: &str) -> PathBuf {
+        self.resolved_base_path()
+            .join(&self.cifar_data_path)
+            .join(bin)
+    }
+
+    /// Number of classes after any `remap_labels`
+    fn num_classes(&self) -> usize {
+        self.label_map.as_ref().map_or(10, |map| {
+            map.iter().max().map_or(0, |&max| max as usize + 1)
+        })
+    }
+
+    /// Number of label values per record
+    fn label_width(&self) -> usize {
+        match self.encode_one_hot {
+            true => self.num_classes(),
+            false => 1,
+        }
+    }
+
+    /// Whether the base path is an `s3://` or `gs://` URL, in which case binaries are fetched from the object store
+    fn is_remote(&self) -> bool {
+        #[cfg(any(feature = "s3", feature = "gcs"))]
+        return is_remote(&self.base_path);
+        #[cfg(not(any(feature = "s3", feature = "gcs")))]
+        return false;
+    }
+
+    /// Reads one of the binaries in full, from the object store if the base path refers to one, and spot checks it
+    pub(crate) fn read_bin(&self, bin: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        #[cfg(any(feature = "s3", feature = "gcs"))]
+        let contents = match self.is_remote() {
+            true => fetch(
+                &remote::join(&self.base_path, &[&self.cifar_data_path, bin]),
+                self.transport(),
+            )?,
+            false => read_file(self, &self.bin_path(bin))?,
+        };
+        #[cfg(not(any(feature = "s3", feature = "gcs")))]
+        let contents = read_file(self, &self.bin_path(bin))?;
+        spot_check::check_binary(self, bin, &contents)?;
+        Ok(contents)
+    }
+
+    /// Number of records contained in each of the given binaries, based on their size
+    fn record_counts(&self, bin_paths: &[String]) -> Result<Vec<usize>, Box<dyn Error>> {
+        bin_paths
+            .iter()
+            .map(|bin| Ok(std::fs::metadata(self.bin_path(bin))?.len() as usize / 3073))
+            .collect()
+    }
+}
+
+/// Expands a leading `~` into the user's home directory
+fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => rest,
+        _ => return PathBuf::from(path),
     };
-    let mut data: Vec<u8> = Vec::with_capacity(num_records * 3072);
-
-    for num in 0..num_records {
-        // println!("Through image #{}/{}", num, num_records);
-        let base = num * (3073);
-
-        let label = buffer[base];
-        // dbg!(buffer[base]);
-        if label > 9 {
-            panic!(
-                "Image {}: Label is {}, which is inconsistent with the CIFAR-10 scheme",
-                num, label
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(rest.trim_start_matches(&['/', '\\'][..])),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Finds which binary holds the `index`-th record of a split, and the record's position within that binary
+fn locate(counts: &[usize], mut index: usize) -> Option<(usize, usize)> {
+    for (file, &count) in counts.iter().enumerate() {
+        if index < count {
+            return Some((file, index));
+        }
+        index -= count;
+    }
+    None
+}
+
+/// Reads only the requested records, seeking directly to their offsets rather than reading whole binaries
+fn read_indexed(
+    config: &Cifar10,
+    bin_paths: &[String],
+    indices: &[usize],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if config.is_remote() {
+        // Object stores are read whole rather than seeked into
+        let mut contents = Vec::new();
+        for bin in bin_paths {
+            contents.extend(config.read_bin(bin)?);
+        }
+        let mut buffer: Vec<u8> = Vec::with_capacity(indices.len() * 3073);
+        for &i in indices {
+            buffer.extend_from_slice(
+                contents.get(i * 3073..(i + 1) * 3073).ok_or_else(|| {
+                    format!("Record {} is not contained in any of the binaries", i)
+                })?,
             );
         }
+        return Ok(buffer);
+    }
 
-        data.extend(&buffer[base + 1..=base + 3072]);
+    for bin in bin_paths {
+        spot_check::check_file(config, bin)?;
+    }
+    let counts = config.record_counts(bin_paths)?;
+    let mut files: Vec<Option<std::fs::File>> = bin_paths.iter().map(|_| None).collect();
+    let mut buffer: Vec<u8> = Vec::with_capacity(indices.len() * 3073);
+    let mut record = [0u8; 3073];
 
-        match config.encode_one_hot {
-            false => labels[num] = label as u8,
-            true => labels[(num * 10) + (label as usize)] = 1u8,
+    for &index in indices {
+        let (file, index_in_file) = locate(&counts, index)
+            .ok_or_else(|| format!("Record {} is not contained in any of the binaries", index))?;
+        let f = match &mut files[file] {
+            Some(f) => f,
+            slot => slot.insert(std::fs::File::open(config.bin_path(&bin_paths[file]))?),
         };
+        f.seek(SeekFrom::Start((index_in_file * 3073) as u64))?;
+        f.read_exact(&mut record)?;
+        buffer.extend_from_slice(&record);
     }
 
+    Ok(buffer)
+}
+
+/// Reads a whole binary into a buffer sized up front, in reads of `read_buffer_size` bytes if one is set
+fn read_file(config: &Cifar10, path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut f = std::fs::File::open(path)?;
+    #[cfg(target_os = "linux")]
+    if config.sequential_read_hint {
+        use std::os::unix::io::AsRawFd;
+        // Purely advisory, so a failure here is not an error
+        unsafe {
+            libc::posix_fadvise(f.as_raw_fd(), 0, 0, libc::POSIX_FADV_SEQUENTIAL);
+        }
+    }
+
+    let mut buffer = vec![0u8; f.metadata()?.len() as usize];
+    match config.read_buffer_size {
+        Some(read_buffer_size) => {
+            for chunk in buffer.chunks_mut(read_buffer_size.max(1)) {
+                f.read_exact(chunk)?;
+            }
+        }
+        None => f.read_exact(&mut buffer)?,
+    }
+    Ok(buffer)
+}
+
+/// Binaries read straight out of the compressed archive, keyed by file name
+#[cfg(feature = "archive")]
+type ArchiveContents = HashMap<String, Vec<u8>>;
+#[cfg(not(feature = "archive"))]
+type ArchiveContents = std::convert::Infallible;
+
+/// Flat `f32` images and labels of both splits, organized as `(train_data, train_labels, test_data, test_labels)`,
+/// followed by the mean image if it was computed
+type CifarF32 = ((Vec<f32>, Vec<f32>, Vec<f32>, Vec<f32>), Option<Vec<f32>>);
+
+/// Number of records that will be parsed from a split
+fn split_len(config: &Cifar10, split: Split) -> usize {
+    let (indices, take, num_records) = match split {
+        Split::Train => (
+            &config.train_indices,
+            config.train_take,
+            config.num_records_train,
+        ),
+        Split::Test => (
+            &config.test_indices,
+            config.test_take,
+            config.num_records_test,
+        ),
+    };
+    match indices {
+        _ if !config.splits.contains(&split) => 0,
+        Some(indices) => indices.len(),
+        None => take.unwrap_or(num_records),
+    }
+}
+
+/// Parses every record of a split in order, handing its label and pixels to `f`. Whole binaries are read one at
+/// a time, so at most a single binary is held in memory alongside the output
+fn for_each_record(
+    config: &Cifar10,
+    split: Split,
+    archive: Option<&ArchiveContents>,
+    report: &mut LoadReport,
+    mut f: impl FnMut(u8, &[u8]),
+) -> Result<(), Box<dyn Error>> {
+    let bin_paths = config.split_bins(split);
+    let indices = match split {
+        Split::Train => &config.train_indices,
+        Split::Test => &config.test_indices,
+    };
+    let num_records = split_len(config, split);
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("parse", ?split, num_records).entered();
+    #[cfg(feature = "tracing")]
+    let (start, bytes_read) = (std::time::Instant::now(), report.bytes_read);
+
+    let mut num = 0;
+    let mut visit = |buffer: &[u8]| {
+        for record in buffer.chunks_exact(3073).take(num_records - num) {
+            let label = record[0];
+            if label > 9 {
+                panic!(
+                    "Image {}: Label is {}, which is inconsistent with the CIFAR-10 scheme",
+                    num, label
+                );
+            }
+            f(label, &record[1..]);
+            num += 1;
+        }
+    };
+
+    match (archive, indices) {
+        #[cfg(feature = "archive")]
+        (Some(archive), Some(indices)) => {
+            let archived = read_archived(config, archive, bin_paths)?;
+            report.decode(|| -> Result<(), Box<dyn Error>> {
+                for &i in indices {
+                    visit(archived.get(i * 3073..(i + 1) * 3073).ok_or_else(|| {
+                        format!("Record {} is not contained in any of the binaries", i)
+                    })?);
+                }
+                Ok(())
+            })?;
+        }
+        #[cfg(feature = "archive")]
+        (Some(archive), None) => {
+            let archived = read_archived(config, archive, bin_paths)?;
+            report.decode(|| visit(&archived));
+        }
+        (_, Some(indices)) => {
+            let buffer = report.read(|| read_indexed(config, bin_paths, indices))?;
+            report.decode(|| visit(&buffer));
+        }
+        (_, None) if config.pipeline => {
+            // Read the next binary on a separate thread while the current one is being parsed
+            let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+            std::thread::scope(|scope| -> Result<(), Box<dyn Error>> {
+                scope.spawn(move || {
+                    for bin in bin_paths {
+                        let start = std::time::Instant::now();
+                        let buffer = config
+                            .read_bin(bin)
+                            .map(|buffer| (buffer, start.elapsed()))
+                            .map_err(|e| e.to_string());
+                        let failed = buffer.is_err();
+                        if sender.send(buffer).is_err() || failed {
+                            break;
+                        }
+                    }
+                });
+                for buffer in receiver {
+                    let (buffer, elapsed) = buffer?;
+                    let buffer = report.read(|| Ok(buffer))?;
+                    report.read += elapsed;
+                    report.decode(|| visit(&buffer));
+                }
+                Ok(())
+            })?;
+        }
+        (_, None) => {
+            for bin in bin_paths {
+                // let full_cifar_path = [
+                //     config.base_path.to_owned(),
+                //     config.cifar_data_path.to_owned(),
+                //     bin.into(),
+                // ]
+                // .join("");
+                // println!("{}", full_cifar_path.display());
+
+                // read the whole file
+                let buffer = report.read(|| config.read_bin(bin))?;
+                report.decode(|| visit(&buffer));
+                // println!(
+                //     "{}",
+                //     format!("- Done parsing binary file {} to Vec<u8>", bin).as_str()
+                // );
+            }
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        monotonic_counter.records_parsed = num as u64,
+        monotonic_counter.bytes_read = report.bytes_read - bytes_read,
+        records_per_sec = num as f64 / start.elapsed().as_secs_f64().max(f64::EPSILON),
+        "parsed the {:?} split",
+        split
+    );
+    if num < num_records {
+        return Err(format!(
+            "Expected {} records in the {:?} split, but the binaries only contain {}",
+            num_records, split, num
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Per-pixel mean of flattened 3072-value images, accumulated in `f64` to avoid drifting over 50,000 images
+fn mean_image(data: &[f32]) -> Vec<f32> {
+    let mut sum = vec![0f64; 3072];
+    for image in data.chunks_exact(3072) {
+        for (s, &x) in sum.iter_mut().zip(image) {
+            *s += x as f64;
+        }
+    }
+    let n = (data.len() / 3072).max(1) as f64;
+    sum.into_iter().map(|s| (s / n) as f32).collect()
+}
+
+/// Parses a split into its flat data and labels, converting each pixel with `convert`
+fn get_data<T: Copy + From<u8> + Send>(
+    config: &Cifar10,
+    split: Split,
+    archive: Option<&ArchiveContents>,
+    report: &mut LoadReport,
+    convert: impl Fn(u8) -> T,
+) -> Result<(Vec<T>, Vec<T>), Box<dyn Error>> {
+    let indices = match split {
+        Split::Train => &config.train_indices,
+        Split::Test => &config.test_indices,
+    };
+    if config.pipeline && config.filter.is_none() && archive.is_none() && indices.is_none() {
+        return get_data_staged(config, split, report, convert);
+    }
+
+    let num_records = split_len(config, split);
+    // Labels are written in their final encoding as records are kept, so there is no second pass over them
+    let width = config.label_width();
+    let mut labels: Vec<T> = Vec::with_capacity(num_records * width);
+    let mut data: Vec<T> = Vec::with_capacity(num_records * 3072);
+
+    let mut converting = std::time::Duration::ZERO;
+    for_each_record(config, split, archive, report, |label, pixels| {
+        if let Some(filter) = &config.filter {
+            if !filter.keep(pixels, label) {
+                return;
+            }
+        }
+        let start = std::time::Instant::now();
+        simd::extend_converted(&mut data, pixels, &convert);
+        converting += start.elapsed();
+        push_label(config, &mut labels, width, label);
+    })?;
+    // Conversion happens while decoding, so move its share of the time over
+    report.decode = report.decode.saturating_sub(converting);
+    report.convert += converting;
+    Ok((data, labels))
+}
+
+/// Same as `get_data` for whole binaries read from disk or an object store, in three stages joined by channels: a
+/// reader thread loads each binary in turn, a label thread checks and encodes the labels of its records, and the
+/// calling thread converts their pixels. Labels and pixels of one binary are thus handled at the same time, while
+/// the next binary is being read
+fn get_data_staged<T: Copy + From<u8> + Send>(
+    config: &Cifar10,
+    split: Split,
+    report: &mut LoadReport,
+    convert: impl Fn(u8) -> T,
+) -> Result<(Vec<T>, Vec<T>), Box<dyn Error>> {
+    let bin_paths = config.split_bins(split);
+    let num_records = split_len(config, split);
+    let width = config.label_width();
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("parse", ?split, num_records, staged = true).entered();
+
+    let (pixel_sender, pixel_receiver) = crossbeam_channel::bounded(1);
+    let (label_sender, label_receiver) = crossbeam_channel::bounded::<Arc<Vec<u8>>>(1);
+    let mut data: Vec<T> = Vec::with_capacity(num_records * 3072);
+    let mut num = 0;
+    let (labels, decoding) = std::thread::scope(|scope| -> Result<_, Box<dyn Error>> {
+        scope.spawn(move || {
+            for bin in bin_paths {
+                let start = std::time::Instant::now();
+                let buffer = config.read_bin(bin).map(Arc::new);
+                let elapsed = start.elapsed();
+                if let Ok(buffer) = &buffer {
+                    if label_sender.send(buffer.clone()).is_err() {
+                        break;
+                    }
+                }
+                let buffer = buffer.map(|buffer| (buffer, elapsed));
+                let failed = buffer.is_err();
+                if pixel_sender
+                    .send(buffer.map_err(|e| e.to_string()))
+                    .is_err()
+                    || failed
+                {
+                    break;
+                }
+            }
+        });
+        let labeler = scope.spawn(move || {
+            let start = std::time::Instant::now();
+            let mut labels: Vec<T> = Vec::with_capacity(num_records * width);
+            let mut num = 0;
+            for buffer in label_receiver {
+                for record in buffer.chunks_exact(3073).take(num_records - num) {
+                    let label = record[0];
+                    if label > 9 {
+                        panic!(
+                            "Image {}: Label is {}, which is inconsistent with the CIFAR-10 scheme",
+                            num, label
+                        );
+                    }
+                    push_label(config, &mut labels, width, label);
+                    num += 1;
+                }
+            }
+            (labels, start.elapsed())
+        });
+
+        for buffer in pixel_receiver {
+            let (buffer, elapsed) = buffer?;
+            report.read += elapsed;
+            report.bytes_read += buffer.len() as u64;
+            report.largest_buffer = report.largest_buffer.max(buffer.len() as u64);
+            let start = std::time::Instant::now();
+            for record in buffer.chunks_exact(3073).take(num_records - num) {
+                simd::extend_converted(&mut data, &record[1..], &convert);
+                num += 1;
+            }
+            report.convert += start.elapsed();
+        }
+        // Re-raise a panic over a bad label with its own message
+        Ok(labeler
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+    })?;
+    report.decode += decoding;
+
+    if num < num_records {
+        return Err(format!(
+            "Expected {} records in the {:?} split, but the binaries only contain {}",
+            num_records, split, num
+        )
+        .into());
+    }
     Ok((data, labels))
 }
 
+/// Appends the label of a kept record in the configured encoding, after any remapping
+fn push_label<T: Copy + From<u8>>(config: &Cifar10, labels: &mut Vec<T>, width: usize, label: u8) {
+    let class = match &config.label_map {
+        Some(map) => map[label as usize],
+        None => label,
+    };
+    match config.encode_one_hot {
+        false => labels.push(T::from(class)),
+        true => {
+            let row = labels.len();
+            labels.resize(row + width, T::from(0));
+            labels[row + class as usize] = T::from(1);
+        }
+    }
+}
+
+/// The `ndarray` form of a `CifarResult`, organized as `(train_data, train_labels, test_data, test_labels)`
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub type NdarrayResult<T> = (Array4<T>, Array2<T>, Array4<T>, Array2<T>);
+
+/// Shared form of a `CifarResult` returned by `Cifar10::build_shared`, organized as
+/// `(train_data, train_labels, test_data, test_labels)`
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub type SharedResult = (
+    ArcArray<u8, Ix4>,
+    ArcArray<u8, Ix2>,
+    ArcArray<u8, Ix4>,
+    ArcArray<u8, Ix2>,
+);
+
+/// Flattened form of a `CifarResult`, with one row per record, organized as
+/// `(train_data, train_labels, test_data, test_labels)`
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub type FlatResult<T> = (Array2<T>, Array2<T>, Array2<T>, Array2<T>);
+
 impl CifarResult {
     #[cfg(any(
         feature = "to_ndarray_016",
@@ -252,20 +1517,59 @@ impl CifarResult {
         feature = "to_ndarray_014",
         feature = "to_ndarray_013"
     ))]
-    pub fn to_ndarray<T: std::convert::From<u8>>(
-        self,
-    ) -> Result<(Array4<T>, Array2<T>, Array4<T>, Array2<T>), Box<dyn Error>> {
+    pub fn to_ndarray<T: std::convert::From<u8>>(self) -> Result<NdarrayResult<T>, Box<dyn Error>> {
+        let (train_records, test_records) = (self.0.len() / 3072, self.2.len() / 3072);
+        let train_width = subset::label_width(&self.0, &self.1);
+        let test_width = subset::label_width(&self.2, &self.3);
         let train_data: Array4<T> =
-            Array::from_shape_vec((50_000, 3, 32, 32), self.0)?.mapv(|x| x.into());
+            nd::array((train_records, 3, 32, 32), self.0)?.mapv(|x| x.into());
         let train_labels: Array2<T> =
-            Array::from_shape_vec((50_000, 10), self.1)?.mapv(|x| x.into());
-        let test_data: Array4<T> =
-            Array::from_shape_vec((10_000, 3, 32, 32), self.2)?.mapv(|x| x.into());
+            nd::array((train_records, train_width), self.1)?.mapv(|x| x.into());
+        let test_data: Array4<T> = nd::array((test_records, 3, 32, 32), self.2)?.mapv(|x| x.into());
         let test_labels: Array2<T> =
-            Array::from_shape_vec((10_000, 10), self.3)?.mapv(|x| x.into());
+            nd::array((test_records, test_width), self.3)?.mapv(|x| x.into());
 
         Ok((train_data, train_labels, test_data, test_labels))
     }
+
+    /// Same as `to_ndarray`, but with every array laid out in column-major (Fortran) memory order, for
+    /// consumers such as LAPACK-style code or Julia which expect it. Labels are `[N, 1]` when stored as class
+    /// indices
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn to_ndarray_f_order<T: std::convert::From<u8>>(
+        self,
+    ) -> Result<NdarrayResult<T>, Box<dyn Error>> {
+        let train_width = subset::label_width(&self.0, &self.1);
+        let test_width = subset::label_width(&self.2, &self.3);
+        Ok((
+            f_order(&self.0, Ix4(self.0.len() / 3072, 3, 32, 32))?,
+            f_order(&self.1, Ix2(self.1.len() / train_width, train_width))?,
+            f_order(&self.2, Ix4(self.2.len() / 3072, 3, 32, 32))?,
+            f_order(&self.3, Ix2(self.3.len() / test_width, test_width))?,
+        ))
+    }
+}
+
+/// Converts row-major `data` into a column-major array of the given shape in a single pass
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+fn f_order<T: From<u8>, D: Dimension>(
+    data: &[u8],
+    shape: D,
+) -> Result<Array<T, D>, Box<dyn Error>> {
+    let view = nd::view(shape.clone(), data)?;
+    // Iterating the transposed view visits the elements in column-major order
+    let elements = view.t().iter().map(|&x| x.into()).collect();
+    nd::array(shape.f(), elements)
 }
 
 #[cfg(any(
@@ -0,0 +1,66 @@
+use crate::{Cifar10, CifarData, Preset};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// The dataset as `f32` pixels, scaled to `[0, 1]` and normalized with `CIFAR10_MEAN` and `CIFAR10_STD`, as
+/// returned by `load_normalized`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedData {
+    /// Flat `3 x 32 x 32` training images, R, G and B planes in that order
+    pub train_images: Vec<f32>,
+    /// Class index of each training image
+    pub train_labels: Vec<u8>,
+    /// Flat `3 x 32 x 32` testing images, R, G and B planes in that order
+    pub test_images: Vec<f32>,
+    /// Class index of each testing image
+    pub test_labels: Vec<u8>,
+}
+
+/// The directory `load` and `load_normalized` keep the dataset in: `$CIFAR_TEN_CACHE` if it is set, otherwise
+/// `cifar-ten` under `$XDG_CACHE_HOME` or `~/.cache`
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("CIFAR_TEN_CACHE") {
+        return PathBuf::from(dir);
+    }
+    match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir).join("cifar-ten"),
+        None => crate::expand_home("~/.cache/cifar-ten"),
+    }
+}
+
+/// The default options pointed at a cache directory, downloading the archive only if a binary is missing
+fn cached(dir: &Path) -> Cifar10 {
+    let config = Cifar10::default().base_path(dir.to_string_lossy());
+    let missing = config.resolved_paths().iter().any(|path| !path.is_file());
+    config.download_and_extract(missing && cfg!(feature = "download"))
+}
+
+/// Loads the whole dataset with default options in one call: the binaries are downloaded into `cache_dir` first
+/// if they are missing (with the `download` feature), verified and parsed. Use the `Cifar10` builder for any
+/// other setup
+pub fn load() -> Result<CifarData<32>, Box<dyn Error>> {
+    load_from(cache_dir())
+}
+
+/// Same as `load`, with the pixels converted to `f32` and normalized as for `Preset::TorchvisionTrain`
+pub fn load_normalized() -> Result<NormalizedData, Box<dyn Error>> {
+    load_normalized_from(cache_dir())
+}
+
+/// Same as `load`, keeping the dataset in `dir` rather than `cache_dir`
+pub fn load_from(dir: impl AsRef<Path>) -> Result<CifarData<32>, Box<dyn Error>> {
+    cached(dir.as_ref()).build_typed()
+}
+
+/// Same as `load_normalized`, keeping the dataset in `dir` rather than `cache_dir`
+pub fn load_normalized_from(dir: impl AsRef<Path>) -> Result<NormalizedData, Box<dyn Error>> {
+    let config = cached(dir.as_ref()).preset(Preset::TorchvisionTrain);
+    let ((train_images, train_labels, test_images, test_labels), _) = config.flat_f32(false)?;
+    let indices = |labels: Vec<f32>| labels.into_iter().map(|x| x as u8).collect();
+    Ok(NormalizedData {
+        train_images,
+        train_labels: indices(train_labels),
+        test_images,
+        test_labels: indices(test_labels),
+    })
+}
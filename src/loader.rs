@@ -0,0 +1,219 @@
+use crate::{Augmentation, CifarResult, Split};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::convert::TryInto;
+use std::error::Error;
+
+/// A batch of two independently augmented views of the same images, as yielded by `CifarResult::contrastive_pairs`
+#[derive(Debug, Clone)]
+pub struct ContrastiveBatch {
+    /// Index of each image within its split
+    pub indices: Vec<usize>,
+    /// First view of each image, as consecutive `3 x 32 x 32` records
+    pub view_a: Vec<u8>,
+    /// Second view of each image, as consecutive `3 x 32 x 32` records
+    pub view_b: Vec<u8>,
+}
+
+/// Iterator over shuffled batches of SimCLR/MoCo-style augmented view pairs
+pub struct ContrastivePairs<'a> {
+    data: &'a [u8],
+    augmentation: &'a Augmentation,
+    order: Vec<usize>,
+    batch_size: usize,
+    position: usize,
+    seed: u64,
+}
+
+/// Random number generator for one view of one image, derived from the seed, the image's index and the view so
+/// that augmentations don't depend on batching or on which thread handles the image
+fn view_rng(seed: u64, index: usize, view: u64) -> StdRng {
+    StdRng::seed_from_u64(crate::augment::splitmix64(
+        seed ^ (index as u64 * 2 + view).wrapping_mul(0x9e37_79b9_7f4a_7c15),
+    ))
+}
+
+impl<'a> Iterator for ContrastivePairs<'a> {
+    type Item = ContrastiveBatch;
+
+    fn next(&mut self) -> Option<ContrastiveBatch> {
+        if self.position >= self.order.len() {
+            return None;
+        }
+        let end = (self.position + self.batch_size).min(self.order.len());
+        let indices = self.order[self.position..end].to_vec();
+        self.position = end;
+
+        let mut view_a = vec![0; indices.len() * 3072];
+        let mut view_b = vec![0; indices.len() * 3072];
+        let (data, augmentation, seed) = (self.data, self.augmentation, self.seed);
+        let augment = |(i, (a, b)): (&usize, (&mut [u8], &mut [u8]))| {
+            let record = &data[i * 3072..(i + 1) * 3072];
+            for (view, output) in [(0, a), (1, b)] {
+                output.copy_from_slice(record);
+                augmentation.apply(output, &mut view_rng(seed, *i, view));
+            }
+        };
+        #[cfg(feature = "rayon")]
+        indices
+            .par_iter()
+            .zip(view_a.par_chunks_mut(3072).zip(view_b.par_chunks_mut(3072)))
+            .for_each(augment);
+        #[cfg(not(feature = "rayon"))]
+        indices
+            .iter()
+            .zip(view_a.chunks_mut(3072).zip(view_b.chunks_mut(3072)))
+            .for_each(augment);
+
+        Some(ContrastiveBatch {
+            indices,
+            view_a,
+            view_b,
+        })
+    }
+}
+
+/// Iterator over consecutive batches of a split converted to normalized `f32`, as yielded by
+/// `CifarResult::flat_f32_batches`
+pub struct FlatF32Batches<'a> {
+    data: std::slice::Chunks<'a, u8>,
+    labels: std::slice::Chunks<'a, u8>,
+}
+
+impl<'a> Iterator for FlatF32Batches<'a> {
+    type Item = (Vec<f32>, &'a [u8]);
+
+    fn next(&mut self) -> Option<(Vec<f32>, &'a [u8])> {
+        let data = self.data.next()?;
+        let labels = self.labels.next()?;
+        let mut converted = Vec::with_capacity(data.len());
+        crate::simd::extend_converted(&mut converted, data, |x| x as f32 / 255.0);
+        Some((converted, labels))
+    }
+}
+
+/// A single image as nested `[channel][row][column]` arrays of `f32` scaled to `[0, 1]`
+pub type ImageArray = [[[f32; 32]; 32]; 3];
+
+/// Iterator over consecutive batches of exactly `B` images with their one-hot labels over `C` classes, as yielded
+/// by `CifarResult::shaped_batches`
+pub struct ShapedBatches<'a, const B: usize, const C: usize> {
+    data: std::slice::ChunksExact<'a, u8>,
+    labels: std::slice::ChunksExact<'a, u8>,
+    width: usize,
+}
+
+impl<'a, const B: usize, const C: usize> Iterator for ShapedBatches<'a, B, C> {
+    type Item = (Box<[ImageArray; B]>, Box<[[f32; C]; B]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.data.next()?;
+        let labels = self.labels.next()?;
+
+        let mut images: Vec<ImageArray> = Vec::with_capacity(B);
+        let mut targets: Vec<[f32; C]> = Vec::with_capacity(B);
+        for (i, record) in data.chunks_exact(3072).enumerate() {
+            let mut image = [[[0f32; 32]; 32]; 3];
+            for (c, plane) in image.iter_mut().enumerate() {
+                for (y, row) in plane.iter_mut().enumerate() {
+                    for (x, value) in row.iter_mut().enumerate() {
+                        *value = record[c * 1024 + y * 32 + x] as f32 / 255.0;
+                    }
+                }
+            }
+            images.push(image);
+            let mut target = [0f32; C];
+            target[crate::subset::class_index(labels, self.width, i) as usize] = 1.0;
+            targets.push(target);
+        }
+
+        Some((
+            images.into_boxed_slice().try_into().ok()?,
+            targets.into_boxed_slice().try_into().ok()?,
+        ))
+    }
+}
+
+impl CifarResult {
+    /// Iterates over a split in order in batches of exactly `B` images, typed as nested arrays so that tensor
+    /// libraries with compile-time shapes can take them without reshaping. Pixels are scaled to `[0, 1]` and labels
+    /// are one-hot over `C` classes (10 for CIFAR-10) whatever their stored encoding. A final partial batch is
+    /// dropped. Fails if one-hot labels aren't `C` wide or a class index isn't below `C`
+    pub fn shaped_batches<const B: usize, const C: usize>(
+        &self,
+        split: Split,
+    ) -> Result<ShapedBatches<'_, B, C>, Box<dyn Error>> {
+        assert!(B > 0, "Batches must hold at least one image");
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        let width = crate::subset::label_width(data, labels);
+        match width {
+            1 => {
+                if let Some(class) = labels.iter().find(|&&class| class as usize >= C) {
+                    return Err(format!("Class {} doesn't fit in {} classes", class, C).into());
+                }
+            }
+            _ if width != C => {
+                return Err(format!("Labels are one-hot over {} classes, not {}", width, C).into())
+            }
+            _ => (),
+        }
+        Ok(ShapedBatches {
+            data: data.chunks_exact(B * 3072),
+            labels: labels.chunks_exact(B * width),
+            width,
+        })
+    }
+
+    /// Iterates over a split in batches of `batch_size` shuffled images, yielding two independently augmented
+    /// views of each image along with their indices. The shuffle and the augmentations are determined by `seed`, with
+    /// each image's augmentations depending only on `seed` and its index. With the `rayon` feature, the images of a
+    /// batch are augmented in parallel on the global rayon pool, with the same results. Panics if `batch_size` is
+    /// zero
+    pub fn contrastive_pairs<'a>(
+        &'a self,
+        split: Split,
+        augmentation: &'a Augmentation,
+        batch_size: usize,
+        seed: u64,
+    ) -> ContrastivePairs<'a> {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        assert!(batch_size > 0, "Batches must hold at least one image");
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut order: Vec<usize> = (0..data.len() / 3072).collect();
+        order.shuffle(&mut rng);
+
+        ContrastivePairs {
+            data,
+            augmentation,
+            order,
+            batch_size,
+            position: 0,
+            seed,
+        }
+    }
+
+    /// Iterates over a split in order in batches of `batch_size` images, converting only the batch being yielded
+    /// to `f32` scaled to `[0, 1]`, so training loops never hold the whole split as `f32`. Each batch is a
+    /// row-major `[batch, 3072]` buffer along with the batch's labels as stored. Panics if `batch_size` is zero
+    pub fn flat_f32_batches(&self, split: Split, batch_size: usize) -> FlatF32Batches<'_> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        assert!(batch_size > 0, "Batches must hold at least one image");
+        let width = crate::subset::label_width(data, labels);
+        FlatF32Batches {
+            data: data.chunks(batch_size * 3072),
+            labels: labels.chunks(batch_size * width),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::subset::{class_index, label_width};
+use crate::CifarResult;
+use std::error::Error;
+
+/// A dataset taking part in `concat`, along with the name of each of its labels
+pub struct ConcatInput {
+    pub result: CifarResult,
+    pub class_names: Vec<String>,
+}
+
+impl ConcatInput {
+    pub fn new(result: CifarResult, class_names: &[&str]) -> Self {
+        ConcatInput {
+            result,
+            class_names: class_names.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+/// Merges several datasets (e.g. CIFAR-10 plus custom data in the CIFAR format) split by split. Each class name is
+/// first replaced according to `renames` (pairs of `(from, to)`), then classes with the same name share a label
+/// in the merged dataset. Returns the merged records, whose labels are class indices rather than one-hot, and the
+/// combined class-name table
+pub fn concat(
+    inputs: Vec<ConcatInput>,
+    renames: &[(&str, &str)],
+) -> Result<(CifarResult, Vec<String>), Box<dyn Error>> {
+    let mut class_names: Vec<String> = Vec::new();
+    let mut merged = CifarResult(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+
+    for input in inputs {
+        let label_map = input
+            .class_names
+            .iter()
+            .map(|name| {
+                let name = renames
+                    .iter()
+                    .find(|(from, _)| from == name)
+                    .map_or(name.as_str(), |(_, to)| to);
+                match class_names.iter().position(|existing| existing == name) {
+                    Some(label) => label,
+                    None => {
+                        class_names.push(name.to_string());
+                        class_names.len() - 1
+                    }
+                }
+            })
+            .collect::<Vec<usize>>();
+        if class_names.len() > 256 {
+            return Err("The merged dataset has more than 256 classes".into());
+        }
+
+        let CifarResult(train_data, train_labels, test_data, test_labels) = input.result;
+        for (data, labels, merged_data, merged_labels) in [
+            (train_data, train_labels, &mut merged.0, &mut merged.1),
+            (test_data, test_labels, &mut merged.2, &mut merged.3),
+        ] {
+            let width = label_width(&data, &labels);
+            for i in 0..data.len() / 3072 {
+                let label = class_index(&labels, width, i) as usize;
+                let mapped = label_map.get(label).ok_or_else(|| {
+                    format!("Label {} has no entry in the class-name table", label)
+                })?;
+                merged_labels.push(*mapped as u8);
+            }
+            merged_data.extend(data);
+        }
+    }
+
+    Ok((merged, class_names))
+}
@@ -0,0 +1,20 @@
+//! The few `ndarray` constructors this crate calls, kept in one place so that each supported `ndarray` version
+//! only has to agree with these signatures, and errors come out as the `Box<dyn Error>` used everywhere else
+use crate::ndarray::{Array, ArrayView, Dimension, StrideShape};
+use std::error::Error;
+
+/// Builds an owned array of the given shape from row-major data
+pub(crate) fn array<T, D: Dimension>(
+    shape: impl Into<StrideShape<D>>,
+    data: Vec<T>,
+) -> Result<Array<T, D>, Box<dyn Error>> {
+    Ok(Array::from_shape_vec(shape, data)?)
+}
+
+/// Borrows row-major data as an array of the given shape, which may use custom strides
+pub(crate) fn view<T, D: Dimension>(
+    shape: impl Into<StrideShape<D>>,
+    data: &[T],
+) -> Result<ArrayView<'_, T, D>, Box<dyn Error>> {
+    Ok(ArrayView::from_shape(shape, data)?)
+}
@@ -0,0 +1,113 @@
+use crate::{Cifar10, CifarResult, Split};
+use std::error::Error;
+use std::path::Path;
+
+/// The CIFAR-10N human-annotated label sets for the training split, as class indices aligned with the images
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoisyLabels {
+    pub aggregate: Vec<u8>,
+    pub random1: Vec<u8>,
+    pub random2: Vec<u8>,
+    pub random3: Vec<u8>,
+    pub worst: Vec<u8>,
+}
+
+impl NoisyLabels {
+    /// Loads the label sets from a CSV file with one row per training image and a header naming the columns
+    /// `aggre_label`, `random_label1`, `random_label2`, `random_label3` and `worse_label` (other columns, such as
+    /// `clean_label`, are ignored). The published `CIFAR-10_human.pt` can be converted with
+    /// `pandas.DataFrame({k: v for k, v in torch.load("CIFAR-10_human.pt").items()}).to_csv(path, index=False)`
+    pub fn from_csv(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        let header: Vec<&str> = lines
+            .next()
+            .ok_or("The CIFAR-10N label file is empty")?
+            .split(',')
+            .map(|name| name.trim())
+            .collect();
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|&h| h == name)
+                .ok_or_else(|| format!("The CIFAR-10N label file has no {} column", name))
+        };
+        let columns = [
+            column("aggre_label")?,
+            column("random_label1")?,
+            column("random_label2")?,
+            column("random_label3")?,
+            column("worse_label")?,
+        ];
+
+        let mut sets: [Vec<u8>; 5] = Default::default();
+        for (row, line) in lines.filter(|line| !line.trim().is_empty()).enumerate() {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            for (set, &col) in sets.iter_mut().zip(&columns) {
+                let label: u8 = fields
+                    .get(col)
+                    .ok_or_else(|| format!("Row {} of the CIFAR-10N label file is too short", row))?
+                    .parse()?;
+                if label > 9 {
+                    return Err(format!(
+                        "Row {}: Label is {}, which is inconsistent with the CIFAR-10 scheme",
+                        row, label
+                    )
+                    .into());
+                }
+                set.push(label);
+            }
+        }
+
+        let [aggregate, random1, random2, random3, worst] = sets;
+        Ok(NoisyLabels {
+            aggregate,
+            random1,
+            random2,
+            random3,
+            worst,
+        })
+    }
+
+    /// Keeps only the labels of the given training images, in that order
+    pub fn select(&self, indices: &[usize]) -> Result<NoisyLabels, Box<dyn Error>> {
+        let pick = |set: &[u8]| -> Result<Vec<u8>, Box<dyn Error>> {
+            indices
+                .iter()
+                .map(|&i| {
+                    set.get(i)
+                        .copied()
+                        .ok_or_else(|| format!("Record {} has no CIFAR-10N label", i).into())
+                })
+                .collect()
+        };
+        Ok(NoisyLabels {
+            aggregate: pick(&self.aggregate)?,
+            random1: pick(&self.random1)?,
+            random2: pick(&self.random2)?,
+            random3: pick(&self.random3)?,
+            worst: pick(&self.worst)?,
+        })
+    }
+}
+
+impl Cifar10 {
+    /// Builds the dataset along with the CIFAR-10N noisy label sets loaded from `path` (see
+    /// `NoisyLabels::from_csv`), aligned with the training images that were loaded
+    pub fn build_with_noisy_labels(
+        self,
+        path: impl AsRef<Path>,
+    ) -> Result<(CifarResult, NoisyLabels), Box<dyn Error>> {
+        if self.filter.is_some() {
+            return Err(
+                "Noisy labels can't be aligned with records selected by filter_records".into(),
+            );
+        }
+        let indices: Vec<usize> = match &self.train_indices {
+            Some(indices) => indices.clone(),
+            None => (0..crate::split_len(&self, Split::Train)).collect(),
+        };
+        let noisy_labels = NoisyLabels::from_csv(path)?.select(&indices)?;
+        Ok((self.build()?, noisy_labels))
+    }
+}
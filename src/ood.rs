@@ -0,0 +1,193 @@
+use crate::{CifarResult, Preprocess, SyntheticNoise};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Out-of-distribution set paired with the CIFAR-10 test split by `CifarResult::ood_evaluation`
+#[derive(Debug, Clone, PartialEq)]
+pub enum OodSource {
+    /// The CIFAR-100 test binary (`test.bin` from `cifar-100-binary.tar.gz`)
+    Cifar100(PathBuf),
+    /// The SVHN test set as distributed (`test_32x32.mat`)
+    #[cfg(feature = "archive")]
+    Svhn(PathBuf),
+    /// `count` images of independent uniformly random pixels
+    UniformNoise { count: usize, seed: u64 },
+    /// `count` images of independent normally distributed pixels, with `mean` and `std` given for pixels scaled to
+    /// `[0, 1]` and values clamped to that range
+    GaussianNoise {
+        count: usize,
+        mean: f32,
+        std: f32,
+        seed: u64,
+    },
+}
+
+impl OodSource {
+    /// Loads the images as flat `3 x 32 x 32` records, R, G and B planes in that order like CIFAR-10's
+    pub fn load(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            OodSource::Cifar100(path) => {
+                let buffer = std::fs::read(path)?;
+                if buffer.len() % 3074 != 0 {
+                    return Err(format!(
+                        "{} is not a CIFAR-100 binary: {} bytes is not a whole number of records",
+                        path.display(),
+                        buffer.len()
+                    )
+                    .into());
+                }
+                // Each record starts with a coarse and a fine label byte
+                Ok(buffer
+                    .chunks_exact(3074)
+                    .flat_map(|record| &record[2..])
+                    .copied()
+                    .collect())
+            }
+            #[cfg(feature = "archive")]
+            OodSource::Svhn(path) => mat::read_images(&std::fs::read(path)?, "X"),
+            &OodSource::UniformNoise { count, seed } => {
+                Ok(SyntheticNoise::Uniform.images(count, seed))
+            }
+            &OodSource::GaussianNoise {
+                count,
+                mean,
+                std,
+                seed,
+            } => Ok(SyntheticNoise::Gaussian { mean, std }.images(count, seed)),
+        }
+    }
+}
+
+/// The standard out-of-distribution detection setup: CIFAR-10 test images followed by out-of-distribution images,
+/// with the origin of each
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OodEvaluation {
+    /// Flat `3 x 32 x 32` records, in-distribution first
+    pub data: Vec<u8>,
+    /// 0 for each in-distribution record and 1 for each out-of-distribution record
+    pub origin: Vec<u8>,
+}
+
+impl OodEvaluation {
+    /// Converts the images to `f32`, divided by 255 when `normalize` is set and then put through `preprocess`, so
+    /// both sets are treated exactly alike
+    pub fn to_f32(&self, normalize: bool, preprocess: &[Preprocess]) -> Vec<f32> {
+        let scale = if normalize { 1.0 / 255.0 } else { 1.0 };
+        let mut data: Vec<f32> = self.data.iter().map(|&x| x as f32 * scale).collect();
+        for image in data.chunks_exact_mut(3072) {
+            for step in preprocess {
+                step.apply(image);
+            }
+        }
+        data
+    }
+}
+
+impl CifarResult {
+    /// Pairs the test split, as the in-distribution set, with the images of `source`
+    pub fn ood_evaluation(&self, source: &OodSource) -> Result<OodEvaluation, Box<dyn Error>> {
+        let ood = source.load()?;
+        let mut origin = vec![0; self.2.len() / 3072];
+        origin.resize(origin.len() + ood.len() / 3072, 1);
+        let mut data = Vec::with_capacity(self.2.len() + ood.len());
+        data.extend_from_slice(&self.2);
+        data.extend(ood);
+        Ok(OodEvaluation { data, origin })
+    }
+}
+
+/// Just enough of the MATLAB level 5 MAT-file format to read the image array out of the SVHN files
+#[cfg(feature = "archive")]
+mod mat {
+    use std::convert::TryInto;
+    use std::error::Error;
+    use std::io::Read;
+
+    const MI_INT32: u32 = 5;
+    const MI_UINT8: u32 = 2;
+    const MI_MATRIX: u32 = 14;
+    const MI_COMPRESSED: u32 = 15;
+
+    /// A data element's type and data, followed by the bytes after it
+    type Element<'a> = (u32, &'a [u8], &'a [u8]);
+
+    /// Splits the next data element off `bytes`, returning its type, its data and whatever follows it
+    fn next_element(bytes: &[u8]) -> Result<Element<'_>, Box<dyn Error>> {
+        let word = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().expect("4 bytes"));
+        if bytes.len() < 8 {
+            return Err("MAT file is truncated".into());
+        }
+        // Elements of up to 4 bytes are packed into the tag
+        if word(0) >> 16 != 0 {
+            let len = (word(0) >> 16) as usize;
+            return Ok((word(0) & 0xffff, &bytes[4..4 + len.min(4)], &bytes[8..]));
+        }
+        let (kind, len) = (word(0), word(4) as usize);
+        let data = bytes.get(8..8 + len).ok_or("MAT file is truncated")?;
+        let padded = match kind {
+            MI_COMPRESSED => len,
+            _ => len.div_ceil(8) * 8,
+        };
+        Ok((kind, data, bytes.get(8 + padded..).unwrap_or(&[])))
+    }
+
+    /// Reads the `32 x 32 x 3 x N` `uint8` array named `name`, converting MATLAB's column-major
+    /// `[row][column][channel][image]` order into flat `3 x 32 x 32` records
+    pub(crate) fn read_images(file: &[u8], name: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        if file.len() < 128 || &file[126..128] != b"IM" {
+            return Err("Not a little-endian level 5 MAT file".into());
+        }
+        let mut rest = &file[128..];
+        while !rest.is_empty() {
+            let (kind, data, next) = next_element(rest)?;
+            rest = next;
+            let decompressed;
+            let (kind, data) = match kind {
+                MI_COMPRESSED => {
+                    let mut buffer = Vec::new();
+                    flate2::read::ZlibDecoder::new(data).read_to_end(&mut buffer)?;
+                    decompressed = buffer;
+                    let (kind, data, _) = next_element(&decompressed)?;
+                    (kind, data)
+                }
+                _ => (kind, data),
+            };
+            if kind != MI_MATRIX {
+                continue;
+            }
+
+            // Array flags, dimensions, name and real part, in that order
+            let (_, _, matrix) = next_element(data)?;
+            let (dims_kind, dims, matrix) = next_element(matrix)?;
+            let (_, array_name, matrix) = next_element(matrix)?;
+            if array_name != name.as_bytes() {
+                continue;
+            }
+            let (real_kind, real, _) = next_element(matrix)?;
+            let dims: Vec<usize> = dims
+                .chunks_exact(4)
+                .map(|d| i32::from_le_bytes(d.try_into().expect("4 bytes")) as usize)
+                .collect();
+            let count = match dims[..] {
+                [32, 32, 3, count] if dims_kind == MI_INT32 => count,
+                _ => return Err(format!("{} is {:?}, not 32 x 32 x 3 x N", name, dims).into()),
+            };
+            if real_kind != MI_UINT8 || real.len() != count * 3072 {
+                return Err(format!("{} is not stored as uint8", name).into());
+            }
+
+            let mut records = vec![0; count * 3072];
+            for (i, record) in records.chunks_exact_mut(3072).enumerate() {
+                for c in 0..3 {
+                    for y in 0..32 {
+                        for x in 0..32 {
+                            record[c * 1024 + y * 32 + x] = real[i * 3072 + c * 1024 + x * 32 + y];
+                        }
+                    }
+                }
+            }
+            return Ok(records);
+        }
+        Err(format!("No array named {} in the MAT file", name).into())
+    }
+}
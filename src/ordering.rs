@@ -0,0 +1,58 @@
+use crate::{Cifar10, LoadReport, Split};
+use std::error::Error;
+
+/// 64-bit FNV-1a, chosen over `DefaultHasher` because its output is fixed across Rust versions and platforms
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+impl Cifar10 {
+    /// Hashes the sequence of `(index, label)` pairs a split would be built from, where the index is the record's
+    /// position across that split's binaries, after `indices`, `take_first` and `filter_records` are applied.
+    /// The checksum is stable across platforms and Rust versions, so distributed workers can compare theirs to
+    /// check that they all constructed the same ordering before training. Only labels are hashed, but the
+    /// binaries are still read in full
+    pub fn ordering_checksum(&self, split: Split) -> Result<u64, Box<dyn Error>> {
+        let mut report = LoadReport::default();
+        let archive = self.prepare(&mut report)?;
+        let indices = match split {
+            Split::Train => &self.train_indices,
+            Split::Test => &self.test_indices,
+        };
+
+        let mut hasher = Fnv1a::new();
+        let mut position = 0;
+        crate::for_each_record(
+            self,
+            split,
+            archive.as_ref(),
+            &mut report,
+            |label, pixels| {
+                let index = match indices {
+                    Some(indices) => indices[position],
+                    None => position,
+                };
+                position += 1;
+                if let Some(filter) = &self.filter {
+                    if !filter.keep(pixels, label) {
+                        return;
+                    }
+                }
+                hasher.write(&(index as u64).to_le_bytes());
+                hasher.write(&[label]);
+            },
+        )?;
+        Ok(hasher.0)
+    }
+}
@@ -0,0 +1,164 @@
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+use crate::ndarray::{Array3, Array4, ArrayView6, ShapeBuilder};
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+use crate::{CifarResult, Split};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::error::Error;
+
+/// Infinite iterator over MAE/SimMIM-style patch masks, one per image, aligned with the patch numbering of
+/// `patchify`: entry `i` of a mask is true when patch `i` is hidden from the encoder
+#[derive(Debug, Clone)]
+pub struct PatchMasks {
+    num_patches: usize,
+    num_masked: usize,
+    rng: StdRng,
+}
+
+impl PatchMasks {
+    /// Masks `mask_ratio` of the `patch_size x patch_size` patches of each `32 x 32` image (rounded to the nearest
+    /// whole patch), drawing which ones from an RNG seeded with `seed`. `patch_size` must divide 32
+    pub fn new(
+        patch_size: usize,
+        mask_ratio: f32,
+        seed: u64,
+    ) -> Result<PatchMasks, Box<dyn Error>> {
+        if patch_size == 0 || 32 % patch_size != 0 {
+            return Err(format!("Patch size {} does not evenly divide 32", patch_size).into());
+        }
+        if !(0.0..=1.0).contains(&mask_ratio) {
+            return Err(format!("Mask ratio {} is not between 0 and 1", mask_ratio).into());
+        }
+        let num_patches = (32 / patch_size) * (32 / patch_size);
+        Ok(PatchMasks {
+            num_patches,
+            num_masked: (mask_ratio * num_patches as f32).round() as usize,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    /// Number of patches in each mask
+    pub fn num_patches(&self) -> usize {
+        self.num_patches
+    }
+}
+
+impl Iterator for PatchMasks {
+    type Item = Vec<bool>;
+
+    fn next(&mut self) -> Option<Vec<bool>> {
+        let mut mask = vec![false; self.num_patches];
+        for patch in rand::seq::index::sample(&mut self.rng, self.num_patches, self.num_masked) {
+            mask[patch] = true;
+        }
+        Some(mask)
+    }
+}
+
+/// Locates pixel `(y, x)` within a grid of `patch_size x patch_size` patches numbered row by row, returning the
+/// patch number and the pixel's row and column within that patch
+pub(crate) fn locate_in_patch(
+    y: usize,
+    x: usize,
+    patch_size: usize,
+    patches_per_row: usize,
+) -> (usize, usize, usize) {
+    (
+        (y / patch_size) * patches_per_row + x / patch_size,
+        y % patch_size,
+        x % patch_size,
+    )
+}
+
+/// Splits each image of an `[N, C, H, W]` array into non-overlapping `patch_size x patch_size` patches for Vision
+/// Transformer style models, returning an `[N, num_patches, patch_dim]` array. Patches are numbered row by row,
+/// and each is flattened in `(row, column, channel)` order so `patch_dim = patch_size * patch_size * C`. Values
+/// are divided by 255 when `normalize` is set
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub fn patchify<T: Copy + Into<f32>>(
+    data: &Array4<T>,
+    patch_size: usize,
+    normalize: bool,
+) -> Array3<f32> {
+    let (n, c, h, w) = data.dim();
+    assert!(
+        patch_size > 0 && h % patch_size == 0 && w % patch_size == 0,
+        "Patch size {} does not evenly divide {} x {} images",
+        patch_size,
+        h,
+        w
+    );
+    let scale = if normalize { 1.0 / 255.0 } else { 1.0 };
+    let patches_per_row = w / patch_size;
+
+    let mut patches = Array3::zeros((
+        n,
+        (h / patch_size) * patches_per_row,
+        patch_size * patch_size * c,
+    ));
+    for ((i, channel, y, x), &value) in data.indexed_iter() {
+        let (patch, row, col) = locate_in_patch(y, x, patch_size, patches_per_row);
+        let offset = (row * patch_size + col) * c + channel;
+        patches[[i, patch, offset]] = value.into() * scale;
+    }
+    patches
+}
+
+/// Views flat `3 x 32 x 32` records as `[N, tile_rows, tile_cols, 3, tile_size, tile_size]`, splitting each image
+/// into non-overlapping `tile_size x tile_size` tiles numbered by their row and column in the grid. The view is
+/// strided over `data`, so nothing is copied; `tile_size` must divide 32
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub fn tiles<T>(data: &[T], tile_size: usize) -> Result<ArrayView6<'_, T>, Box<dyn Error>> {
+    if tile_size == 0 || 32 % tile_size != 0 {
+        return Err(format!("Tile size {} does not evenly divide 32", tile_size).into());
+    }
+    let grid = 32 / tile_size;
+    let shape = (data.len() / 3072, grid, grid, 3, tile_size, tile_size);
+    if shape.0 == 0 {
+        // ndarray rejects custom strides over an empty slice
+        return crate::nd::view(shape, &data[..0]);
+    }
+    let strides = (3072, tile_size * 32, tile_size, 1024, 32, 1);
+    crate::nd::view(shape.strides(strides), &data[..shape.0 * 3072])
+}
+
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+impl CifarResult {
+    /// Views the images of a split as a grid of tiles, as described by `tiles`
+    pub fn tiles(
+        &self,
+        split: Split,
+        tile_size: usize,
+    ) -> Result<ArrayView6<'_, u8>, Box<dyn Error>> {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        tiles(data, tile_size)
+    }
+}
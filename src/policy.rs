@@ -0,0 +1,187 @@
+use crate::{Augmentation, EqualizeMode, Transform};
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An operation that augmentation policies are built from, with its strength set by a magnitude in `[0, 1]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PolicyOp {
+    HorizontalFlip,
+    VerticalFlip,
+    /// One to three quarter turns
+    Rotate90,
+    /// Random shifts of up to 8 pixels
+    Translate,
+    Brightness,
+    Contrast,
+    Saturation,
+    Hue,
+    /// Gaussian blur with a standard deviation of up to 2 pixels
+    Blur,
+    Solarize,
+    /// Per-channel histogram equalization
+    Equalize,
+}
+
+impl PolicyOp {
+    /// Every operation, in the order `Policy::random` draws from
+    pub const ALL: [PolicyOp; 11] = [
+        PolicyOp::HorizontalFlip,
+        PolicyOp::VerticalFlip,
+        PolicyOp::Rotate90,
+        PolicyOp::Translate,
+        PolicyOp::Brightness,
+        PolicyOp::Contrast,
+        PolicyOp::Saturation,
+        PolicyOp::Hue,
+        PolicyOp::Blur,
+        PolicyOp::Solarize,
+        PolicyOp::Equalize,
+    ];
+
+    /// The transform performing the operation at `magnitude`, clamped to `[0, 1]`. Flips and equalization have
+    /// no strength, so ignore it
+    pub fn transform(&self, magnitude: f32) -> Transform {
+        let m = magnitude.clamp(0.0, 1.0);
+        let jitter = |brightness, contrast, saturation, hue| Transform::ColorJitter {
+            brightness,
+            contrast,
+            saturation,
+            hue,
+        };
+        match self {
+            PolicyOp::HorizontalFlip => Transform::HorizontalFlip { p: 1.0 },
+            PolicyOp::VerticalFlip => Transform::VerticalFlip { p: 1.0 },
+            PolicyOp::Rotate90 => Transform::Rot90 {
+                k: 1 + (m * 2.0).round() as i32,
+            },
+            PolicyOp::Translate => Transform::RandomCrop {
+                padding: (m * 8.0).round() as usize,
+            },
+            PolicyOp::Brightness => jitter(0.9 * m, 0.0, 0.0, 0.0),
+            PolicyOp::Contrast => jitter(0.0, 0.9 * m, 0.0, 0.0),
+            PolicyOp::Saturation => jitter(0.0, 0.0, 0.9 * m, 0.0),
+            PolicyOp::Hue => jitter(0.0, 0.0, 0.0, 0.5 * m),
+            PolicyOp::Blur => Transform::GaussianBlur {
+                sigma_range: (0.1, 0.1 + 1.9 * m),
+            },
+            PolicyOp::Solarize => Transform::Solarize { threshold: 1.0 - m },
+            PolicyOp::Equalize => Transform::HistogramEqualize {
+                mode: EqualizeMode::PerChannel,
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PolicyOp::HorizontalFlip => "HorizontalFlip",
+            PolicyOp::VerticalFlip => "VerticalFlip",
+            PolicyOp::Rotate90 => "Rotate90",
+            PolicyOp::Translate => "Translate",
+            PolicyOp::Brightness => "Brightness",
+            PolicyOp::Contrast => "Contrast",
+            PolicyOp::Saturation => "Saturation",
+            PolicyOp::Hue => "Hue",
+            PolicyOp::Blur => "Blur",
+            PolicyOp::Solarize => "Solarize",
+            PolicyOp::Equalize => "Equalize",
+        }
+    }
+}
+
+/// One step of a `Policy`: `op` at `magnitude`, applied with `probability`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PolicyStep {
+    pub op: PolicyOp,
+    pub magnitude: f32,
+    pub probability: f32,
+}
+
+/// An augmentation policy in the style of AutoAugment: steps applied one after another, each with its own
+/// probability, for small-scale policy search experiments. Policies print as (and parse from) a compact
+/// `op:magnitude:probability,...` form for logging search results, and also serialize with the `serde` feature
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Policy {
+    pub steps: Vec<PolicyStep>,
+}
+
+impl Policy {
+    /// Samples a policy of `len` steps, each a uniformly random operation whose magnitude and probability are
+    /// drawn from the 11 levels 0, 0.1, ..., 1 as in AutoAugment's search space
+    pub fn random<R: Rng + ?Sized>(len: usize, rng: &mut R) -> Policy {
+        let level = |rng: &mut R| rng.gen_range(0..=10) as f32 / 10.0;
+        let steps = (0..len)
+            .map(|_| PolicyStep {
+                op: PolicyOp::ALL[rng.gen_range(0..PolicyOp::ALL.len())],
+                magnitude: level(rng),
+                probability: level(rng),
+            })
+            .collect();
+        Policy { steps }
+    }
+
+    /// The policy as an `Augmentation`, so it runs through the same pipeline as hand-written ones
+    pub fn augmentation(&self) -> Augmentation {
+        Augmentation::new(
+            self.steps
+                .iter()
+                .map(|step| Transform::RandomApply {
+                    p: step.probability,
+                    transform: Box::new(step.op.transform(step.magnitude)),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(
+                f,
+                "{}:{}:{}",
+                step.op.name(),
+                step.magnitude,
+                step.probability
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = Vec::new();
+        for step in s.split(',').map(str::trim).filter(|step| !step.is_empty()) {
+            let invalid = || format!("Expected op:magnitude:probability, got {}", step);
+            let mut fields = step.split(':');
+            let (op, magnitude, probability) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(op), Some(magnitude), Some(probability)) if fields.next().is_none() => {
+                    (op, magnitude, probability)
+                }
+                _ => return Err(invalid().into()),
+            };
+            let op = *PolicyOp::ALL
+                .iter()
+                .find(|candidate| candidate.name() == op)
+                .ok_or_else(|| format!("Unknown policy operation {}", op))?;
+            steps.push(PolicyStep {
+                op,
+                magnitude: magnitude.parse().map_err(|_| invalid())?,
+                probability: probability.parse().map_err(|_| invalid())?,
+            });
+        }
+        Ok(Policy { steps })
+    }
+}
@@ -0,0 +1,25 @@
+//! The types most programs need, for a single glob import:
+//!
+//! ```
+//! use cifar_ten::prelude::*;
+//! ```
+//!
+//! With one of the `to_ndarray_*` features enabled, this also brings in the `ndarray` crate this one was built
+//! against, and the array types used in its signatures, so arrays returned here can be used without depending on
+//! a matching `ndarray` version downstream.
+
+pub use crate::{
+    Augmentation, Cifar10, CifarResult, FlatF32Batches, ImageArray, Preprocess, Preset, RecordRef,
+    ShapedBatches, Split, Transform, CLASS_NAMES,
+};
+
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+pub use crate::ndarray::{
+    self, Array, Array1, Array2, Array3, Array4, ArrayView, ArrayView2, ArrayView3, ArrayView6,
+    Axis,
+};
@@ -0,0 +1,105 @@
+use crate::{CifarResult, Split};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Per-channel mean of the CIFAR-10 training images, scaled to `[0, 1]`
+pub const CIFAR10_MEAN: [f32; 3] = [0.4914, 0.4822, 0.4465];
+
+/// Per-channel standard deviation of the CIFAR-10 training images, scaled to `[0, 1]`
+pub const CIFAR10_STD: [f32; 3] = [0.2470, 0.2435, 0.2616];
+
+/// Per-channel mean of the CIFAR-10 training images in pixel units, rounded to the nearest integer, for
+/// integer-only preprocessing with `CifarResult::centered_i8` and `CifarResult::centered_u8`
+pub const CIFAR10_MEAN_U8: [u8; 3] = [125, 123, 114];
+
+/// Preprocessing step applied to each image in the `f32` outputs, after scaling and mean subtraction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Preprocess {
+    /// Subtracts the image's mean and divides by its standard deviation, which is floored at `1 / sqrt(3072)` so
+    /// that uniform images don't divide by zero (like `tf.image.per_image_standardization`)
+    PerImageStandardization,
+}
+
+impl Preprocess {
+    /// Applies the step to a single flattened image
+    pub fn apply(&self, image: &mut [f32]) {
+        match self {
+            Preprocess::PerImageStandardization => {
+                let n = image.len() as f64;
+                let mean = image.iter().map(|&x| x as f64).sum::<f64>() / n;
+                let variance = image
+                    .iter()
+                    .map(|&x| (x as f64 - mean).powi(2))
+                    .sum::<f64>()
+                    / n;
+                let stddev = variance.sqrt().max(1.0 / n.sqrt());
+                for x in image.iter_mut() {
+                    *x = ((*x as f64 - mean) / stddev) as f32;
+                }
+            }
+        }
+    }
+}
+
+/// Subtracts a per-channel mean from a single flattened image and divides by a per-channel standard deviation
+pub(crate) fn normalize_channels(image: &mut [f32], mean: [f32; 3], std: [f32; 3]) {
+    for (c, plane) in image.chunks_exact_mut(1024).enumerate() {
+        for x in plane.iter_mut() {
+            *x = (*x - mean[c]) / std[c];
+        }
+    }
+}
+
+impl CifarResult {
+    /// Per-channel mean of a split in pixel units, rounded to the nearest integer and computed without floating
+    /// point, so it matches what an integer-only device would compute
+    pub fn channel_means_u8(&self, split: Split) -> [u8; 3] {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        let mut sums = [0u64; 3];
+        for record in data.chunks_exact(3072) {
+            for (sum, plane) in sums.iter_mut().zip(record.chunks_exact(1024)) {
+                *sum += plane.iter().map(|&x| x as u64).sum::<u64>();
+            }
+        }
+        let count = (data.len() / 3) as u64;
+        match count {
+            0 => [0; 3],
+            _ => sums.map(|sum| ((sum + count / 2) / count) as u8),
+        }
+    }
+
+    /// Subtracts a per-channel integer mean from every pixel of a split, saturating the results to `i8`, for
+    /// microcontroller inference pipelines without floating point. Use `CIFAR10_MEAN_U8` or `channel_means_u8`
+    /// for the means
+    pub fn centered_i8(&self, split: Split, means: [u8; 3]) -> Vec<i8> {
+        self.centered(split, means, |x| {
+            x.clamp(i8::MIN as i16, i8::MAX as i16) as i8
+        })
+    }
+
+    /// Subtracts a per-channel integer mean from every pixel of a split and adds `bias`, saturating the results
+    /// to `u8`. A bias of 128 gives the offset-binary form of `centered_i8`, for quantized models whose inputs
+    /// have a zero point
+    pub fn centered_u8(&self, split: Split, means: [u8; 3], bias: u8) -> Vec<u8> {
+        self.centered(split, means, |x| (x + bias as i16).clamp(0, 255) as u8)
+    }
+
+    fn centered<T>(&self, split: Split, means: [u8; 3], saturate: impl Fn(i16) -> T) -> Vec<T> {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        data.chunks_exact(1024)
+            .enumerate()
+            .flat_map(|(plane, pixels)| {
+                let mean = means[plane % 3] as i16;
+                pixels.iter().map(move |&x| x as i16 - mean)
+            })
+            .map(saturate)
+            .collect()
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{Augmentation, Cifar10, Transform, CIFAR10_MEAN, CIFAR10_STD};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Common combinations of builder options, applied with `Cifar10::preset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Preset {
+    /// Matches the usual torchvision training setup: `f32` outputs scaled to `[0, 1]` and normalized with
+    /// `CIFAR10_MEAN` and `CIFAR10_STD` with no other preprocessing, and labels as class indices rather than
+    /// one-hot. Images are already `[N, 3, 32, 32]` (NCHW). Labels stay `u8`; `CifarResult::label_indices_i64`
+    /// gives the `i64` class indices torch losses expect. Pair it with `Preset::augmentation`
+    TorchvisionTrain,
+    /// Favors load time: the next binary is read while the current one is parsed, with sequential read-ahead
+    FastLoad,
+    /// Raw `u8` pixels with class index labels, and nothing else
+    Minimal,
+}
+
+impl Preset {
+    /// The augmentation that goes with the preset: random 4-pixel-padded crops and horizontal flips for
+    /// `TorchvisionTrain`, and none otherwise
+    pub fn augmentation(&self) -> Augmentation {
+        match self {
+            Preset::TorchvisionTrain => Augmentation::new(vec![
+                Transform::RandomCrop { padding: 4 },
+                Transform::HorizontalFlip { p: 0.5 },
+            ]),
+            Preset::FastLoad | Preset::Minimal => Augmentation::default(),
+        }
+    }
+}
+
+impl Cifar10 {
+    /// Sets the options bundled in a preset, replacing any earlier choices for them. Builder calls made after
+    /// this override the preset
+    pub fn preset(self, preset: Preset) -> Self {
+        match preset {
+            Preset::TorchvisionTrain => Cifar10 {
+                preprocess: vec![],
+                ..self
+            }
+            .normalize(true)
+            .normalize_channels(CIFAR10_MEAN, CIFAR10_STD)
+            .subtract_mean_image(false)
+            .encode_one_hot(false),
+            Preset::FastLoad => self.pipeline(true).sequential_read_hint(true),
+            Preset::Minimal => Cifar10 {
+                channel_normalization: None,
+                preprocess: vec![],
+                ..self
+            }
+            .normalize(false)
+            .subtract_mean_image(false)
+            .encode_one_hot(false),
+        }
+    }
+}
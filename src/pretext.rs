@@ -0,0 +1,296 @@
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+use crate::ndarray::{Array, Array1, Array4};
+use crate::patch::locate_in_patch;
+use crate::{rot90, CifarResult, ResizeFilter, Split};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::error::Error;
+
+/// Copy of a `3 x 32 x 32` record rotated counter-clockwise by `quarter_turns` times 90 degrees
+pub(crate) fn rotate(record: &[u8], quarter_turns: u8) -> Vec<u8> {
+    let mut output = record.to_vec();
+    rot90(&mut output, quarter_turns as i32);
+    output
+}
+
+/// Settings for the jigsaw pretext task: images are cut into `patch_size x patch_size` patches that are shuffled
+/// by one of a fixed set of permutations, and the task is to predict which one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jigsaw {
+    patch_size: usize,
+    permutations: Vec<Vec<usize>>,
+}
+
+impl Jigsaw {
+    /// Uses the given permutation set, where output patch `i` shows input patch `permutation[i]`. Patches are
+    /// numbered row by row, and `patch_size` must divide 32
+    pub fn new(patch_size: usize, permutations: Vec<Vec<usize>>) -> Result<Jigsaw, Box<dyn Error>> {
+        if patch_size == 0 || 32 % patch_size != 0 {
+            return Err(format!("Patch size {} does not evenly divide 32", patch_size).into());
+        }
+        let num_patches = (32 / patch_size) * (32 / patch_size);
+        for (i, permutation) in permutations.iter().enumerate() {
+            let mut sorted = permutation.clone();
+            sorted.sort_unstable();
+            if !sorted.iter().copied().eq(0..num_patches) {
+                return Err(format!(
+                    "Permutation {} is not a permutation of the {} patches",
+                    i, num_patches
+                )
+                .into());
+            }
+        }
+        if permutations.is_empty() {
+            return Err("The permutation set is empty".into());
+        }
+        Ok(Jigsaw {
+            patch_size,
+            permutations,
+        })
+    }
+
+    /// Draws a set of `count` distinct random permutations, determined by `seed`
+    pub fn random(patch_size: usize, count: usize, seed: u64) -> Result<Jigsaw, Box<dyn Error>> {
+        if patch_size == 0 || 32 % patch_size != 0 {
+            return Err(format!("Patch size {} does not evenly divide 32", patch_size).into());
+        }
+        let num_patches = (32 / patch_size) * (32 / patch_size);
+        // The count of permutations only overflows for large grids, which are never short of permutations
+        if let Some(available) = (1..=num_patches).try_fold(1usize, |n, k| n.checked_mul(k)) {
+            if count > available {
+                return Err(format!(
+                    "Only {} permutations of {} patches exist",
+                    available, num_patches
+                )
+                .into());
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut permutations: Vec<Vec<usize>> = Vec::with_capacity(count);
+        while permutations.len() < count {
+            let mut permutation: Vec<usize> = (0..num_patches).collect();
+            permutation.shuffle(&mut rng);
+            if !permutations.contains(&permutation) {
+                permutations.push(permutation);
+            }
+        }
+        Jigsaw::new(patch_size, permutations)
+    }
+
+    /// The permutation set, indexed by label
+    pub fn permutations(&self) -> &[Vec<usize>] {
+        &self.permutations
+    }
+
+    /// Shuffles the patches of a `3 x 32 x 32` record by the permutation at `index`
+    pub fn apply(&self, record: &[u8], index: usize) -> Vec<u8> {
+        let permutation = &self.permutations[index];
+        let patches_per_row = 32 / self.patch_size;
+        let mut output = vec![0; 3072];
+        for y in 0..32 {
+            for x in 0..32 {
+                let (patch, row, col) = locate_in_patch(y, x, self.patch_size, patches_per_row);
+                let source = permutation[patch];
+                let sy = (source / patches_per_row) * self.patch_size + row;
+                let sx = (source % patches_per_row) * self.patch_size + col;
+                for c in 0..3 {
+                    output[c * 1024 + y * 32 + x] = record[c * 1024 + sy * 32 + sx];
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Noise added to images by `CifarResult::noisy_pairs`, on images scaled to `[0, 1]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseModel {
+    /// Additive Gaussian noise with the given standard deviation
+    Gaussian(f32),
+    /// Each pixel value is replaced by 0 or 1 (with equal odds) with the given probability
+    SaltPepper(f32),
+    /// Shot noise, treating each 8-bit pixel value as a photon count
+    Poisson,
+}
+
+/// Draws from the standard normal distribution with the Box-Muller transform
+pub(crate) fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f32 {
+    let u1: f32 = 1.0 - rng.gen::<f32>();
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
+/// Draws from a Poisson distribution, by Knuth's method for small means and a normal approximation otherwise
+fn poisson<R: Rng + ?Sized>(rng: &mut R, mean: f32) -> f32 {
+    if mean >= 30.0 {
+        return (mean + mean.sqrt() * standard_normal(rng)).round().max(0.0);
+    }
+    let limit = (-mean).exp();
+    let mut count = 0.0;
+    let mut product: f32 = rng.gen();
+    while product > limit {
+        count += 1.0;
+        product *= rng.gen::<f32>();
+    }
+    count
+}
+
+impl NoiseModel {
+    /// Noisy version of a pixel value in `[0, 1]`, clamped back into that range
+    fn apply<R: Rng + ?Sized>(&self, x: f32, rng: &mut R) -> f32 {
+        let noisy = match *self {
+            NoiseModel::Gaussian(sigma) => x + sigma * standard_normal(rng),
+            NoiseModel::SaltPepper(p) => match rng.gen::<f32>() < p {
+                true => rng.gen_range(0..2) as f32,
+                false => x,
+            },
+            NoiseModel::Poisson => poisson(rng, x * 255.0) / 255.0,
+        };
+        noisy.clamp(0.0, 1.0)
+    }
+}
+
+impl CifarResult {
+    /// Iterates over the RotNet pretext task for a split: every image rotated counter-clockwise by 0, 90, 180
+    /// and 270 degrees in turn, each paired with its rotation index from 0 to 3
+    pub fn rotations(&self, split: Split) -> impl Iterator<Item = (Vec<u8>, u8)> + '_ {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        data.chunks_exact(3072)
+            .flat_map(|record| (0..4).map(move |rotation| (rotate(record, rotation), rotation)))
+    }
+
+    /// Returns the RotNet pretext task for a split as flat images and rotation labels, in the order yielded by
+    /// `rotations`, so there are four times as many records as in the split
+    pub fn rotation_task(&self, split: Split) -> (Vec<u8>, Vec<u8>) {
+        let mut data = Vec::new();
+        let mut labels = Vec::new();
+        for (image, rotation) in self.rotations(split) {
+            data.extend(image);
+            labels.push(rotation);
+        }
+        (data, labels)
+    }
+
+    /// Returns the jigsaw pretext task for a split: each image with its patches shuffled by a permutation drawn
+    /// from `jigsaw`'s set, as flat images along with the index of the permutation used. The draws are
+    /// determined by `seed`
+    pub fn jigsaw_task(&self, split: Split, jigsaw: &Jigsaw, seed: u64) -> (Vec<u8>, Vec<usize>) {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut shuffled = Vec::with_capacity(data.len());
+        let mut labels = Vec::with_capacity(data.len() / 3072);
+        for record in data.chunks_exact(3072) {
+            let index = rng.gen_range(0..jigsaw.permutations.len());
+            shuffled.extend(jigsaw.apply(record, index));
+            labels.push(index);
+        }
+        (shuffled, labels)
+    }
+
+    /// Returns the colorization task for a split: single-channel grayscale inputs (ITU-R BT.601 luma) as
+    /// consecutive `1 x 32 x 32` images, and the original colors as consecutive `3 x 32 x 32` targets. Both are
+    /// divided by 255 when `normalize` is set, so inputs and targets are always on the same scale
+    pub fn colorization_task(&self, split: Split, normalize: bool) -> (Vec<f32>, Vec<f32>) {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        let scale = if normalize { 1.0 / 255.0 } else { 1.0 };
+        let mut inputs = Vec::with_capacity(data.len() / 3);
+        for record in data.chunks_exact(3072) {
+            inputs.extend((0..1024).map(|i| {
+                let (r, g, b) = (record[i], record[1024 + i], record[2048 + i]);
+                (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) * scale
+            }));
+        }
+        let targets = data.iter().map(|&x| x as f32 * scale).collect();
+        (inputs, targets)
+    }
+
+    /// Same as `colorization_task`, as `[N, 1, 32, 32]` inputs and `[N, 3, 32, 32]` targets
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn colorization_task_ndarray(
+        &self,
+        split: Split,
+        normalize: bool,
+    ) -> Result<(Array4<f32>, Array4<f32>), Box<dyn Error>> {
+        let (inputs, targets) = self.colorization_task(split, normalize);
+        let n = inputs.len() / 1024;
+        Ok((
+            crate::nd::array((n, 1, 32, 32), inputs)?,
+            crate::nd::array((n, 3, 32, 32), targets)?,
+        ))
+    }
+
+    /// Returns super-resolution pairs for a split: each image downsampled by `scale` with `filter` as consecutive
+    /// `3 x (32 / scale) x (32 / scale)` low-resolution inputs, and the original `3 x 32 x 32` images as
+    /// high-resolution targets. `scale` must divide 32
+    pub fn super_resolution_pairs(
+        &self,
+        split: Split,
+        scale: usize,
+        filter: ResizeFilter,
+    ) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+        if scale == 0 || 32 % scale != 0 {
+            return Err(format!("Scale factor {} does not evenly divide 32", scale).into());
+        }
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        let low_resolution = data
+            .chunks_exact(3072)
+            .flat_map(|record| filter.apply(record, 32 / scale))
+            .collect();
+        Ok((low_resolution, data.clone()))
+    }
+
+    /// Returns denoising pairs for a split as flat `3 x 32 x 32` images scaled to `[0, 1]`: the images with noise
+    /// from `model` added, and the clean images. The noise is determined by `seed`
+    pub fn noisy_pairs(&self, split: Split, model: NoiseModel, seed: u64) -> (Vec<f32>, Vec<f32>) {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let clean: Vec<f32> = data.iter().map(|&x| x as f32 / 255.0).collect();
+        let noisy = clean.iter().map(|&x| model.apply(x, &mut rng)).collect();
+        (noisy, clean)
+    }
+
+    /// Same as `rotation_task`, with the images as an `[N * 4, 3, 32, 32]` array
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn rotation_task_ndarray(
+        &self,
+        split: Split,
+    ) -> Result<(Array4<u8>, Array1<u8>), Box<dyn Error>> {
+        let (data, labels) = self.rotation_task(split);
+        Ok((
+            crate::nd::array((labels.len(), 3, 32, 32), data)?,
+            Array::from(labels),
+        ))
+    }
+}
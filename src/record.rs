@@ -0,0 +1,103 @@
+#[cfg(any(
+    feature = "to_ndarray_016",
+    feature = "to_ndarray_015",
+    feature = "to_ndarray_014",
+    feature = "to_ndarray_013"
+))]
+use crate::ndarray::ArrayView2;
+use crate::subset::{class_index, label_width};
+use crate::PixelPlaneOrder;
+use crate::{Cifar10, CifarResult, Split};
+use std::convert::TryInto;
+use std::error::Error;
+
+/// A borrowed view of a single record, for decoding straight into custom tensor types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordRef<'a> {
+    /// Class index of the record
+    pub label: u8,
+    /// `3 x 32 x 32` pixels, R, G and B planes in that order
+    pub pixels: &'a [u8; 3072],
+}
+
+impl<'a> RecordRef<'a> {
+    /// Value of `channel` (0 for red, 1 for green, 2 for blue) at column `x` and row `y`. Panics if any of them
+    /// is out of bounds
+    pub fn pixel(&self, x: usize, y: usize, channel: usize) -> u8 {
+        self.pixels[PixelPlaneOrder::CIFAR.index(channel, y, x)]
+    }
+
+    /// The red, green and blue values of row `y`, in that order
+    pub fn row(&self, y: usize) -> [&'a [u8; 32]; 3] {
+        assert!(y < 32, "Row is out of bounds");
+        let pixels: &'a [u8; 3072] = self.pixels;
+        [0, 1, 2].map(|channel| {
+            let start = channel * 1024 + y * 32;
+            pixels[start..start + 32]
+                .try_into()
+                .expect("Rows are 32 bytes")
+        })
+    }
+
+    /// The `32 x 32` plane of one channel, indexed `[[y, x]]`
+    #[cfg(any(
+        feature = "to_ndarray_016",
+        feature = "to_ndarray_015",
+        feature = "to_ndarray_014",
+        feature = "to_ndarray_013"
+    ))]
+    pub fn channel(&self, channel: usize) -> ArrayView2<'a, u8> {
+        assert!(channel < 3, "Channel is out of bounds");
+        let pixels: &'a [u8; 3072] = self.pixels;
+        crate::nd::view((32, 32), &pixels[channel * 1024..(channel + 1) * 1024])
+            .expect("Channels are 32 x 32")
+    }
+}
+
+/// Iterates over the records of a raw CIFAR-10 format buffer (one label byte followed by 3072 pixel bytes per
+/// record), such as the contents of a batch binary. Any trailing partial record is ignored
+pub fn records(buffer: &[u8]) -> impl Iterator<Item = RecordRef<'_>> {
+    buffer.chunks_exact(3073).map(|record| RecordRef {
+        label: record[0],
+        pixels: record[1..].try_into().expect("Records are 3073 bytes"),
+    })
+}
+
+impl CifarResult {
+    /// Iterates over the records of a split as borrowed views
+    pub fn records(&self, split: Split) -> impl Iterator<Item = RecordRef<'_>> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        let width = label_width(data, labels);
+        data.chunks_exact(3072)
+            .enumerate()
+            .map(move |(i, pixels)| RecordRef {
+                label: class_index(labels, width, i),
+                pixels: pixels.try_into().expect("Records are 3072 bytes"),
+            })
+    }
+}
+
+impl Cifar10 {
+    /// Returns the raw, concatenated contents of a split's binaries without decoding them, for use with `records`
+    pub fn raw_buffer(&self, split: Split) -> Result<Vec<u8>, Box<dyn Error>> {
+        let bin_paths = match split {
+            Split::Train => &self.training_bin_paths,
+            Split::Test => &self.testing_bin_paths,
+        };
+        #[cfg(feature = "archive")]
+        if let Some(archive) = self.prepare(&mut crate::LoadReport::default())? {
+            return crate::read_archived(self, &archive, bin_paths);
+        }
+        #[cfg(not(feature = "archive"))]
+        self.prepare(&mut crate::LoadReport::default())?;
+
+        let mut buffer = Vec::new();
+        for bin in bin_paths {
+            buffer.extend(self.read_bin(bin)?);
+        }
+        Ok(buffer)
+    }
+}
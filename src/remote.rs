@@ -0,0 +1,58 @@
+use crate::http::Transport;
+use std::error::Error;
+
+/// Whether a base path or URL refers to an object store rather than the local filesystem
+pub(crate) fn is_remote(path: &str) -> bool {
+    path.starts_with("s3://") || path.starts_with("gs://")
+}
+
+/// Translates an `s3://bucket/key` or `gs://bucket/key` URL into the HTTPS URL serving that object. S3 objects
+/// are fetched path-style from `AWS_ENDPOINT_URL` when it is set, so S3-compatible stores such as MinIO work too
+pub(crate) fn object_url(url: &str) -> Result<String, Box<dyn Error>> {
+    if let Some(rest) = url.strip_prefix("s3://") {
+        let (bucket, key) = split_bucket(rest)?;
+        #[cfg(feature = "s3")]
+        return Ok(match std::env::var("AWS_ENDPOINT_URL") {
+            Ok(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+            Err(_) => format!("https://{}.s3.amazonaws.com/{}", bucket, key),
+        });
+        #[cfg(not(feature = "s3"))]
+        return Err(format!("Reading s3://{}/{} requires the `s3` feature", bucket, key).into());
+    }
+    if let Some(rest) = url.strip_prefix("gs://") {
+        let (bucket, key) = split_bucket(rest)?;
+        #[cfg(feature = "gcs")]
+        return Ok(format!("https://storage.googleapis.com/{}/{}", bucket, key));
+        #[cfg(not(feature = "gcs"))]
+        return Err(format!("Reading gs://{}/{} requires the `gcs` feature", bucket, key).into());
+    }
+    Ok(url.to_string())
+}
+
+/// Joins path segments onto an object store URL, using `/` regardless of platform
+pub(crate) fn join(base: &str, segments: &[&str]) -> String {
+    let mut url = base.trim_end_matches('/').to_string();
+    for segment in segments {
+        let segment = segment.trim_matches('/');
+        if !segment.is_empty() {
+            url.push('/');
+            url.push_str(segment);
+        }
+    }
+    url
+}
+
+/// Downloads a whole object into memory
+pub(crate) fn fetch(url: &str, transport: Transport<'_>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buffer = Vec::new();
+    transport.fetch(&object_url(url)?, &mut buffer)?;
+    Ok(buffer)
+}
+
+fn split_bucket(rest: &str) -> Result<(&str, &str), Box<dyn Error>> {
+    match rest.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() => Ok((bucket, key.trim_start_matches('/'))),
+        None if !rest.is_empty() => Ok((rest, "")),
+        _ => Err(format!("Object store URL is missing a bucket: {}", rest).into()),
+    }
+}
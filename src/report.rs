@@ -0,0 +1,99 @@
+use crate::{Cifar10, CifarResult, Split};
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Wall time spent in each loading stage along with the amount of data read, returned by
+/// `Cifar10::build_with_report` to help diagnose slow data loading
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    /// Downloading the archive
+    pub download: Duration,
+    /// Extracting the archive to disk, or decompressing it into memory
+    pub extract: Duration,
+    /// Reading the binaries
+    pub read: Duration,
+    /// Splitting the binaries into records and parsing labels
+    pub decode: Duration,
+    /// Converting pixels to the output type
+    pub convert: Duration,
+    /// Bytes read from disk or the network, including the archive when it is read into memory
+    pub bytes_read: u64,
+    /// Estimate of the most memory held at once: the output, the archive contents if they are kept in memory,
+    /// and the largest binary buffer (three of them when pipelining)
+    pub peak_bytes_estimate: u64,
+    pub(crate) largest_buffer: u64,
+}
+
+impl LoadReport {
+    /// Total wall time across all stages
+    pub fn total(&self) -> Duration {
+        self.download + self.extract + self.read + self.decode + self.convert
+    }
+
+    /// Times a read of `f`, counting the bytes it returns
+    pub(crate) fn read(
+        &mut self,
+        f: impl FnOnce() -> Result<Vec<u8>, Box<dyn Error>>,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let start = Instant::now();
+        let buffer = f()?;
+        self.read += start.elapsed();
+        self.bytes_read += buffer.len() as u64;
+        self.largest_buffer = self.largest_buffer.max(buffer.len() as u64);
+        Ok(buffer)
+    }
+
+    /// Times `f`, adding the elapsed time to `decode`
+    pub(crate) fn decode<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.decode += start.elapsed();
+        result
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(f, "download: {:>10.1} ms", ms(self.download))?;
+        writeln!(f, "extract:  {:>10.1} ms", ms(self.extract))?;
+        writeln!(f, "read:     {:>10.1} ms", ms(self.read))?;
+        writeln!(f, "decode:   {:>10.1} ms", ms(self.decode))?;
+        writeln!(f, "convert:  {:>10.1} ms", ms(self.convert))?;
+        writeln!(f, "total:    {:>10.1} ms", ms(self.total()))?;
+        writeln!(f, "bytes read: {}", self.bytes_read)?;
+        write!(
+            f,
+            "peak memory estimate: {} bytes",
+            self.peak_bytes_estimate
+        )
+    }
+}
+
+impl Cifar10 {
+    /// Builds the dataset like `build`, also returning a `LoadReport` of how long each stage took and how much
+    /// was read
+    pub fn build_with_report(self) -> Result<(CifarResult, LoadReport), Box<dyn Error>> {
+        let mut report = LoadReport::default();
+        let archive = self.prepare(&mut report)?;
+        let (train_data, train_labels) =
+            crate::get_data(&self, Split::Train, archive.as_ref(), &mut report, |x| x)?;
+        let (test_data, test_labels) =
+            crate::get_data(&self, Split::Test, archive.as_ref(), &mut report, |x| x)?;
+
+        #[cfg(feature = "archive")]
+        let archived: usize = archive.iter().flat_map(|a| a.values()).map(Vec::len).sum();
+        #[cfg(not(feature = "archive"))]
+        let archived = 0;
+        // When pipelining, one binary can be queued and another read while the current one is parsed
+        let buffers = if self.pipeline { 3 } else { 1 };
+        let output = train_data.len() + train_labels.len() + test_data.len() + test_labels.len();
+        report.peak_bytes_estimate = (output + archived) as u64 + report.largest_buffer * buffers;
+
+        Ok((
+            CifarResult(train_data, train_labels, test_data, test_labels),
+            report,
+        ))
+    }
+}
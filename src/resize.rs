@@ -0,0 +1,188 @@
+use crate::augment::resized_crop;
+use crate::{Cifar10, CifarResult, Split};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// `(train_data, train_labels, test_data, test_labels)` with each image flattened to `3 x size x size` bytes
+pub type ResizedResult = (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>);
+
+/// Interpolation used when resizing images with `Cifar10::resize` or `CifarResult::super_resolution_pairs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResizeFilter {
+    Nearest,
+    Bilinear,
+}
+
+impl ResizeFilter {
+    fn name(&self) -> &'static str {
+        match self {
+            ResizeFilter::Nearest => "nearest",
+            ResizeFilter::Bilinear => "bilinear",
+        }
+    }
+
+    /// Resizes a `3 x 32 x 32` record to `3 x size x size`
+    pub(crate) fn apply(&self, record: &[u8], size: usize) -> Vec<u8> {
+        match self {
+            ResizeFilter::Bilinear => resized_crop(record, 32, (0, 0, 32, 32), size),
+            ResizeFilter::Nearest => {
+                let mut output = vec![0; 3 * size * size];
+                for c in 0..3 {
+                    for y in 0..size {
+                        for x in 0..size {
+                            output[c * size * size + y * size + x] =
+                                record[c * 1024 + (y * 32 / size) * 32 + x * 32 / size];
+                        }
+                    }
+                }
+                output
+            }
+        }
+    }
+}
+
+impl Cifar10 {
+    /// Returns the dataset with every image resized to `3 x size x size` by `build_resized`, using the size and
+    /// filter set with `resize` (or unchanged if none was set). Unless `resize_cache(false)` is set, the resized
+    /// images and their labels are cached next to the binaries, keyed by size, filter, the selected records, the
+    /// label encoding and the size and modification time of the binaries, so later runs skip parsing and resizing
+    /// altogether. Labels are returned as they are by `build`
+    pub fn build_resized(self) -> Result<ResizedResult, Box<dyn Error>> {
+        let (size, filter) = self.resize.unwrap_or((32, ResizeFilter::Nearest));
+        if size == 32 {
+            let CifarResult(train_data, train_labels, test_data, test_labels) = self.build()?;
+            return Ok((train_data, train_labels, test_data, test_labels));
+        }
+
+        let image_len = 3 * size * size;
+        let label_width = self.label_width();
+        let compression = self.cache_compression;
+        // Each cache entry holds a split's resized images followed by its labels
+        let entry_len = |split| crate::split_len(&self, split) * (image_len + label_width);
+        let cached = |split| {
+            let path = self.resize_cache_path(split, size, filter)?;
+            let contents = std::fs::read(path).ok()?;
+            #[cfg(feature = "zstd")]
+            if compression.is_some() {
+                return crate::compress::decompress(&contents, entry_len(split)).ok();
+            }
+            Some(contents).filter(|contents| contents.len() == entry_len(split))
+        };
+
+        let mut splits = Vec::with_capacity(2);
+        let mut missing = Vec::with_capacity(2);
+        for split in [Split::Train, Split::Test] {
+            let entry = match crate::split_len(&self, split) {
+                0 => Some(vec![]),
+                _ => cached(split),
+            };
+            if entry.is_none() {
+                missing.push(split);
+            }
+            splits.push(entry.map(|mut entry| {
+                let labels = entry.split_off(entry.len() / (image_len + label_width) * image_len);
+                (entry, labels)
+            }));
+        }
+
+        // Only the splits that aren't cached are parsed and resized
+        if !missing.is_empty() {
+            let CifarResult(train_data, train_labels, test_data, test_labels) =
+                self.clone().splits(&missing).build()?;
+            let loaded = [
+                (0, Split::Train, train_data, train_labels),
+                (1, Split::Test, test_data, test_labels),
+            ];
+            for (n, split, data, labels) in loaded {
+                if splits[n].is_some() {
+                    continue;
+                }
+                let data: Vec<u8> = data
+                    .chunks_exact(3072)
+                    .flat_map(|record| filter.apply(record, size))
+                    .collect();
+                if let Some(path) = self.resize_cache_path(split, size, filter) {
+                    std::fs::create_dir_all(
+                        path.parent().expect("Cache files are in a directory"),
+                    )?;
+                    let entry = [&data[..], &labels[..]].concat();
+                    match compression {
+                        #[cfg(feature = "zstd")]
+                        Some(level) => {
+                            std::fs::write(path, crate::compress::compress(&entry, level)?)?
+                        }
+                        _ => std::fs::write(path, entry)?,
+                    }
+                }
+                splits[n] = Some((data, labels));
+            }
+        }
+
+        let (test_data, test_labels) = splits.pop().flatten().expect("Both splits were loaded");
+        let (train_data, train_labels) = splits.pop().flatten().expect("Both splits were loaded");
+        Ok((train_data, train_labels, test_data, test_labels))
+    }
+
+    /// Where the resized images of a split are cached, if caching is enabled and the binaries are on local disk
+    fn resize_cache_path(
+        &self,
+        split: Split,
+        size: usize,
+        filter: ResizeFilter,
+    ) -> Option<PathBuf> {
+        // Filtered selections can't be keyed, as the predicate is opaque
+        if !self.resize_cache || self.is_remote() || self.filter.is_some() {
+            return None;
+        }
+        let (bin_paths, indices) = match split {
+            Split::Train => (&self.training_bin_paths, &self.train_indices),
+            Split::Test => (&self.testing_bin_paths, &self.test_indices),
+        };
+        // Replacing or rewriting a binary changes its size or modification time, which invalidates the entry
+        let sources = bin_paths
+            .iter()
+            .map(|bin| {
+                let metadata = std::fs::metadata(self.bin_path(bin)).ok()?;
+                let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+                Some((metadata.len(), modified))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut hasher = DefaultHasher::new();
+        (
+            bin_paths,
+            sources,
+            indices,
+            crate::split_len(self, split),
+            self.encode_one_hot,
+            &self.label_map,
+        )
+            .hash(&mut hasher);
+        let name = format!(
+            "{}_{}_{}_{:016x}.{}",
+            match split {
+                Split::Train => "train",
+                Split::Test => "test",
+            },
+            size,
+            filter.name(),
+            hasher.finish(),
+            if self.cache_compression.is_some() {
+                "bin.zst"
+            } else {
+                "bin"
+            }
+        );
+        Some(
+            self.resolved_base_path()
+                .join(&self.cifar_data_path)
+                .join("resize_cache")
+                .join(name),
+        )
+    }
+}
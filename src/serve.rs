@@ -0,0 +1,115 @@
+use crate::subset::{class_index, label_width};
+use crate::{record_to_png, CifarResult, Split};
+use rand::seq::index::sample;
+use std::error::Error;
+use std::io::Cursor;
+use tiny_http::{Header, Response, Server};
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+impl CifarResult {
+    /// Serves the dataset over HTTP on `addr` (e.g. `"127.0.0.1:8000"`), blocking the current thread. The available
+    /// routes are
+    /// - `/train/{idx}` and `/test/{idx}`: the image as a PNG, with its class index in the `X-Cifar-Label` header and
+    ///   embedded in the PNG metadata
+    /// - `/train/{idx}/label` and `/test/{idx}/label`: `{"split": "train", "index": idx, "label": class}`
+    /// - `/batch?size=n` (optionally `&split=test`): `n` random records in the CIFAR-10 binary format
+    ///
+    /// With the `tracing` feature, the bound address is logged once the server is listening
+    pub fn serve(&self, addr: &str) -> Result<(), Box<dyn Error>> {
+        let server = Server::http(addr).map_err(|e| e.to_string())?;
+        #[cfg(feature = "tracing")]
+        tracing::info!("serving CIFAR-10 on http://{}", server.server_addr());
+        for request in server.incoming_requests() {
+            let response = self.respond(request.url());
+            request.respond(response)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn respond(&self, url: &str) -> HttpResponse {
+        let (path, query) = match url.find('?') {
+            Some(i) => (&url[..i], &url[i + 1..]),
+            None => (url, ""),
+        };
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        let response = match segments.as_slice() {
+            ["batch"] => self.batch_response(query),
+            [split, idx] => self.image_response(split, idx),
+            [split, idx, "label"] => self.label_response(split, idx),
+            _ => None,
+        };
+        response.unwrap_or_else(|| Response::from_string("Not found").with_status_code(404))
+    }
+
+    fn record(&self, split: &str, idx: &str) -> Option<(Split, usize, &[u8], u8)> {
+        let (split, data, labels) = match split {
+            "train" => (Split::Train, &self.0, &self.1),
+            "test" => (Split::Test, &self.2, &self.3),
+            _ => return None,
+        };
+        let idx: usize = idx.parse().ok()?;
+        // Indices come straight from the URL, so they may be far past the end
+        let start = idx.checked_mul(3072)?;
+        let record = data.get(start..start.checked_add(3072)?)?;
+        let label = class_index(labels, label_width(data, labels), idx);
+        Some((split, idx, record, label))
+    }
+
+    fn image_response(&self, split: &str, idx: &str) -> Option<HttpResponse> {
+        let (_, _, record, label) = self.record(split, idx)?;
+        let png = record_to_png(record, Some(label)).ok()?;
+        Some(
+            Response::from_data(png)
+                .with_header(header("Content-Type", "image/png"))
+                .with_header(header("X-Cifar-Label", &label.to_string())),
+        )
+    }
+
+    fn label_response(&self, split: &str, idx: &str) -> Option<HttpResponse> {
+        let (split, idx, _, label) = self.record(split, idx)?;
+        let split = match split {
+            Split::Train => "train",
+            Split::Test => "test",
+        };
+        let json = format!(
+            r#"{{"split": "{}", "index": {}, "label": {}}}"#,
+            split, idx, label
+        );
+        Some(Response::from_string(json).with_header(header("Content-Type", "application/json")))
+    }
+
+    fn batch_response(&self, query: &str) -> Option<HttpResponse> {
+        let mut size = 1;
+        let (mut data, mut labels) = (&self.0, &self.1);
+        for pair in query.split('&') {
+            match pair.split_once('=') {
+                Some(("size", value)) => size = value.parse().ok()?,
+                Some(("split", "test")) => {
+                    data = &self.2;
+                    labels = &self.3;
+                }
+                _ => (),
+            }
+        }
+
+        let num_records = data.len() / 3072;
+        // The size comes straight from the query string, so never allocate for more records than there are
+        let size = size.min(num_records);
+        let width = label_width(data, labels);
+        let mut blob = Vec::with_capacity(size * 3073);
+        for idx in sample(&mut rand::thread_rng(), num_records, size) {
+            blob.push(class_index(labels, width, idx));
+            blob.extend_from_slice(&data[idx * 3072..(idx + 1) * 3072]);
+        }
+        Some(
+            Response::from_data(blob)
+                .with_header(header("Content-Type", "application/octet-stream")),
+        )
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("Invalid HTTP header")
+}
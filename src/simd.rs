@@ -0,0 +1,38 @@
+/// Appends `convert` applied to each byte of `src` to `dst`. The loop is plain Rust that the compiler vectorizes,
+/// and a second copy compiled with AVX2 enabled is picked at runtime when the CPU supports it, so prebuilt binaries
+/// get the wider instructions without `-C target-cpu=native`. NEON is part of the aarch64 baseline, so there the
+/// plain loop is already vectorized
+#[inline]
+pub(crate) fn extend_converted<T>(dst: &mut Vec<T>, src: &[u8], convert: impl Fn(u8) -> T) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if avx2_detected() {
+        // Safe as the CPU was just checked to support AVX2
+        unsafe { extend_converted_avx2(dst, src, convert) };
+        return;
+    }
+    extend_converted_scalar(dst, src, convert);
+}
+
+/// Whether `extend_converted` takes the AVX2 copy of the loop on this CPU
+pub(crate) fn avx2_detected() -> bool {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    return std::is_x86_feature_detected!("avx2");
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    return false;
+}
+
+#[inline(always)]
+pub(crate) fn extend_converted_scalar<T>(dst: &mut Vec<T>, src: &[u8], convert: impl Fn(u8) -> T) {
+    dst.extend(src.iter().map(|&x| convert(x)));
+}
+
+/// The plain loop compiled with AVX2 enabled. Must only be called when `avx2_detected` returns true
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn extend_converted_avx2<T>(
+    dst: &mut Vec<T>,
+    src: &[u8],
+    convert: impl Fn(u8) -> T,
+) {
+    extend_converted_scalar(dst, src, convert);
+}
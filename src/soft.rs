@@ -0,0 +1,264 @@
+use crate::{Cifar10, CifarResult, Split};
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+use std::path::Path;
+
+/// Per-record soft labels for knowledge distillation, such as a teacher's logits or class probabilities, with
+/// `width` values per record
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoftLabels {
+    /// `width` values for each row, rows one after another
+    pub values: Vec<f32>,
+    pub width: usize,
+    /// Index within the split of the record each row belongs to, or `None` when row `n` belongs to record `n`
+    pub indices: Option<Vec<usize>>,
+}
+
+impl SoftLabels {
+    /// Loads soft labels from a NumPy `.npy` file holding a `[N, width]` (or `[N]`) `float32` or `float64` array,
+    /// as written by `np.save`
+    pub fn from_npy(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let buffer = std::fs::read(path)?;
+        let (descr, shape, data) = npy::parse(&buffer)?;
+        let width = match shape[..] {
+            [_] => 1,
+            [_, width] => width,
+            _ => {
+                return Err(
+                    format!("Expected a 1 or 2 dimensional array, got shape {:?}", shape).into(),
+                )
+            }
+        };
+        let values = match descr {
+            "<f4" => data
+                .chunks_exact(4)
+                .map(|x| f32::from_le_bytes(x.try_into().expect("4 bytes")))
+                .collect(),
+            "<f8" => data
+                .chunks_exact(8)
+                .map(|x| f64::from_le_bytes(x.try_into().expect("8 bytes")) as f32)
+                .collect(),
+            _ => {
+                return Err(format!("Soft labels must be float32 or float64, got {}", descr).into())
+            }
+        };
+        Ok(SoftLabels {
+            values,
+            width,
+            indices: None,
+        })
+    }
+
+    /// Loads soft labels from a raw binary file of little-endian `f32` values, `width` per record
+    pub fn from_raw(path: impl AsRef<Path>, width: usize) -> Result<Self, Box<dyn Error>> {
+        let buffer = std::fs::read(path)?;
+        if width == 0 || buffer.len() % (4 * width) != 0 {
+            return Err(format!(
+                "{} bytes aren't a whole number of rows of {} f32 values",
+                buffer.len(),
+                width
+            )
+            .into());
+        }
+        Ok(SoftLabels {
+            values: buffer
+                .chunks_exact(4)
+                .map(|x| f32::from_le_bytes(x.try_into().expect("4 bytes")))
+                .collect(),
+            width,
+            indices: None,
+        })
+    }
+
+    /// Reads the record indices stored alongside soft labels, from a `.npy` file holding a 1 dimensional
+    /// `int32`, `int64`, `uint32` or `uint64` array
+    pub fn read_indices_npy(path: impl AsRef<Path>) -> Result<Vec<usize>, Box<dyn Error>> {
+        let buffer = std::fs::read(path)?;
+        let (descr, shape, data) = npy::parse(&buffer)?;
+        if shape.len() != 1 {
+            return Err(format!("Expected a 1 dimensional array, got shape {:?}", shape).into());
+        }
+        let index = |x: i128| {
+            usize::try_from(x).map_err(|_| format!("Record index {} is negative or too large", x))
+        };
+        let indices: Result<Vec<usize>, String> = match descr {
+            "<i4" => data
+                .chunks_exact(4)
+                .map(|x| index(i32::from_le_bytes(x.try_into().expect("4 bytes")) as i128))
+                .collect(),
+            "<i8" => data
+                .chunks_exact(8)
+                .map(|x| index(i64::from_le_bytes(x.try_into().expect("8 bytes")) as i128))
+                .collect(),
+            "<u4" => data
+                .chunks_exact(4)
+                .map(|x| index(u32::from_le_bytes(x.try_into().expect("4 bytes")) as i128))
+                .collect(),
+            "<u8" => data
+                .chunks_exact(8)
+                .map(|x| index(u64::from_le_bytes(x.try_into().expect("8 bytes")) as i128))
+                .collect(),
+            _ => return Err(format!("Record indices must be integers, got {}", descr).into()),
+        };
+        Ok(indices?)
+    }
+
+    /// Sets the record each row belongs to, for soft labels that only cover some records of a split
+    pub fn with_indices(mut self, indices: Vec<usize>) -> Result<Self, Box<dyn Error>> {
+        if indices.len() * self.width != self.values.len() {
+            return Err(format!(
+                "Got {} indices for {} rows of soft labels",
+                indices.len(),
+                self.values.len() / self.width.max(1)
+            )
+            .into());
+        }
+        self.indices = Some(indices);
+        Ok(self)
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.values.len() / self.width.max(1)
+    }
+
+    /// Whether there are no rows
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Converts logits into class probabilities with a softmax at the given temperature, as used for distillation
+    pub fn softmax(&self, temperature: f32) -> SoftLabels {
+        let mut values = self.values.clone();
+        for row in values.chunks_exact_mut(self.width.max(1)) {
+            let max = row.iter().fold(f32::NEG_INFINITY, |max, &x| max.max(x));
+            let mut sum = 0.0;
+            for x in row.iter_mut() {
+                *x = ((*x - max) / temperature).exp();
+                sum += *x;
+            }
+            for x in row.iter_mut() {
+                *x /= sum;
+            }
+        }
+        SoftLabels {
+            values,
+            width: self.width,
+            indices: self.indices.clone(),
+        }
+    }
+
+    /// Returns the rows of the given records of the split (in that order), one after another
+    pub fn select(&self, indices: &[usize]) -> Result<Vec<f32>, Box<dyn Error>> {
+        let rows: Option<HashMap<usize, usize>> = self.indices.as_ref().map(|indices| {
+            indices
+                .iter()
+                .enumerate()
+                .map(|(row, &i)| (i, row))
+                .collect()
+        });
+        let mut selected = Vec::with_capacity(indices.len() * self.width);
+        for &i in indices {
+            let row = match &rows {
+                Some(rows) => rows.get(&i).copied(),
+                None => Some(i).filter(|&i| i < self.len()),
+            };
+            let row = row.ok_or_else(|| format!("Record {} has no soft label", i))?;
+            selected.extend_from_slice(&self.values[row * self.width..(row + 1) * self.width]);
+        }
+        Ok(selected)
+    }
+}
+
+impl Cifar10 {
+    /// Builds the dataset along with the soft labels of the records of `split` that were loaded, aligned with
+    /// them row for row
+    pub fn build_with_soft_labels(
+        self,
+        split: Split,
+        soft_labels: &SoftLabels,
+    ) -> Result<(CifarResult, Vec<f32>), Box<dyn Error>> {
+        if self.filter.is_some() {
+            return Err(
+                "Soft labels can't be aligned with records selected by filter_records".into(),
+            );
+        }
+        let indices: Vec<usize> = match split {
+            Split::Train => self.train_indices.clone(),
+            Split::Test => self.test_indices.clone(),
+        }
+        .unwrap_or_else(|| (0..crate::split_len(&self, split)).collect());
+        let soft_labels = soft_labels.select(&indices)?;
+        Ok((self.build()?, soft_labels))
+    }
+}
+
+/// Just enough of the NumPy `.npy` format to read a C-ordered array
+mod npy {
+    use std::convert::TryInto;
+    use std::error::Error;
+
+    /// An array's dtype descriptor (such as `<f4`), its shape and its data
+    type Array<'a> = (&'a str, Vec<usize>, &'a [u8]);
+
+    /// Splits a `.npy` file into its dtype descriptor, its shape and its data
+    pub(super) fn parse(buffer: &[u8]) -> Result<Array<'_>, Box<dyn Error>> {
+        if buffer.len() < 10 || &buffer[..6] != b"\x93NUMPY" {
+            return Err("Not a .npy file".into());
+        }
+        let (header_len, start) = match buffer[6] {
+            1 => (u16::from_le_bytes([buffer[8], buffer[9]]) as usize, 10),
+            2 | 3 if buffer.len() >= 12 => (
+                u32::from_le_bytes(buffer[8..12].try_into().expect("4 bytes")) as usize,
+                12,
+            ),
+            version => return Err(format!("Unsupported .npy version {}", version).into()),
+        };
+        let header = buffer
+            .get(start..start + header_len)
+            .ok_or(".npy header is truncated")?;
+        let header = std::str::from_utf8(header)?;
+
+        let descr = value(header, "descr")?.trim_matches(|c| c == '\'' || c == '"');
+        if value(header, "fortran_order")? != "False" {
+            return Err("Fortran-ordered .npy arrays aren't supported".into());
+        }
+        let shape = value(header, "shape")?
+            .trim_matches(|c| c == '(' || c == ')')
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<usize>, _>>()?;
+
+        let size: usize = descr
+            .get(2..)
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(0);
+        let len = shape.iter().product::<usize>() * size;
+        let data = buffer
+            .get(start + header_len..start + header_len + len)
+            .ok_or(".npy data is truncated")?;
+        Ok((descr, shape, data))
+    }
+
+    /// The value of `key` in the header's Python dict literal
+    fn value<'a>(header: &'a str, key: &str) -> Result<&'a str, Box<dyn Error>> {
+        let missing = || format!(".npy header has no {}", key);
+        let rest = header
+            .find(&format!("'{}'", key))
+            .map(|i| &header[i + key.len() + 2..])
+            .ok_or_else(missing)?;
+        let rest = rest
+            .trim_start()
+            .strip_prefix(':')
+            .ok_or_else(missing)?
+            .trim_start();
+        let end = match rest.starts_with('(') {
+            true => rest.find(')').map(|i| i + 1),
+            false => rest.find([',', '}']),
+        };
+        Ok(rest[..end.ok_or_else(missing)?].trim())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::Cifar10;
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+
+/// What is known of one of the published binaries, which are matched by file name
+pub(crate) struct Reference {
+    pub(crate) binary: &'static str,
+    /// Number of records the binary holds
+    pub(crate) records: usize,
+    /// Labels of its first records
+    pub(crate) labels: &'static [u8],
+    /// Leading red values of its first record
+    pub(crate) red: &'static [u8],
+}
+
+/// Reference values of the published binaries. Only the sizes are known for all of them, so corruption past the
+/// listed labels and pixels goes unnoticed
+const REFERENCES: [Reference; 6] = [
+    Reference {
+        binary: "data_batch_1.bin",
+        records: 10_000,
+        labels: &[6, 9, 9, 4, 1, 1, 2, 7, 8, 3],
+        red: &[59, 43, 50, 68, 98, 119, 139, 145, 149, 149],
+    },
+    Reference {
+        binary: "data_batch_2.bin",
+        records: 10_000,
+        labels: &[],
+        red: &[],
+    },
+    Reference {
+        binary: "data_batch_3.bin",
+        records: 10_000,
+        labels: &[],
+        red: &[],
+    },
+    Reference {
+        binary: "data_batch_4.bin",
+        records: 10_000,
+        labels: &[],
+        red: &[],
+    },
+    Reference {
+        binary: "data_batch_5.bin",
+        records: 10_000,
+        labels: &[],
+        red: &[],
+    },
+    Reference {
+        binary: "test_batch.bin",
+        records: 10_000,
+        labels: &[3, 8, 8, 0, 6, 6, 1, 6, 3, 1],
+        red: &[],
+    },
+];
+
+/// Checks the full contents of a binary read for parsing against `REFERENCES`, if spot checks are enabled
+pub(crate) fn check_binary(
+    config: &Cifar10,
+    bin: &str,
+    contents: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    match config.spot_check {
+        true => check_against(&REFERENCES, bin, contents.len(), contents),
+        false => Ok(()),
+    }
+}
+
+/// Same as `check_binary` for a binary on disk that is only partly read, reading just its leading records
+pub(crate) fn check_file(config: &Cifar10, bin: &str) -> Result<(), Box<dyn Error>> {
+    let reference = match find(&REFERENCES, bin) {
+        Some(reference) if config.spot_check => reference,
+        _ => return Ok(()),
+    };
+    let mut f = std::fs::File::open(config.bin_path(bin))?;
+    let len = f.metadata()?.len() as usize;
+    if len != reference.records * 3073 {
+        return check_against(&REFERENCES, bin, len, &[]);
+    }
+    let mut head = vec![0u8; reference.labels.len().max(1) * 3073];
+    f.read_exact(&mut head)?;
+    check_against(&REFERENCES, bin, len, &head)
+}
+
+fn find<'a>(references: &'a [Reference], bin: &str) -> Option<&'a Reference> {
+    let name = Path::new(bin).file_name();
+    references
+        .iter()
+        .find(|reference| name == Some(reference.binary.as_ref()))
+}
+
+/// Compares a binary of `len` bytes against its entry in `references`, given at least its leading records in
+/// `head`. Binaries that aren't listed pass
+pub(crate) fn check_against(
+    references: &[Reference],
+    bin: &str,
+    len: usize,
+    head: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let reference = match find(references, bin) {
+        Some(reference) => reference,
+        None => return Ok(()),
+    };
+    if len != reference.records * 3073 {
+        return Err(format!(
+            "{} holds {} bytes rather than the {} of the published CIFAR-10 binary and may be truncated; delete \
+             and re-download it, or disable the check with spot_check(false) if it is a custom dataset",
+            bin,
+            len,
+            reference.records * 3073
+        )
+        .into());
+    }
+
+    let labels: Vec<u8> = head
+        .chunks_exact(3073)
+        .take(reference.labels.len())
+        .map(|record| record[0])
+        .collect();
+    if labels[..] != reference.labels[..] || !head[1..3073].starts_with(reference.red) {
+        return Err(format!(
+            "{} does not match the published CIFAR-10 binaries and may be corrupted; delete and re-download it, \
+             or disable the check with spot_check(false) if it is a custom dataset",
+            bin
+        )
+        .into());
+    }
+    Ok(())
+}
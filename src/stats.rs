@@ -0,0 +1,213 @@
+use crate::subset::{class_index, label_width};
+use crate::{class_name, CifarResult, Split};
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+/// Channel statistics of the images of one class, in pixel units from 0 to 255
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassStats {
+    pub count: usize,
+    /// Mean of the red, green and blue channels
+    pub channel_mean: [f64; 3],
+    /// Population standard deviation of the red, green and blue channels
+    pub channel_std: [f64; 3],
+}
+
+/// Per-class statistics of a split, as returned by `CifarResult::class_stats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitStats {
+    /// Statistics of each class, indexed by class
+    pub classes: Vec<ClassStats>,
+    /// L2 distance between the mean images of each pair of classes, indexed by class
+    pub mean_image_distances: Vec<Vec<f64>>,
+}
+
+/// An image whose channel statistics lie far from the rest of its split, as reported by
+/// `CifarResult::channel_outliers`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelOutlier {
+    /// Index of the image within its split
+    pub index: usize,
+    /// Largest robust z-score among the image's channel means and standard deviations
+    pub score: f64,
+    /// Mean of the image's red, green and blue channels
+    pub channel_mean: [f64; 3],
+    /// Population standard deviation of the image's red, green and blue channels
+    pub channel_std: [f64; 3],
+}
+
+impl SplitStats {
+    /// Writes one row per class with its count, channel means and standard deviations, and its mean-image distance
+    /// to every class
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let mut f = std::io::BufWriter::new(std::fs::File::create(path)?);
+        write!(f, "class,name,count,mean_r,mean_g,mean_b,std_r,std_g,std_b")?;
+        for class in 0..self.classes.len() {
+            write!(f, ",distance_{}", class_name(class as u8))?;
+        }
+        writeln!(f)?;
+
+        for (class, stats) in self.classes.iter().enumerate() {
+            write!(f, "{},{},{}", class, class_name(class as u8), stats.count)?;
+            for value in stats.channel_mean.iter().chain(&stats.channel_std) {
+                write!(f, ",{}", value)?;
+            }
+            for distance in self.mean_image_distances[class].iter() {
+                write!(f, ",{}", distance)?;
+            }
+            writeln!(f)?;
+        }
+        f.flush()?;
+        Ok(())
+    }
+}
+
+impl CifarResult {
+    /// Computes per-class channel means and standard deviations for a split, along with the distances between the
+    /// classes' mean images, for dataset write-ups and sanity checks of custom subsets. There is one entry per
+    /// class up to `num_classes`, and classes with no images have zeroed statistics
+    pub fn class_stats(&self, split: Split) -> SplitStats {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        let width = label_width(data, labels);
+        let num_classes = self.num_classes();
+
+        let mut counts = vec![0usize; num_classes];
+        let mut sums = vec![[0f64; 3072]; num_classes];
+        let mut squares = vec![[0f64; 3]; num_classes];
+        for (i, record) in data.chunks_exact(3072).enumerate() {
+            let class = class_index(labels, width, i) as usize;
+            counts[class] += 1;
+            for (j, &x) in record.iter().enumerate() {
+                sums[class][j] += x as f64;
+                squares[class][j / 1024] += (x as f64) * (x as f64);
+            }
+        }
+
+        let mean_images: Vec<Vec<f64>> = sums
+            .iter()
+            .zip(&counts)
+            .map(|(sum, &count)| sum.iter().map(|s| s / count.max(1) as f64).collect())
+            .collect();
+        let classes = (0..num_classes)
+            .map(|class| {
+                let n = (counts[class].max(1) * 1024) as f64;
+                let mut channel_mean = [0.0; 3];
+                let mut channel_std = [0.0; 3];
+                for c in 0..3 {
+                    let mean = mean_images[class][c * 1024..(c + 1) * 1024]
+                        .iter()
+                        .sum::<f64>()
+                        / 1024.0;
+                    channel_mean[c] = mean;
+                    channel_std[c] = (squares[class][c] / n - mean * mean).max(0.0).sqrt();
+                }
+                ClassStats {
+                    count: counts[class],
+                    channel_mean,
+                    channel_std,
+                }
+            })
+            .collect();
+
+        let mut mean_image_distances = vec![vec![0.0; num_classes]; num_classes];
+        for a in 0..num_classes {
+            for b in 0..num_classes {
+                mean_image_distances[a][b] = mean_images[a]
+                    .iter()
+                    .zip(&mean_images[b])
+                    .map(|(x, y)| (x - y) * (x - y))
+                    .sum::<f64>()
+                    .sqrt();
+            }
+        }
+
+        SplitStats {
+            classes,
+            mean_image_distances,
+        }
+    }
+
+    /// Flags the images of a split whose channel means or standard deviations are extreme outliers, such as the
+    /// near-blank or garbage images known to be in CIFAR-10. Each statistic is scored with a robust z-score (its
+    /// distance from the split's median in units of the scaled median absolute deviation), and images scoring above
+    /// `threshold` (6 is a reasonable start) are returned, highest score first. With the `export` feature,
+    /// `CifarResult::contact_sheet_of` can render the flagged images
+    pub fn channel_outliers(&self, split: Split, threshold: f64) -> Vec<ChannelOutlier> {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        let stats: Vec<([f64; 3], [f64; 3])> = data
+            .chunks_exact(3072)
+            .map(|record| {
+                let mut channel_mean = [0.0; 3];
+                let mut channel_std = [0.0; 3];
+                for (c, plane) in record.chunks_exact(1024).enumerate() {
+                    let mean = plane.iter().map(|&x| x as f64).sum::<f64>() / 1024.0;
+                    let variance = plane
+                        .iter()
+                        .map(|&x| (x as f64 - mean).powi(2))
+                        .sum::<f64>()
+                        / 1024.0;
+                    channel_mean[c] = mean;
+                    channel_std[c] = variance.sqrt();
+                }
+                (channel_mean, channel_std)
+            })
+            .collect();
+
+        let feature = |(mean, std): &([f64; 3], [f64; 3]), k: usize| match k {
+            0..=2 => mean[k],
+            _ => std[k - 3],
+        };
+        let mut scores = vec![0f64; stats.len()];
+        for k in 0..6 {
+            let values: Vec<f64> = stats.iter().map(|s| feature(s, k)).collect();
+            let center = median(values.clone());
+            // 1.4826 scales the median absolute deviation to a standard deviation for normal data
+            let spread = 1.4826 * median(values.iter().map(|v| (v - center).abs()).collect());
+            for (score, value) in scores.iter_mut().zip(&values) {
+                let z = match spread > 0.0 {
+                    true => (value - center).abs() / spread,
+                    false if *value == center => 0.0,
+                    false => f64::INFINITY,
+                };
+                *score = score.max(z);
+            }
+        }
+
+        let mut outliers: Vec<ChannelOutlier> = stats
+            .into_iter()
+            .zip(scores)
+            .enumerate()
+            .filter(|(_, (_, score))| *score > threshold)
+            .map(
+                |(index, ((channel_mean, channel_std), score))| ChannelOutlier {
+                    index,
+                    score,
+                    channel_mean,
+                    channel_std,
+                },
+            )
+            .collect();
+        outliers.sort_by(|a, b| b.score.total_cmp(&a.score));
+        outliers
+    }
+}
+
+/// Median of a set of values, or zero if there are none
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[mid - 1] + values[mid]) / 2.0,
+        _ => values[mid],
+    }
+}
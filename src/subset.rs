@@ -0,0 +1,255 @@
+use crate::augment::splitmix64;
+use crate::{Cifar10, CifarResult, Split};
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// How `CifarResult::shard` distributes records among workers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShardStrategy {
+    /// Each worker receives one contiguous block of records
+    Contiguous,
+    /// Worker `rank` receives records `rank`, `rank + world_size`, `rank + 2 * world_size`, ...
+    Strided,
+}
+
+/// Predicate set with `Cifar10::filter_records`, deciding from a record's pixels and label whether it is kept
+#[derive(Clone)]
+pub struct RecordFilter(pub(crate) Arc<FilterFn>);
+
+type FilterFn = dyn Fn(&[u8; 3072], u8) -> bool + Send + Sync;
+
+impl fmt::Debug for RecordFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RecordFilter")
+    }
+}
+
+impl RecordFilter {
+    /// Whether the record is kept
+    pub(crate) fn keep(&self, pixels: &[u8], label: u8) -> bool {
+        (self.0)(pixels.try_into().expect("Records are 3072 bytes"), label)
+    }
+}
+
+/// Number of label bytes per record, which is the number of classes when one-hot encoded and 1 otherwise
+pub(crate) fn label_width(data: &[u8], labels: &[u8]) -> usize {
+    match data.len() / 3072 {
+        0 => 1,
+        n => labels.len() / n,
+    }
+}
+
+/// Class index of record `i`, whether its label is one-hot encoded or not
+pub(crate) fn class_index(labels: &[u8], width: usize, i: usize) -> u8 {
+    match width {
+        1 => labels[i],
+        _ => labels[i * width..(i + 1) * width]
+            .iter()
+            .position(|&x| x == 1)
+            .unwrap_or(0) as u8,
+    }
+}
+
+/// Copies the records at `indices` (in that order) out of a split's data and labels
+pub(crate) fn select(data: &[u8], labels: &[u8], indices: &[usize]) -> (Vec<u8>, Vec<u8>) {
+    let width = label_width(data, labels);
+    let mut selected_data = Vec::with_capacity(indices.len() * 3072);
+    let mut selected_labels = Vec::with_capacity(indices.len() * width);
+    for &i in indices {
+        selected_data.extend_from_slice(&data[i * 3072..(i + 1) * 3072]);
+        selected_labels.extend_from_slice(&labels[i * width..(i + 1) * width]);
+    }
+    (selected_data, selected_labels)
+}
+
+pub(crate) fn shard_indices(
+    num_records: usize,
+    rank: usize,
+    world_size: usize,
+    strategy: ShardStrategy,
+) -> Vec<usize> {
+    match strategy {
+        ShardStrategy::Contiguous => {
+            (rank * num_records / world_size..(rank + 1) * num_records / world_size).collect()
+        }
+        ShardStrategy::Strided => (rank..num_records).step_by(world_size).collect(),
+    }
+}
+
+/// Whether record `index` falls in the `p` fraction picked by `seed`, decided by hashing the index alone so the
+/// answer is the same on every run and machine
+pub(crate) fn in_fraction(index: usize, p: f64, seed: u64) -> bool {
+    let hash = splitmix64(splitmix64(seed) ^ index as u64);
+    ((hash >> 11) as f64 / (1u64 << 53) as f64) < p
+}
+
+impl Cifar10 {
+    /// Keep a reproducible `p` fraction of each split, choosing records by a hash of their index and `seed` rather
+    /// than by shuffling, so the same records are picked on every run and machine and kept in their original
+    /// order. Narrows any selection made so far with `indices` or `take_first` (or else the record counts), so
+    /// set those first
+    pub fn fraction(mut self, p: f64, seed: u64) -> Self {
+        assert!((0.0..=1.0).contains(&p), "Fraction must be within [0, 1]");
+        for split in [Split::Train, Split::Test] {
+            let (indices, take, num_records) = match split {
+                Split::Train => (
+                    &mut self.train_indices,
+                    self.train_take,
+                    self.num_records_train,
+                ),
+                Split::Test => (
+                    &mut self.test_indices,
+                    self.test_take,
+                    self.num_records_test,
+                ),
+            };
+            let selected = match indices.take() {
+                Some(indices) => indices,
+                None => (0..take.unwrap_or(num_records)).collect(),
+            };
+            *indices = Some(
+                selected
+                    .into_iter()
+                    .filter(|&i| in_fraction(i, p, seed))
+                    .collect(),
+            );
+        }
+        self
+    }
+}
+
+impl CifarResult {
+    /// Returns the class index of each record of a split, borrowing the labels when they are already stored as
+    /// indices rather than taking the argmax of one-hot labels
+    pub fn label_indices(&self, split: Split) -> Cow<'_, [u8]> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        match label_width(data, labels) {
+            1 => Cow::Borrowed(labels),
+            width => Cow::Owned(
+                (0..data.len() / 3072)
+                    .map(|i| class_index(labels, width, i))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Same as `label_indices`, widened to the `i64` class indices that torch losses such as `CrossEntropyLoss`
+    /// expect
+    pub fn label_indices_i64(&self, split: Split) -> Vec<i64> {
+        self.label_indices(split)
+            .iter()
+            .map(|&x| x as i64)
+            .collect()
+    }
+
+    /// Number of classes the labels can take: the width of one-hot labels, or else the highest class index plus
+    /// one and at least the ten CIFAR-10 classes. Class indices can't tell that `merge_classes` left fewer than
+    /// ten, so keep such results one-hot encoded
+    pub fn num_classes(&self) -> usize {
+        let splits = [(&self.0, &self.1), (&self.2, &self.3)];
+        match splits
+            .iter()
+            .map(|(data, labels)| label_width(data, labels))
+            .find(|&width| width > 1)
+        {
+            Some(width) => width,
+            None => self
+                .1
+                .iter()
+                .chain(&self.3)
+                .max()
+                .map_or(0, |&max| max as usize + 1)
+                .max(10),
+        }
+    }
+
+    /// Returns the one-hot labels of a split, `num_classes` values per record, borrowing them when they are
+    /// already one-hot encoded and otherwise generating them from the class indices, so only callers that need
+    /// one-hot labels pay for them
+    pub fn one_hot_labels(&self, split: Split) -> Cow<'_, [u8]> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        match label_width(data, labels) {
+            1 => {
+                let width = self.num_classes();
+                let mut one_hot = vec![0; labels.len() * width];
+                for (i, &class) in labels.iter().enumerate() {
+                    one_hot[i * width + class as usize] = 1;
+                }
+                Cow::Owned(one_hot)
+            }
+            _ => Cow::Borrowed(labels),
+        }
+    }
+
+    /// Returns the indices of every record of each class within a split, one list per class up to `num_classes`,
+    /// in one pass over the labels, so samplers and subset builders can look up the members of a class directly
+    pub fn class_indices(&self, split: Split) -> Vec<Vec<usize>> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        let width = label_width(data, labels);
+
+        let mut indices = vec![vec![]; self.num_classes()];
+        for i in 0..data.len() / 3072 {
+            indices[class_index(labels, width, i) as usize].push(i);
+        }
+        indices
+    }
+
+    /// Deterministically partitions both splits across `world_size` workers and keeps the shard belonging to
+    /// `rank`, so data-parallel workers receive non-overlapping records
+    pub fn shard(self, rank: usize, world_size: usize, strategy: ShardStrategy) -> CifarResult {
+        assert!(
+            rank < world_size,
+            "Rank {} is out of range for a world size of {}",
+            rank,
+            world_size
+        );
+        let train = shard_indices(self.0.len() / 3072, rank, world_size, strategy);
+        let test = shard_indices(self.2.len() / 3072, rank, world_size, strategy);
+
+        let (train_data, train_labels) = select(&self.0, &self.1, &train);
+        let (test_data, test_labels) = select(&self.2, &self.3, &test);
+        CifarResult(train_data, train_labels, test_data, test_labels)
+    }
+
+    /// Reorders the training split by ascending difficulty, given one score per training record (such as
+    /// c-scores negated, so that harder records score higher), for curriculum learning. Returns the reordered
+    /// result along with the permutation used, where entry `i` is the original index of the `i`th record. Ties
+    /// keep their original order
+    pub fn order_by_scores(
+        self,
+        scores: &[f32],
+    ) -> Result<(CifarResult, Vec<usize>), Box<dyn Error>> {
+        let num_records = self.0.len() / 3072;
+        if scores.len() != num_records {
+            return Err(format!(
+                "Expected {} scores, one per training record, but got {}",
+                num_records,
+                scores.len()
+            )
+            .into());
+        }
+        if let Some(i) = scores.iter().position(|score| score.is_nan()) {
+            return Err(format!("Score {} is NaN", i).into());
+        }
+
+        let mut permutation: Vec<usize> = (0..num_records).collect();
+        permutation.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+        let (train_data, train_labels) = select(&self.0, &self.1, &permutation);
+        Ok((
+            CifarResult(train_data, train_labels, self.2, self.3),
+            permutation,
+        ))
+    }
+}
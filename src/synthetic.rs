@@ -0,0 +1,49 @@
+use crate::pretext::standard_normal;
+use crate::{CifarResult, Split};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Distribution of the pixels of CIFAR-shaped noise images, used as out-of-distribution baselines and to sanity
+/// check training pipelines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntheticNoise {
+    /// Independent uniformly random pixels
+    Uniform,
+    /// Independent normally distributed pixels, with `mean` and `std` given for pixels scaled to `[0, 1]` and
+    /// values clamped to that range
+    Gaussian { mean: f32, std: f32 },
+}
+
+impl SyntheticNoise {
+    /// Generates `count` flat `3 x 32 x 32` records, the same ones for the same seed
+    pub fn images(&self, count: usize, seed: u64) -> Vec<u8> {
+        self.sample(count, &mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Generates a dataset of noise images with uniformly random classes, `train_count` training and `test_count`
+    /// test records, laid out as `Cifar10::build` returns them with its default one-hot labels
+    pub fn dataset(&self, train_count: usize, test_count: usize, seed: u64) -> CifarResult {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let train_data = self.sample(train_count, &mut rng);
+        let train_labels: Vec<u8> = (0..train_count).map(|_| rng.gen_range(0..10)).collect();
+        let test_data = self.sample(test_count, &mut rng);
+        let test_labels: Vec<u8> = (0..test_count).map(|_| rng.gen_range(0..10)).collect();
+        let indices = CifarResult(train_data, train_labels, test_data, test_labels);
+        let train_labels = indices.one_hot_labels(Split::Train).into_owned();
+        let test_labels = indices.one_hot_labels(Split::Test).into_owned();
+        let CifarResult(train_data, _, test_data, _) = indices;
+        CifarResult(train_data, train_labels, test_data, test_labels)
+    }
+
+    fn sample(&self, count: usize, rng: &mut StdRng) -> Vec<u8> {
+        match *self {
+            SyntheticNoise::Uniform => (0..count * 3072).map(|_| rng.gen()).collect(),
+            SyntheticNoise::Gaussian { mean, std } => (0..count * 3072)
+                .map(|_| {
+                    let x = mean + std * standard_normal(rng);
+                    (x.clamp(0.0, 1.0) * 255.0).round() as u8
+                })
+                .collect(),
+        }
+    }
+}
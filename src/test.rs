@@ -38,3 +38,2650 @@ fn test_download_extract_build_f32() {
         .to_ndarray::<f32>()
         .unwrap();
 }
+
+#[test]
+fn test_near_duplicates() {
+    let train: Vec<u8> = (0..3 * 3072).map(|i| (i * 31 % 251) as u8).collect();
+    let mut test: Vec<u8> = (0..2 * 3072).map(|i| (i * 17 % 241) as u8).collect();
+    test[3072..].copy_from_slice(&train[2 * 3072..]);
+    let result = CifarResult(train, vec![0; 3], test, vec![0; 2]);
+
+    let pairs = result.near_duplicates(0);
+    assert!(pairs.contains(&DuplicatePair {
+        train_index: 2,
+        test_index: 1,
+        distance: 0
+    }));
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_provenance() {
+    let config = Cifar10::default();
+    let provenance = config.provenance(10_001).unwrap();
+    assert_eq!(provenance.split, Split::Train);
+    assert_eq!(provenance.file, "data_batch_2.bin");
+    assert_eq!(provenance.index_in_file, 1);
+
+    let provenance = config.provenance(50_000).unwrap();
+    assert_eq!(provenance.split, Split::Test);
+    assert_eq!(provenance.file, "test_batch.bin");
+    assert_eq!(provenance.index_in_file, 0);
+}
+
+#[cfg(all(not(feature = "download"), feature = "to_ndarray_016"))]
+#[test]
+fn test_build_as_flat_u8() {
+    let (train_data, train_labels, test_data, test_labels) = Cifar10::default()
+        .encode_one_hot(false)
+        .build_as_flat_u8()
+        .unwrap();
+    assert_eq!(train_data.shape(), &[50_000, 3072]);
+    assert_eq!(train_labels.shape(), &[50_000, 1]);
+    assert_eq!(test_data.shape(), &[10_000, 3072]);
+    assert_eq!(test_labels.shape(), &[10_000, 1]);
+}
+
+#[cfg(feature = "to_ndarray_016")]
+#[test]
+fn test_to_ndarray_f_order() {
+    let train: Vec<u8> = (0..2 * 3072).map(|i| (i % 256) as u8).collect();
+    let labels = vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+    let result = CifarResult(train.clone(), labels.clone(), train.clone(), labels);
+
+    let (train_data, train_labels, _, _) = result.to_ndarray_f_order::<f32>().unwrap();
+    assert!(train_data.t().is_standard_layout());
+    assert_eq!(
+        train_data[[1, 2, 3, 4]],
+        train[3072 + 2048 + 3 * 32 + 4] as f32
+    );
+    assert_eq!(train_labels[[1, 1]], 1.0);
+
+    // Labels stored as class indices are one per record
+    let result = CifarResult(train.clone(), vec![3, 7], vec![], vec![]);
+    let (_, train_labels, _, _) = result.to_ndarray_f_order::<f32>().unwrap();
+    assert_eq!(train_labels.shape(), &[2, 1]);
+    assert_eq!(train_labels[[1, 0]], 7.0);
+}
+
+#[test]
+fn test_shard() {
+    let data: Vec<u8> = (0..10).flat_map(|i| vec![i as u8; 3072]).collect();
+    let labels: Vec<u8> = (0..10).collect();
+
+    for strategy in [ShardStrategy::Contiguous, ShardStrategy::Strided].iter() {
+        let mut seen: Vec<u8> = (0..3)
+            .flat_map(|rank| {
+                CifarResult(data.clone(), labels.clone(), vec![], vec![])
+                    .shard(rank, 3, *strategy)
+                    .1
+            })
+            .collect();
+        seen.sort_unstable();
+        assert_eq!(seen, labels);
+    }
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_stream_batches_arrow() {
+    let data: Vec<u8> = (0..5 * 3072).map(|i| (i % 256) as u8).collect();
+    let result = CifarResult(data.clone(), vec![0, 1, 2, 3, 4], data, vec![5, 6, 7, 8, 9]);
+
+    let mut stream = Vec::new();
+    result.stream_batches_arrow(&mut stream, 2).unwrap();
+
+    let reader = arrow_ipc::reader::StreamReader::try_new(stream.as_slice(), None).unwrap();
+    let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+    assert_eq!(batches.len(), 6);
+    assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 10);
+    assert!(result.stream_batches_arrow(Vec::new(), 0).is_err());
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_indices() {
+    let full = Cifar10::default().encode_one_hot(false).build().unwrap();
+    let subset = Cifar10::default()
+        .encode_one_hot(false)
+        .indices(Split::Train, &[12_345, 3, 49_999])
+        .indices(Split::Test, &[])
+        .build()
+        .unwrap();
+
+    assert_eq!(subset.1, vec![full.1[12_345], full.1[3], full.1[49_999]]);
+    assert_eq!(&subset.0[..3072], &full.0[12_345 * 3072..12_346 * 3072]);
+    assert!(subset.2.is_empty());
+}
+
+#[cfg(all(feature = "archive", not(feature = "download")))]
+#[test]
+fn test_read_from_archive() {
+    use flate2::{write::GzEncoder, Compression};
+
+    let base_path = std::env::temp_dir().join("cifar_ten_test_read_from_archive");
+    std::fs::create_dir_all(&base_path).unwrap();
+    let record = |label: u8| {
+        let mut record = vec![label; 3073];
+        record[1] = 255 - label;
+        record
+    };
+
+    let tar_gz = std::fs::File::create(base_path.join("cifar-10-binary.tar.gz")).unwrap();
+    let mut builder = tar::Builder::new(GzEncoder::new(tar_gz, Compression::fast()));
+    for (name, labels) in [("data_batch_1.bin", [1, 2]), ("test_batch.bin", [3, 4])] {
+        let contents: Vec<u8> = labels.iter().flat_map(|&label| record(label)).collect();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("cifar-10-batches-bin/{}", name),
+                contents.as_slice(),
+            )
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let result = Cifar10::default()
+        .base_path(base_path.to_str().unwrap())
+        .training_bin_paths(vec!["data_batch_1.bin".into()])
+        .num_records_train(2)
+        .num_records_test(2)
+        .encode_one_hot(false)
+        .extract_to_disk(false)
+        .spot_check(false)
+        .build()
+        .unwrap();
+    std::fs::remove_dir_all(&base_path).unwrap();
+
+    assert_eq!(result.1, vec![1, 2]);
+    assert_eq!(result.3, vec![3, 4]);
+    assert_eq!(result.2[3072], 255 - 4);
+}
+
+#[test]
+fn test_augmentation_stages() {
+    let record: Vec<u8> = (0..3072).map(|i| (i % 32) as u8).collect();
+    let augmentation = Augmentation::new(vec![
+        Transform::HorizontalFlip { p: 1.0 },
+        Transform::RandomCrop { padding: 0 },
+    ]);
+    let stages = augmentation.stages(&record, &mut rand::thread_rng());
+
+    assert_eq!(stages.len(), 3);
+    assert_eq!(stages[0], record);
+    assert_eq!(stages[1][0], 31);
+    assert_eq!(stages[2], stages[1]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_augmentation_serde() {
+    let config =
+        r#"{"transforms": [{"HorizontalFlip": {"p": 0.5}}, {"RandomCrop": {"padding": 4}}]}"#;
+    let augmentation: Augmentation = serde_json::from_str(config).unwrap();
+    assert_eq!(
+        augmentation,
+        Augmentation::new(vec![
+            Transform::HorizontalFlip { p: 0.5 },
+            Transform::RandomCrop { padding: 4 },
+        ])
+    );
+
+    let round_trip: Augmentation =
+        serde_json::from_str(&serde_json::to_string(&augmentation).unwrap()).unwrap();
+    assert_eq!(round_trip, augmentation);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_cifar10_serde() {
+    let config: Cifar10 =
+        serde_json::from_str(r#"{"base_path": "datasets/", "num_records_test": 500}"#).unwrap();
+    assert_eq!(config.base_path, "datasets/");
+    assert_eq!(config.num_records_test, 500);
+    assert_eq!(config.num_records_train, 50_000);
+
+    let logged = serde_json::to_value(&config).unwrap();
+    assert_eq!(logged["testing_bin_paths"][0], "test_batch.bin");
+}
+
+#[cfg(all(not(feature = "download"), feature = "to_ndarray_016"))]
+#[test]
+fn test_build_as_flat_f32() {
+    let (train_u8, _, _, _) = Cifar10::default().build_as_flat_u8().unwrap();
+    let (train_data, train_labels, test_data, test_labels) = Cifar10::default()
+        .normalize(true)
+        .build_as_flat_f32()
+        .unwrap();
+    assert_eq!(train_data.shape(), &[50_000, 3072]);
+    assert_eq!(train_labels.shape(), &[50_000, 10]);
+    assert_eq!(test_data.shape(), &[10_000, 3072]);
+    assert_eq!(test_labels.shape(), &[10_000, 10]);
+    assert_eq!(train_data[[123, 456]], train_u8[[123, 456]] as f32 / 255.0);
+}
+
+#[cfg(all(not(feature = "download"), feature = "to_ndarray_016"))]
+#[test]
+fn test_subtract_mean_image() {
+    let ((raw, _, raw_test, _), _) = Cifar10::default()
+        .take_first(Split::Train, 1000)
+        .build_as_flat_f32_with_mean()
+        .unwrap();
+    let ((train_data, _, test_data, _), mean) = Cifar10::default()
+        .take_first(Split::Train, 1000)
+        .subtract_mean_image(true)
+        .build_as_flat_f32_with_mean()
+        .unwrap();
+    assert_eq!(mean.len(), 3072);
+    let expected = raw.column(7).sum() / 1000.0;
+    assert!((mean[7] - expected).abs() < 1e-3);
+    assert!(train_data.column(7).sum().abs() < 1e-1);
+    assert_eq!(test_data[[3, 7]], raw_test[[3, 7]] - mean[7]);
+}
+
+#[test]
+fn test_cifar10_writer() {
+    let dir = std::env::temp_dir().join("cifar_ten_test_cifar10_writer");
+    let mut writer = Cifar10Writer::new(&dir, "data_batch").records_per_file(2);
+    for label in 0..3 {
+        writer.write_record(label, &[label * 10; 3072]).unwrap();
+    }
+    assert!(writer.write_record(10, &[0; 3072]).is_err());
+    let files = writer.finish().unwrap();
+
+    assert_eq!(files.len(), 2);
+    let second = std::fs::read(&files[1]).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(second.len(), 3073);
+    assert_eq!(&second[..2], &[2, 20]);
+}
+
+#[test]
+fn test_cifar10_writer_f32() {
+    let dir = std::env::temp_dir().join("cifar_ten_test_cifar10_writer_f32");
+    let mut pixels = vec![0.5; 3072];
+    pixels[0] = -0.25;
+    pixels[1] = 1.5;
+    pixels[2] = 1.0;
+
+    let mut strict = Cifar10Writer::new(&dir, "strict");
+    assert!(strict.write_record_f32(0, &pixels).is_err());
+    pixels[3] = f32::NAN;
+    let mut writer = Cifar10Writer::new(&dir, "clamped").clamp_f32(true);
+    assert!(writer.write_record_f32(0, &pixels).is_err());
+    pixels[3] = 0.5;
+    writer.write_record_f32(6, &pixels).unwrap();
+    let report = writer.f32_report();
+    let files = writer.finish().unwrap();
+    assert!(strict.finish().unwrap().is_empty());
+
+    let written = std::fs::read(&files[0]).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(&written[..5], &[6, 0, 255, 255, 128]);
+    assert_eq!(
+        (report.values, report.clipped_low, report.clipped_high),
+        (3072, 1, 1)
+    );
+    assert_eq!(report.clipped(), 2);
+}
+
+#[test]
+fn test_write_subset() {
+    let data: Vec<u8> = (0..4).flat_map(|i| vec![i as u8; 3072]).collect();
+    let mut one_hot = vec![0; 40];
+    for (i, label) in [3, 1, 4, 1].iter().enumerate() {
+        one_hot[i * 10 + label] = 1;
+    }
+    let result = CifarResult(vec![], vec![], data, one_hot);
+
+    let path = std::env::temp_dir().join("cifar_ten_test_write_subset.bin");
+    result.write_subset(Split::Test, &[2, 0], &path).unwrap();
+    let written = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(written.len(), 2 * 3073);
+    assert_eq!(&written[..2], &[4, 2]);
+    assert_eq!(&written[3073..3075], &[3, 0]);
+    assert!(result.write_subset(Split::Test, &[4], &path).is_err());
+}
+
+#[test]
+fn test_contains_image() {
+    let train: Vec<u8> = (0..2).flat_map(|i| vec![i as u8; 3072]).collect();
+    let test: Vec<u8> = (2..5).flat_map(|i| vec![i as u8; 3072]).collect();
+    let result = CifarResult(train, vec![0, 0], test, vec![0, 0, 0]);
+
+    assert_eq!(
+        result.contains_image(&vec![1; 3072]),
+        Some((Split::Train, 1))
+    );
+    assert_eq!(
+        result.contains_image(&[4; 3072][..]),
+        Some((Split::Test, 2))
+    );
+    assert_eq!(result.contains_image(&vec![7; 3072]), None);
+}
+
+#[test]
+fn test_multi_crop() {
+    let record = vec![100; 3072];
+    let crops = MultiCrop::default().crops(&record, &mut rand::thread_rng());
+
+    assert_eq!(crops.len(), 8);
+    assert!(crops[..2].iter().all(|crop| crop.len() == 3 * 32 * 32));
+    assert!(crops[2..].iter().all(|crop| crop.len() == 3 * 16 * 16));
+    assert!(crops.iter().flatten().all(|&x| x == 100));
+}
+
+#[test]
+fn test_contrastive_pairs() {
+    let data: Vec<u8> = (0..5).flat_map(|i| vec![i as u8; 3072]).collect();
+    let result = CifarResult(data, vec![0; 5], vec![], vec![]);
+    let augmentation = Augmentation::new(vec![Transform::HorizontalFlip { p: 0.5 }]);
+
+    let batches: Vec<ContrastiveBatch> = result
+        .contrastive_pairs(Split::Train, &augmentation, 2, 7)
+        .collect();
+    assert_eq!(batches.len(), 3);
+
+    let mut indices: Vec<usize> = batches.iter().flat_map(|b| b.indices.clone()).collect();
+    indices.sort_unstable();
+    assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+    for batch in &batches {
+        assert_eq!(batch.view_a.len(), batch.indices.len() * 3072);
+        assert_eq!(batch.view_a[0], batch.indices[0] as u8);
+        assert_eq!(batch.view_b[0], batch.indices[0] as u8);
+    }
+}
+
+#[test]
+fn test_concat() {
+    let cifar = CifarResult(vec![0; 2 * 3072], vec![1, 9], vec![0; 3072], vec![3]);
+    let extra = CifarResult(vec![1; 2 * 3072], vec![0, 1], vec![], vec![]);
+
+    let (merged, class_names) = concat(
+        vec![
+            ConcatInput::new(cifar, &CLASS_NAMES),
+            ConcatInput::new(extra, &["car", "bicycle"]),
+        ],
+        &[("automobile", "car")],
+    )
+    .unwrap();
+
+    assert_eq!(class_names.len(), 11);
+    assert_eq!(class_names[1], "car");
+    assert_eq!(class_names[10], "bicycle");
+    assert_eq!(merged.1, vec![1, 9, 1, 10]);
+    assert_eq!(merged.0.len(), 4 * 3072);
+    assert_eq!(merged.3, vec![3]);
+}
+
+#[cfg(feature = "to_ndarray_016")]
+#[test]
+fn test_patchify() {
+    let data = Array::from_shape_fn((2, 3, 32, 32), |(n, c, y, x)| {
+        (n * 100 + c * 10 + y / 8 * 4 + x / 8) as u8
+    });
+    let patches = patchify(&data, 8, false);
+
+    assert_eq!(patches.shape(), &[2, 16, 192]);
+    assert_eq!(patches[[1, 6, 0]], 106.0);
+    assert_eq!(patches[[1, 6, 2]], 126.0);
+    assert_eq!(patchify(&data, 8, true)[[0, 1, 1]], 11.0 / 255.0);
+}
+
+#[test]
+fn test_class_indices() {
+    let mut one_hot = vec![0; 40];
+    for (i, label) in [3, 1, 3, 9].iter().enumerate() {
+        one_hot[i * 10 + label] = 1;
+    }
+    let result = CifarResult(vec![0; 4 * 3072], one_hot, vec![0; 3072], vec![5]);
+
+    let train = result.class_indices(Split::Train);
+    assert_eq!(train[3], vec![0, 2]);
+    assert_eq!(train[1], vec![1]);
+    assert_eq!(train[9], vec![3]);
+    assert!(train[0].is_empty());
+    assert_eq!(result.class_indices(Split::Test)[5], vec![0]);
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_read_buffer_size() {
+    let indices = [0, 9_999, 10_000, 49_999];
+    let expected = Cifar10::default()
+        .indices(Split::Train, &indices)
+        .build()
+        .unwrap();
+    let result = Cifar10::default()
+        .read_buffer_size(1 << 20)
+        .sequential_read_hint(true)
+        .build()
+        .unwrap();
+    for (i, &index) in indices.iter().enumerate() {
+        assert_eq!(
+            &result.0[index * 3072..(index + 1) * 3072],
+            &expected.0[i * 3072..(i + 1) * 3072]
+        );
+    }
+}
+
+#[cfg(all(feature = "global", not(feature = "download")))]
+#[test]
+fn test_global() {
+    let first = Cifar10::global().unwrap();
+    let second = Cifar10::default()
+        .num_records_train(10)
+        .build_global()
+        .unwrap();
+    assert!(std::sync::Arc::ptr_eq(&first, &second));
+    assert_eq!(second.0.len(), 50_000 * 3072);
+}
+
+#[test]
+fn test_noisy_labels_from_csv() {
+    let path = std::env::temp_dir().join("cifar_ten_test_noisy_labels.csv");
+    std::fs::write(
+        &path,
+        "clean_label,worse_label,aggre_label,random_label1,random_label2,random_label3\n\
+         6,6,6,6,6,6\n\
+         9,1,9,9,1,9\n",
+    )
+    .unwrap();
+    let labels = NoisyLabels::from_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(labels.aggregate, vec![6, 9]);
+    assert_eq!(labels.random2, vec![6, 1]);
+    assert_eq!(labels.worst, vec![6, 1]);
+    assert_eq!(labels.select(&[1]).unwrap().worst, vec![1]);
+    assert!(labels.select(&[2]).is_err());
+}
+
+#[test]
+fn test_records() {
+    let mut buffer = vec![7; 2 * 3073 + 10];
+    buffer[3073] = 2;
+    let views: Vec<RecordRef> = records(&buffer).collect();
+    assert_eq!(views.len(), 2);
+    assert_eq!(views[0].label, 7);
+    assert_eq!(views[1].label, 2);
+    assert_eq!(views[1].pixels[0], 7);
+
+    let result = CifarResult(
+        vec![1; 3072],
+        vec![0, 0, 0, 0, 1, 0, 0, 0, 0, 0],
+        vec![],
+        vec![],
+    );
+    let view = result.records(Split::Train).next().unwrap();
+    assert_eq!(view.label, 4);
+    assert_eq!(view.pixels[..], [1; 3072][..]);
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_pipeline() {
+    let expected = Cifar10::default().build().unwrap();
+    let result = Cifar10::default().pipeline(true).build().unwrap();
+    assert!(result.0 == expected.0 && result.1 == expected.1);
+    assert!(result.2 == expected.2 && result.3 == expected.3);
+
+    let missing = Cifar10::default()
+        .pipeline(true)
+        .training_bin_paths(vec!["data_batch_1.bin".into(), "missing.bin".into()])
+        .build();
+    assert!(missing.is_err());
+
+    // Labels and pixels stay aligned across binaries when taken from the front, in either encoding
+    let base_path = std::env::temp_dir().join("cifar_ten_test_pipeline");
+    let config = testing::write_dataset(&base_path, &[7, 5], 4)
+        .unwrap()
+        .pipeline(true)
+        .take_first(Split::Train, 9);
+    let result = config.clone().encode_one_hot(false).build().unwrap();
+    let merged = config.merge_classes(&[&[0, 1, 8, 9]]).build().unwrap();
+    std::fs::remove_dir_all(&base_path).unwrap();
+    testing::check_split(&result.0, &result.1, &(0..9).collect::<Vec<_>>()).unwrap();
+    testing::check_split(&result.2, &result.3, &[0, 1, 2, 3]).unwrap();
+    assert_eq!(merged.1.len(), 9 * 7);
+    assert_eq!(&merged.1[8 * 7..], &[1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_resolved_paths() {
+    let paths = Cifar10::default()
+        .path_root("/srv/ml")
+        .testing_bin_paths(vec![])
+        .resolved_paths();
+    assert_eq!(paths.len(), 5);
+    assert_eq!(
+        paths[0],
+        std::path::Path::new("/srv/ml/data/cifar-10-batches-bin/data_batch_1.bin")
+    );
+
+    let home = std::env::var("HOME").unwrap();
+    let paths = Cifar10::default()
+        .base_path("~/datasets")
+        .path_root("/ignored")
+        .resolved_paths();
+    assert!(paths[5].starts_with(std::path::Path::new(&home).join("datasets")));
+    assert!(paths.iter().all(|path| path.is_absolute()));
+}
+
+#[cfg(feature = "gcs")]
+#[test]
+fn test_object_url() {
+    use crate::remote::{is_remote, join, object_url};
+
+    let url = join(
+        "gs://bucket/cifar/",
+        &["cifar-10-batches-bin/", "data_batch_1.bin"],
+    );
+    assert_eq!(
+        url,
+        "gs://bucket/cifar/cifar-10-batches-bin/data_batch_1.bin"
+    );
+    assert!(is_remote(&url) && !is_remote("data/"));
+    assert_eq!(
+        object_url(&url).unwrap(),
+        "https://storage.googleapis.com/bucket/cifar/cifar-10-batches-bin/data_batch_1.bin"
+    );
+    assert!(object_url("gs:///key").is_err());
+}
+
+#[cfg(feature = "export_archive")]
+#[test]
+fn test_export_archive() {
+    let train: Vec<u8> = (0..2).flat_map(|i| vec![i as u8 * 100; 3072]).collect();
+    let result = CifarResult(train, vec![2, 9], vec![7; 3072], vec![0]);
+
+    let path = std::env::temp_dir().join("cifar_ten_test_export_archive.tar");
+    result
+        .export_archive(&path, ExportFormat::Jpeg { quality: 90 })
+        .unwrap();
+    let mut archive = tar::Archive::new(std::fs::File::open(&path).unwrap());
+    let mut names: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+        .collect();
+    std::fs::remove_file(&path).unwrap();
+
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "test/airplane/0.jpg",
+            "train/bird/0.jpg",
+            "train/truck/1.jpg"
+        ]
+    );
+}
+
+#[test]
+fn test_missing_binaries() {
+    let config = Cifar10::default().base_path("missing/");
+    let err = config.clone().build().err().unwrap().to_string();
+    assert!(err.starts_with("Missing CIFAR-10 binaries"));
+    assert!(err.contains("data_batch_1.bin") && err.contains("test_batch.bin"));
+    assert_eq!(config.raw_buffer(Split::Test).unwrap_err().to_string(), err);
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_build_with_report() {
+    let (result, report) = Cifar10::default()
+        .testing_bin_paths(vec![])
+        .num_records_test(0)
+        .build_with_report()
+        .unwrap();
+    assert_eq!(report.bytes_read, 5 * 10000 * 3073);
+    assert_eq!(report.download, std::time::Duration::ZERO);
+    assert!(report.read > std::time::Duration::ZERO);
+    assert!(report.peak_bytes_estimate >= (result.0.len() + 10000 * 3073) as u64);
+    assert!(report.to_string().contains("bytes read: 153650000"));
+}
+
+#[cfg(feature = "export")]
+#[test]
+fn test_export_embedding_projector() {
+    let train: Vec<u8> = (0..4).flat_map(|i| vec![i as u8 * 60; 3072]).collect();
+    let result = CifarResult(train, vec![0, 1, 2, 3], vec![255; 3072], vec![9]);
+
+    let dir = std::env::temp_dir().join("cifar_ten_test_embedding_projector");
+    result.export_embedding_projector(&dir).unwrap();
+    let sprite = image::open(dir.join("sprite.png")).unwrap().to_rgb8();
+    let metadata = std::fs::read_to_string(dir.join("metadata.tsv")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(sprite.dimensions(), (96, 96));
+    assert_eq!(sprite.get_pixel(0, 32)[0], 180);
+    assert_eq!(sprite.get_pixel(32, 32)[0], 255);
+    assert_eq!(sprite.get_pixel(64, 32)[0], 0);
+    let lines: Vec<&str> = metadata.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "index\tsplit\tlabel");
+    assert_eq!(lines[5], "0\ttest\ttruck");
+}
+
+#[cfg(feature = "export")]
+#[test]
+fn test_png_labels() {
+    let test: Vec<u8> = (0..2).flat_map(testing::pixels).collect();
+    let result = CifarResult(vec![], vec![], test, vec![7, 2]);
+    let dir = std::env::temp_dir().join("cifar_ten_test_png_labels");
+    result
+        .export_pngs(dir.join("labeled"), Split::Test, true)
+        .unwrap();
+    result
+        .export_pngs(dir.join("plain"), Split::Test, false)
+        .unwrap();
+    let labeled = std::fs::read(dir.join("labeled").join("1.png")).unwrap();
+    let plain = std::fs::read(dir.join("plain").join("1.png")).unwrap();
+    let decoded = image::load_from_memory(&labeled).unwrap().to_rgb8();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(png_label(&labeled), Some(2));
+    assert_eq!(png_label(&plain), None);
+    assert_eq!(crate::image_to_record(&decoded), testing::pixels(1));
+    let text = b"tEXtLabel\0bird";
+    assert!(labeled.windows(text.len()).any(|w| w == text));
+    let past_ten = record_to_png(&testing::pixels(0), Some(10)).unwrap();
+    assert_eq!(png_label(&past_ten), Some(10));
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn test_serve_out_of_range() {
+    let train: Vec<u8> = (0..3).flat_map(testing::pixels).collect();
+    let result = CifarResult(train, vec![0, 1, 2], vec![], vec![]);
+
+    let batch = result.respond("/batch?size=18446744073709551615");
+    assert_eq!(batch.status_code().0, 200);
+    assert_eq!(batch.data_length(), Some(3 * 3073));
+    assert_eq!(result.respond("/train/2").status_code().0, 200);
+    for url in ["/train/3", "/train/18446744073709551615", "/test/0/label"].iter() {
+        assert_eq!(result.respond(url).status_code().0, 404);
+    }
+}
+
+#[cfg(feature = "export")]
+#[test]
+fn test_review_errors() {
+    let test: Vec<u8> = (0..3).flat_map(testing::pixels).collect();
+    let result = CifarResult(vec![], vec![], test, vec![3, 5, 8]);
+    let dir = std::env::temp_dir().join("cifar_ten_test_review_errors");
+    assert!(result.review_errors(&[3, 5], &dir).is_err());
+    assert!(result.review_errors(&[3, 5, 10], &dir).is_err());
+
+    let errors = result.review_errors(&[3, 3, 1], &dir).unwrap();
+    let tsv = std::fs::read_to_string(dir.join("errors.tsv")).unwrap();
+    let html = std::fs::read_to_string(dir.join("index.html")).unwrap();
+    let image = image::open(dir.join("2.png")).unwrap().to_rgb8();
+    let skipped = dir.join("0.png").exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(errors, [1, 2]);
+    assert!(!skipped);
+    assert_eq!(crate::image_to_record(&image), testing::pixels(2));
+    let lines: Vec<&str> = tsv.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "index\ttrue\tpredicted",
+            "1\tdog\tcat",
+            "2\tship\tautomobile"
+        ]
+    );
+    assert!(html.contains("<img src=\"2.png\""));
+    assert!(html.contains("2 of 3 misclassified"));
+}
+
+#[test]
+fn test_build_resized() {
+    let base_path = std::env::temp_dir().join("cifar_ten_test_build_resized");
+    let config = testing::write_dataset(&base_path, &[10], 0)
+        .unwrap()
+        .indices(Split::Train, &[3, 1, 4])
+        .resize(64, ResizeFilter::Nearest);
+    let cache_dir = base_path.join("cifar-10-batches-bin/resize_cache");
+
+    let (train_data, train_labels, test_data, _) = config.clone().build_resized().unwrap();
+    let result = config.clone().build().unwrap();
+    assert_eq!(train_data.len(), 3 * 3 * 64 * 64);
+    assert_eq!(train_labels, result.1);
+    assert!(test_data.is_empty());
+    // Nearest neighbour upscaling by two repeats each pixel
+    assert_eq!(train_data[64 + 3], result.0[1]);
+    let entries: Vec<_> = std::fs::read_dir(&cache_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(entries.len(), 1);
+
+    // A cache hit is returned as stored, without parsing the binaries again
+    let mut entry = std::fs::read(&entries[0]).unwrap();
+    entry[0] ^= 0xff;
+    std::fs::write(&entries[0], &entry).unwrap();
+    let (hit, hit_labels, _, _) = config.clone().build_resized().unwrap();
+    assert_eq!(hit[0], train_data[0] ^ 0xff);
+    assert_eq!(hit_labels, train_labels);
+
+    // A corrupted cache entry is rebuilt rather than returned
+    std::fs::write(&entries[0], b"stale").unwrap();
+    let (rebuilt, _, _, _) = config.clone().build_resized().unwrap();
+    assert_eq!(rebuilt, train_data);
+    assert_eq!(
+        std::fs::read(&entries[0]).unwrap().len(),
+        3 * (3 * 64 * 64 + 10)
+    );
+
+    // Rewriting the binaries invalidates their entries
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    testing::write_dataset(&base_path, &[10], 0).unwrap();
+    config.build_resized().unwrap();
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 2);
+    std::fs::remove_dir_all(&base_path).unwrap();
+}
+
+#[test]
+fn test_pixel_plane_order() {
+    let record: Vec<u8> = (0..3072).map(|i| (i / 1024 * 100 + i % 8) as u8).collect();
+    assert_eq!(PixelPlaneOrder::CIFAR, PixelPlaneOrder::PlanarRgb);
+    assert_eq!(PixelPlaneOrder::CIFAR.index(1, 2, 3), 1024 + 67);
+    assert_eq!(PixelPlaneOrder::CIFAR.pixel(&record, 0, 1), [1, 101, 201]);
+
+    let interleaved = PixelPlaneOrder::CIFAR.convert(&record, PixelPlaneOrder::InterleavedRgb);
+    assert_eq!(&interleaved[3..6], &[1, 101, 201]);
+    assert_eq!(
+        PixelPlaneOrder::InterleavedRgb.convert(&interleaved, PixelPlaneOrder::CIFAR),
+        record
+    );
+}
+
+// Reference values from the official dataset: the first image of data_batch_1.bin is a frog whose top-left
+// pixels are (59, 62, 63) and (43, 46, 45)
+#[cfg(feature = "download")]
+#[test]
+fn test_reference_pixels() {
+    let result = Cifar10::default()
+        .download_and_extract(true)
+        .download_url("https://cmoran.xyz/data/cifar/cifar-10-binary.tar.gz")
+        .testing_bin_paths(vec![])
+        .num_records_test(0)
+        .indices(Split::Train, &[0])
+        .build()
+        .unwrap();
+    assert_eq!(result.1, [0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(PixelPlaneOrder::CIFAR.pixel(&result.0, 0, 0), [59, 62, 63]);
+    assert_eq!(PixelPlaneOrder::CIFAR.pixel(&result.0, 0, 1), [43, 46, 45]);
+}
+
+// A failed download leaves nothing behind, and a pre-placed archive is used without fetching the unreachable URL
+#[cfg(feature = "download")]
+#[test]
+fn test_archive_reader() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Read;
+
+    // Rejects the request after sending part of a body, as a failed or unauthorized download would
+    struct Unauthorized;
+    impl HttpFetch for Unauthorized {
+        fn fetch(
+            &self,
+            _: &str,
+            _: &[(&str, &str)],
+            body: &mut dyn std::io::Write,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            body.write_all(b"\x1f\x8b")?;
+            Err("401 Unauthorized".into())
+        }
+    }
+
+    let base_path = std::env::temp_dir().join("cifar_ten_test_archive_reader");
+    let _ = std::fs::remove_dir_all(&base_path);
+    std::fs::create_dir_all(&base_path).unwrap();
+    let archive_path = base_path.join("cifar-10-binary.tar.gz");
+    let failing = Cifar10::default()
+        .base_path(base_path.to_str().unwrap())
+        .http_client(Unauthorized);
+    assert!(failing.download_archive_only().is_err());
+    // Nothing is left for the next attempt to mistake for a complete archive
+    assert!(!archive_path.exists());
+    assert!(!archive_path.with_extension("part").exists());
+
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        std::fs::File::create(&archive_path).unwrap(),
+        Compression::fast(),
+    ));
+    let contents = testing::batch(0, 2);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(
+            &mut header,
+            "cifar-10-batches-bin/test_batch.bin",
+            contents.as_slice(),
+        )
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let config = Cifar10::default()
+        .base_path(base_path.to_str().unwrap())
+        .download_url("http://localhost:9/cifar-10-binary.tar.gz");
+    assert_eq!(config.download_archive_only().unwrap(), archive_path);
+
+    let mut archive = tar::Archive::new(config.archive_reader().unwrap());
+    let mut entries = archive.entries().unwrap();
+    let mut entry = entries.next().unwrap().unwrap();
+    assert_eq!(
+        entry.path().unwrap(),
+        std::path::Path::new("cifar-10-batches-bin/test_batch.bin")
+    );
+    let mut read = Vec::new();
+    entry.read_to_end(&mut read).unwrap();
+    assert_eq!(read, contents);
+    assert!(entries.next().is_none());
+    std::fs::remove_dir_all(&base_path).unwrap();
+}
+
+#[cfg(feature = "download")]
+#[test]
+fn test_failed_download_cleanup() {
+    use std::io::Write;
+
+    // Sends the first record, then fails partway through the second
+    struct Flaky(bool);
+    impl HttpFetch for Flaky {
+        fn fetch(
+            &self,
+            _: &str,
+            _: &[(&str, &str)],
+            body: &mut dyn Write,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            match self.0 {
+                true => body.write_all(&testing::batch(0, 2))?,
+                false => {
+                    body.write_all(&testing::batch(0, 1))?;
+                    return Err("Connection reset".into());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let base_path = std::env::temp_dir().join("cifar_ten_test_failed_download_cleanup");
+    let bin = base_path.join("cifar-10-batches-bin/test_batch.bin");
+    let config = Cifar10::default()
+        .base_path(base_path.to_str().unwrap())
+        .download_and_extract(true)
+        .test_batch_url("http://localhost:9/test_batch.bin")
+        .splits(&[Split::Test])
+        .num_records_test(2)
+        .spot_check(false);
+
+    assert!(config.clone().http_client(Flaky(false)).build().is_err());
+    assert!(!bin.exists());
+    assert!(!bin.with_extension("part").exists());
+    // The testing binary on its own reports its progress like the archive does
+    let summaries = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let reported = summaries.clone();
+    let result = config
+        .http_client(Flaky(true))
+        .download_progress(move |progress| {
+            if progress.done {
+                reported.lock().unwrap().push(progress.bytes);
+            }
+        })
+        .build()
+        .unwrap();
+    testing::check_split(&result.2, &result.3, &[0, 1]).unwrap();
+    assert_eq!(*summaries.lock().unwrap(), [2 * 3073]);
+    std::fs::remove_dir_all(&base_path).unwrap();
+}
+
+#[test]
+fn test_simd_conversion() {
+    // Lengths around the vector widths catch mistakes in the tails of the loops
+    let src: Vec<u8> = (0..3072 + 37).map(|i| (i * 7 % 256) as u8).collect();
+    for len in [0, 1, 15, 31, 32, 33, 3072, src.len()] {
+        let expected: Vec<f32> = src[..len].iter().map(|&x| x as f32 / 255.0).collect();
+        let mut dispatched = vec![1.5f32];
+        crate::simd::extend_converted(&mut dispatched, &src[..len], |x| x as f32 / 255.0);
+        assert_eq!(dispatched[0], 1.5);
+        assert_eq!(dispatched[1..], expected[..]);
+
+        for avx2 in [false, true] {
+            let mut converted = vec![];
+            match testing::convert_pixels(&mut converted, &src[..len], avx2) {
+                true => assert_eq!(converted, expected),
+                false => assert!(avx2 && !crate::simd::avx2_detected() && converted.is_empty()),
+            }
+        }
+    }
+
+    let mut bytes = vec![];
+    crate::simd::extend_converted(&mut bytes, &src, |x| x);
+    assert_eq!(bytes, src);
+}
+
+#[test]
+fn test_per_image_standardization() {
+    let mut image: Vec<f32> = (0..3072).map(|i| (i % 256) as f32).collect();
+    Preprocess::PerImageStandardization.apply(&mut image);
+    let mean = image.iter().sum::<f32>() / 3072.0;
+    let variance = image.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / 3072.0;
+    assert!(mean.abs() < 1e-4);
+    assert!((variance - 1.0).abs() < 1e-4);
+
+    let mut uniform = vec![0.5; 3072];
+    Preprocess::PerImageStandardization.apply(&mut uniform);
+    assert!(uniform.iter().all(|&x| x == 0.0));
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_filter_records() {
+    let config = Cifar10::default()
+        .testing_bin_paths(vec![])
+        .num_records_test(0)
+        .take_first(Split::Train, 1000);
+    let full = config.clone().build().unwrap();
+    let bright = config
+        .filter_records(|pixels, label| label != 3 && pixels[0] > 128)
+        .build()
+        .unwrap();
+
+    let expected: Vec<usize> = (0..1000)
+        .filter(|&i| full.1[i * 10 + 3] == 0 && full.0[i * 3072] > 128)
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(bright.0.len(), expected.len() * 3072);
+    assert_eq!(bright.1.len(), expected.len() * 10);
+    let last = *expected.last().unwrap();
+    assert_eq!(
+        &bright.0[bright.0.len() - 3072..],
+        &full.0[last * 3072..(last + 1) * 3072]
+    );
+    assert_eq!(
+        &bright.1[bright.1.len() - 10..],
+        &full.1[last * 10..(last + 1) * 10]
+    );
+}
+
+#[test]
+fn test_order_by_scores() {
+    let data: Vec<u8> = (0..4).flat_map(|i| vec![i as u8; 3072]).collect();
+    let result = CifarResult(data, vec![0, 1, 2, 3], vec![], vec![]);
+
+    let (ordered, permutation) = result.order_by_scores(&[0.5, -1.0, 2.0, 0.5]).unwrap();
+    assert_eq!(permutation, [1, 0, 3, 2]);
+    assert_eq!(ordered.1, [1, 0, 3, 2]);
+    assert_eq!(ordered.0[3072], 0);
+
+    let result = CifarResult(vec![0; 3072], vec![0], vec![], vec![]);
+    assert!(result.order_by_scores(&[1.0, 2.0]).is_err());
+}
+
+#[test]
+fn test_rotation_task() {
+    let record: Vec<u8> = (0..3072).map(|i| (i % 251) as u8).collect();
+    let result = CifarResult(vec![], vec![], record.clone(), vec![0]);
+
+    let (data, labels) = result.rotation_task(Split::Test);
+    assert_eq!(labels, [0, 1, 2, 3]);
+    assert_eq!(&data[..3072], &record[..]);
+    // Counter-clockwise: the top-right pixel moves to the top-left
+    assert_eq!(data[3072], record[31]);
+    assert_eq!(data[3072 + 1024], record[1024 + 31]);
+    // Half a turn: the bottom-right pixel moves to the top-left
+    assert_eq!(data[2 * 3072], record[1023]);
+    assert_eq!(crate::pretext::rotate(&data[3 * 3072..], 1), record);
+}
+
+#[test]
+fn test_jigsaw_task() {
+    let record: Vec<u8> = (0..3072)
+        .map(|i| (i / 16 % 2 + i / 512 % 2 * 2) as u8)
+        .collect();
+    let jigsaw = Jigsaw::new(16, vec![vec![0, 1, 2, 3], vec![3, 2, 1, 0]]).unwrap();
+    // Each 16 x 16 patch of the record holds its own patch number
+    assert_eq!(jigsaw.apply(&record, 0), record);
+    let reversed = jigsaw.apply(&record, 1);
+    assert_eq!(
+        [
+            reversed[0],
+            reversed[16],
+            reversed[512],
+            reversed[1024 + 528]
+        ],
+        [3, 2, 1, 0]
+    );
+
+    let result = CifarResult(vec![], vec![], record.repeat(20), vec![0; 20]);
+    let (data, labels) = result.jigsaw_task(Split::Test, &jigsaw, 7);
+    assert_eq!(data.len(), 20 * 3072);
+    assert!(labels.contains(&0) && labels.contains(&1));
+    assert_eq!(data[3072 * 5], 3 * labels[5] as u8);
+
+    assert!(Jigsaw::new(16, vec![vec![0, 1, 1, 3]]).is_err());
+    assert!(Jigsaw::new(12, vec![vec![0]]).is_err());
+    assert_eq!(Jigsaw::random(16, 24, 0).unwrap().permutations().len(), 24);
+    assert!(Jigsaw::random(16, 25, 0).is_err());
+}
+
+#[test]
+fn test_colorization_task() {
+    let mut record = vec![0u8; 3072];
+    record[5] = 255;
+    record[2048 + 6] = 255;
+    let result = CifarResult(record.clone(), vec![0], vec![], vec![]);
+
+    let (inputs, targets) = result.colorization_task(Split::Train, true);
+    assert_eq!(inputs.len(), 1024);
+    assert!((inputs[5] - 0.299).abs() < 1e-6);
+    assert!((inputs[6] - 0.114).abs() < 1e-6);
+    assert_eq!(targets[2048 + 6], 1.0);
+    assert_eq!(result.colorization_task(Split::Train, false).1[5], 255.0);
+}
+
+#[test]
+fn test_super_resolution_pairs() {
+    let record: Vec<u8> = (0..3072).map(|i| (i % 32 * 8) as u8).collect();
+    let result = CifarResult(record.clone(), vec![0], vec![], vec![]);
+
+    let (low, high) = result
+        .super_resolution_pairs(Split::Train, 2, ResizeFilter::Bilinear)
+        .unwrap();
+    assert_eq!(low.len(), 3 * 16 * 16);
+    assert_eq!(high, record);
+    // Halving averages each pair of columns
+    assert_eq!(&low[..3], &[4, 20, 36]);
+
+    let (low, _) = result
+        .super_resolution_pairs(Split::Train, 4, ResizeFilter::Nearest)
+        .unwrap();
+    assert_eq!(&low[..3], &[0, 32, 64]);
+    assert!(result
+        .super_resolution_pairs(Split::Train, 3, ResizeFilter::Nearest)
+        .is_err());
+}
+
+#[test]
+fn test_noisy_pairs() {
+    let data: Vec<u8> = (0..4 * 3072).map(|i| (i % 256) as u8).collect();
+    let result = CifarResult(data, vec![0; 4], vec![], vec![]);
+
+    let (noisy, clean) = result.noisy_pairs(Split::Train, NoiseModel::Gaussian(0.1), 3);
+    assert_eq!(clean[255], 1.0);
+    assert!(noisy.iter().all(|&x| (0.0..=1.0).contains(&x)));
+    let unclamped: Vec<f32> = noisy
+        .iter()
+        .zip(&clean)
+        .filter(|(_, &c)| (0.3..0.7).contains(&c))
+        .map(|(n, c)| n - c)
+        .collect();
+    let variance = unclamped.iter().map(|d| d * d).sum::<f32>() / unclamped.len() as f32;
+    assert!((variance.sqrt() - 0.1).abs() < 0.01);
+    assert_eq!(
+        result
+            .noisy_pairs(Split::Train, NoiseModel::Gaussian(0.1), 3)
+            .0,
+        noisy
+    );
+
+    let (noisy, clean) = result.noisy_pairs(Split::Train, NoiseModel::SaltPepper(0.2), 3);
+    let changed = noisy.iter().zip(&clean).filter(|(n, c)| n != c).count();
+    assert!((changed as f32 / clean.len() as f32 - 0.2).abs() < 0.02);
+    assert!(noisy
+        .iter()
+        .zip(&clean)
+        .all(|(n, c)| n == c || *n == 0.0 || *n == 1.0));
+
+    let (noisy, clean) = result.noisy_pairs(Split::Train, NoiseModel::Poisson, 3);
+    let mean_noisy = noisy.iter().sum::<f32>() / noisy.len() as f32;
+    let mean_clean = clean.iter().sum::<f32>() / clean.len() as f32;
+    assert!((mean_noisy - mean_clean).abs() < 0.01);
+}
+
+#[test]
+fn test_patch_masks() {
+    let mut masks = PatchMasks::new(4, 0.75, 5).unwrap();
+    assert_eq!(masks.num_patches(), 64);
+    let first = masks.next().unwrap();
+    let second = masks.next().unwrap();
+    assert_eq!(first.iter().filter(|&&masked| masked).count(), 48);
+    assert_ne!(first, second);
+    assert_eq!(PatchMasks::new(4, 0.75, 5).unwrap().next().unwrap(), first);
+
+    assert!(PatchMasks::new(5, 0.5, 0).is_err());
+    assert!(PatchMasks::new(4, 1.5, 0).is_err());
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_build_as() {
+    let config = Cifar10::default()
+        .testing_bin_paths(vec![])
+        .num_records_test(0)
+        .take_first(Split::Train, 100);
+    let result = config.clone().build().unwrap();
+
+    let (train_data, train_labels, _, _) = config
+        .clone()
+        .pixel_scaling(PixelScaling::Multiply(257))
+        .build_as::<u16>()
+        .unwrap();
+    assert_eq!(train_data[42], result.0[42] as u16 * 257);
+    assert_eq!(
+        train_labels[..10],
+        result.1[..10].iter().map(|&x| x as u16).collect::<Vec<_>>()[..]
+    );
+
+    let (train_data, _, _, _) = config
+        .clone()
+        .pixel_scaling(PixelScaling::Shift(7))
+        .build_as::<i16>()
+        .unwrap();
+    assert_eq!(train_data[42], (result.0[42] as i16) << 7);
+    assert!(config
+        .pixel_scaling(PixelScaling::Shift(8))
+        .build_as::<i16>()
+        .is_err());
+}
+
+#[test]
+fn test_class_stats() {
+    let mut data = vec![10u8; 3072];
+    data.extend(vec![30u8; 3072]);
+    data.extend((0..3072).map(|i| (i / 1024 * 100) as u8));
+    let result = CifarResult(data, vec![2, 2, 5], vec![], vec![]);
+
+    let stats = result.class_stats(Split::Train);
+    assert_eq!(stats.classes[2].count, 2);
+    assert_eq!(stats.classes[2].channel_mean, [20.0; 3]);
+    assert_eq!(stats.classes[2].channel_std, [10.0; 3]);
+    assert_eq!(stats.classes[5].channel_mean, [0.0, 100.0, 200.0]);
+    assert_eq!(stats.classes[0].count, 0);
+    let expected = ((20f64.powi(2) + 80f64.powi(2) + 180f64.powi(2)) * 1024.0).sqrt();
+    assert!((stats.mean_image_distances[2][5] - expected).abs() < 1e-9);
+    assert_eq!(stats.mean_image_distances[5][5], 0.0);
+
+    let path = std::env::temp_dir().join("cifar_ten_test_class_stats.csv");
+    stats.write_csv(&path).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(csv.lines().count(), 11);
+    assert!(csv
+        .lines()
+        .nth(3)
+        .unwrap()
+        .starts_with("2,bird,2,20,20,20,10,10,10,"));
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_export_labels_csv() {
+    let config = Cifar10::default().take_first(Split::Train, 10_001);
+    let result = config.clone().build().unwrap();
+
+    let path = std::env::temp_dir().join("cifar_ten_test_export_labels.csv");
+    result.export_labels_csv(&path, Some(&config)).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 1 + 10_001 + 10_000);
+    assert_eq!(lines[0], "index,split,class,name,file,index_in_file");
+    let class = CLASS_NAMES[result.3[..10].iter().position(|&x| x == 1).unwrap()];
+    assert!(lines[10_002].starts_with("0,test,"));
+    assert!(lines[10_002].ends_with(&format!("{},test_batch.bin,0", class)));
+    assert!(lines[10_001].ends_with(",data_batch_2.bin,0"));
+
+    result.export_labels_csv(&path, None).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(csv.lines().next().unwrap(), "index,split,class,name");
+    assert!(result
+        .export_labels_csv(&path, Some(&config.indices(Split::Test, &[0])))
+        .is_err());
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_take_first() {
+    let config = Cifar10::default().encode_one_hot(false);
+    let result = config.clone().take_first(Split::Test, 5).build().unwrap();
+    assert_eq!(result.2.len(), 5 * 3072);
+    assert_eq!(result.3.len(), 5);
+    assert_eq!(result.1.len(), 50_000);
+
+    let err = config
+        .clone()
+        .num_records_train(10_000)
+        .build()
+        .err()
+        .unwrap();
+    assert!(err
+        .to_string()
+        .contains("contain 50000 records, but 10000 were expected"));
+    assert!(config.clone().num_records_test(10_001).build().is_err());
+    assert!(config.take_first(Split::Test, 10_001).build().is_err());
+}
+
+#[test]
+fn test_flat_f32_batches() {
+    let data: Vec<u8> = (0..5).flat_map(|i| vec![i as u8 * 51; 3072]).collect();
+    let result = CifarResult(data, vec![0, 1, 2, 3, 4], vec![], vec![]);
+
+    let batches: Vec<(Vec<f32>, &[u8])> = result.flat_f32_batches(Split::Train, 2).collect();
+    assert_eq!(batches.len(), 3);
+    assert_eq!(batches[0].0.len(), 2 * 3072);
+    assert_eq!(batches[1].0[3072], 153.0 / 255.0);
+    assert_eq!(batches[2].0, vec![204.0 / 255.0; 3072]);
+    assert_eq!(batches[2].1, &[4]);
+    assert_eq!(result.flat_f32_batches(Split::Test, 2).count(), 0);
+}
+
+#[test]
+fn test_augmentation_f32() {
+    let record: Vec<u8> = (0..3072).map(|i| (i % 251) as u8).collect();
+    let augmentation = Augmentation::new(vec![
+        Transform::HorizontalFlip { p: 0.5 },
+        Transform::RandomCrop { padding: 4 },
+    ]);
+
+    let mut bytes = record.repeat(3);
+    let mut floats: Vec<f32> = bytes.iter().map(|&x| x as f32 / 255.0).collect();
+    augmentation.apply_all(&mut bytes, 9);
+    augmentation.apply_all(&mut floats, 9);
+    let expected: Vec<f32> = bytes.iter().map(|&x| x as f32 / 255.0).collect();
+    assert_eq!(floats, expected);
+
+    let mut blended = vec![1.0f32; 3072];
+    mixup(&mut blended, &floats[..3072], 0.3);
+    assert!((blended[7] - (0.3 + 0.7 * floats[7])).abs() < 1e-6);
+    let mut blended = vec![255u8; 3072];
+    mixup(&mut blended, &[0; 3072], 0.5);
+    assert_eq!(blended[0], 128);
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_ordering_checksum() {
+    let config = Cifar10::default().take_first(Split::Test, 100);
+    let checksum = config.ordering_checksum(Split::Test).unwrap();
+    assert_eq!(checksum, config.ordering_checksum(Split::Test).unwrap());
+
+    let indices: Vec<usize> = (0..100).collect();
+    let same = Cifar10::default().indices(Split::Test, &indices);
+    assert_eq!(same.ordering_checksum(Split::Test).unwrap(), checksum);
+
+    let reversed: Vec<usize> = (0..100).rev().collect();
+    let reordered = Cifar10::default().indices(Split::Test, &reversed);
+    assert_ne!(reordered.ordering_checksum(Split::Test).unwrap(), checksum);
+}
+
+#[cfg(feature = "export")]
+#[test]
+fn test_contact_sheet() {
+    let result = CifarResult(vec![], vec![], vec![100; 3072], vec![9]);
+    let path = std::env::temp_dir().join("cifar_ten_test_contact_sheet.png");
+    result.contact_sheet(&path, 1, 2, Split::Test, 0).unwrap();
+    let sheet = image::open(&path).unwrap().to_rgb8();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(sheet.dimensions(), (72, 36));
+    assert_eq!(sheet.get_pixel(0, 0).0, CLASS_COLORS[9]);
+    assert_eq!(sheet.get_pixel(2, 2).0, [100, 100, 100]);
+    assert_eq!(sheet.get_pixel(50, 20).0, [255, 255, 255]);
+
+    let error = result
+        .contact_sheet_of(&path, Split::Test, &[0, 1], 2)
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("out of range"));
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_drop_invalid_pixels() {
+    let mut train = vec![0u8; 3072];
+    train.extend((0..3072).map(|i| i as u8));
+    train.extend(vec![7u8; 3072]);
+    let result = CifarResult(train, vec![1, 2, 3], vec![5; 3072], vec![4]);
+
+    assert_eq!(
+        result.validate_pixels(Split::Train),
+        vec![(0, PixelIssue::AllZero), (2, PixelIssue::Constant(7))]
+    );
+    let (result, (train_issues, test_issues)) = result.drop_invalid_pixels();
+    assert_eq!(train_issues.len(), 2);
+    assert_eq!(test_issues, vec![(0, PixelIssue::Constant(5))]);
+    assert_eq!(result.1, vec![2]);
+    assert_eq!(result.0[1], 1);
+    assert!(result.2.is_empty() && result.3.is_empty());
+}
+
+#[test]
+fn test_contrastive_pairs_batch_independent() {
+    let data: Vec<u8> = (0..6 * 3072).map(|i| (i % 253) as u8).collect();
+    let result = CifarResult(data, vec![0; 6], vec![], vec![]);
+    let augmentation = Augmentation::new(vec![
+        Transform::HorizontalFlip { p: 0.5 },
+        Transform::RandomCrop { padding: 4 },
+    ]);
+
+    let views = |batch_size| {
+        let mut views: Vec<(usize, Vec<u8>)> = result
+            .contrastive_pairs(Split::Train, &augmentation, batch_size, 3)
+            .flat_map(|batch| {
+                let view_a = batch.view_a;
+                batch
+                    .indices
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(k, i)| (i, view_a[k * 3072..(k + 1) * 3072].to_vec()))
+            })
+            .collect();
+        views.sort();
+        views
+    };
+    assert_eq!(views(1), views(4));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_cache_compression() {
+    let data: Vec<u8> = (0..3 * 1024 * 1024 + 5)
+        .map(|i| (i / 7 % 256) as u8)
+        .collect();
+    let compressed = crate::compress::compress(&data, 3).unwrap();
+    assert!(compressed.len() < data.len() / 2);
+    assert_eq!(
+        crate::compress::decompress(&compressed, data.len()).unwrap(),
+        data
+    );
+    assert!(crate::compress::decompress(&compressed, data.len() - 1).is_err());
+    assert!(crate::compress::decompress(&compressed[..100], data.len()).is_err());
+    // A frame size that would overflow the offset is a miss, not a panic
+    let mut corrupt = compressed.clone();
+    corrupt[4..12].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(crate::compress::decompress(&corrupt, data.len()).is_err());
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_lazy_one_hot() {
+    let indices = Cifar10::default()
+        .encode_one_hot(false)
+        .take_first(Split::Test, 20)
+        .build()
+        .unwrap();
+    let one_hot = Cifar10::default()
+        .take_first(Split::Test, 20)
+        .build()
+        .unwrap();
+
+    assert!(matches!(
+        indices.label_indices(Split::Test),
+        std::borrow::Cow::Borrowed(_)
+    ));
+    assert_eq!(
+        indices.label_indices(Split::Test),
+        one_hot.label_indices(Split::Test)
+    );
+    assert_eq!(indices.one_hot_labels(Split::Test)[..], one_hot.3[..]);
+    assert!(matches!(
+        one_hot.one_hot_labels(Split::Test),
+        std::borrow::Cow::Borrowed(_)
+    ));
+}
+
+#[test]
+fn test_color_jitter() {
+    use rand::{Rng, SeedableRng};
+    let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+    let identity = Transform::ColorJitter {
+        brightness: 0.0,
+        contrast: 0.0,
+        saturation: 0.0,
+        hue: 0.0,
+    };
+    let jitter = Transform::ColorJitter {
+        brightness: 0.8,
+        contrast: 0.8,
+        saturation: 0.8,
+        hue: 0.5,
+    };
+
+    for _ in 0..50 {
+        let record: Vec<u8> = (0..3072).map(|_| rng.gen()).collect();
+        let mut unchanged = record.clone();
+        identity.apply(&mut unchanged, &mut rng);
+        assert_eq!(unchanged, record);
+
+        let mut floats: Vec<f32> = record.iter().map(|&x| x as f32 / 255.0).collect();
+        jitter.apply(&mut floats, &mut rng);
+        assert!(floats.iter().all(|x| (0.0..=1.0).contains(x)));
+    }
+
+    // A gray image has no saturation or hue to change, and saturates at white when brightened
+    let mut gray = vec![200u8; 3072];
+    Transform::ColorJitter {
+        brightness: 0.0,
+        contrast: 0.0,
+        saturation: 1.0,
+        hue: 0.5,
+    }
+    .apply(&mut gray, &mut rng);
+    assert!(gray.iter().all(|&x| x == 200));
+    let mut bright = vec![200u8; 3072];
+    for _ in 0..20 {
+        Transform::ColorJitter {
+            brightness: 1.0,
+            contrast: 0.0,
+            saturation: 0.0,
+            hue: 0.0,
+        }
+        .apply(&mut bright, &mut rng);
+    }
+    assert!(bright.iter().all(|&x| x == bright[0]));
+}
+
+#[test]
+fn test_random_resized_crop() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+    let record: Vec<u8> = (0..3072).map(|i| (i / 1024 * 80) as u8).collect();
+
+    // Cropping the whole image at the same size leaves it unchanged
+    let full = Transform::RandomResizedCrop {
+        scale: (1.0, 1.0),
+        ratio: (1.0, 1.0),
+        size: 32,
+    };
+    let mut same = record.clone();
+    full.apply(&mut same, &mut rng);
+    assert_eq!(same, record);
+
+    let augmentation = Augmentation::new(vec![
+        Transform::RandomResizedCrop {
+            scale: (0.08, 1.0),
+            ratio: (0.75, 4.0 / 3.0),
+            size: 24,
+        },
+        Transform::HorizontalFlip { p: 0.5 },
+    ]);
+    for _ in 0..20 {
+        let crop = augmentation.apply_resized(&record, &mut rng);
+        assert_eq!(crop.len(), 3 * 24 * 24);
+        assert!(crop[..576].iter().all(|&x| x == 0));
+        assert!(crop[2 * 576..].iter().all(|&x| x == 160));
+    }
+
+    // Resizing can't be done in place, even behind RandomApply
+    assert!(Augmentation::try_new(augmentation.transforms.clone()).is_err());
+    assert!(Augmentation::try_new(vec![Transform::RandomApply {
+        p: 0.5,
+        transform: Box::new(augmentation.transforms[0].clone()),
+    }])
+    .is_err());
+    assert!(augmentation.validate(24).is_ok());
+    assert!(Augmentation::try_new(vec![full]).is_ok());
+}
+
+#[test]
+fn test_gaussian_blur_solarize() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+    let blur = Transform::GaussianBlur {
+        sigma_range: (0.5, 2.0),
+    };
+
+    let mut flat = vec![90u8; 3072];
+    blur.apply(&mut flat, &mut rng);
+    assert!(flat.iter().all(|&x| x == 90));
+
+    // A single bright pixel is spread out, keeping its total brightness and its peak in place
+    let mut point = vec![0f32; 3072];
+    point[16 * 32 + 16] = 1.0;
+    blur.apply(&mut point, &mut rng);
+    let total: f32 = point[..1024].iter().sum();
+    assert!((total - 1.0).abs() < 1e-4);
+    assert!(point[16 * 32 + 16] < 1.0);
+    assert!(point[..1024].iter().all(|&x| x <= point[16 * 32 + 16]));
+    assert!(point[1024..].iter().all(|&x| x == 0.0));
+
+    let mut record: Vec<u8> = vec![100, 127, 128, 255];
+    record.resize(3072, 0);
+    Transform::Solarize { threshold: 0.5 }.apply(&mut record, &mut rng);
+    assert_eq!(record[..4], [100, 127, 255 - 128, 0]);
+
+    for &sigma_range in &[(2.0, 0.5), (f32::NAN, 1.0), (0.5, f32::NAN), (-1.0, 1.0)] {
+        assert!(Augmentation::try_new(vec![Transform::GaussianBlur { sigma_range }]).is_err());
+    }
+    assert!(Augmentation::try_new(vec![
+        blur,
+        Transform::GaussianBlur {
+            sigma_range: (1.0, 1.0)
+        }
+    ])
+    .is_ok());
+}
+
+#[cfg(feature = "to_ndarray_016")]
+#[test]
+fn test_blur_solarize_views() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+    let record: Vec<f32> = (0..3072).map(|i| (i % 97) as f32 / 96.0).collect();
+    let blur = Transform::GaussianBlur {
+        sigma_range: (1.5, 1.5),
+    };
+    let solarize = Transform::Solarize { threshold: 0.5 };
+
+    // Views match the slice transforms, including views that aren't in standard layout
+    for k in 0..4 {
+        let mut expected = record.clone();
+        rot90(&mut expected, k);
+        let (mut blurred, mut solarized) = (expected.clone(), expected.clone());
+        blur.apply(&mut blurred, &mut rng);
+        solarize.apply(&mut solarized, &mut rng);
+
+        for (transform, expected) in [(0, blurred), (1, solarized)] {
+            let mut image = ndarray::Array3::from_shape_vec((3, 32, 32), record.clone()).unwrap();
+            let mut view = image.view_mut();
+            match k.rem_euclid(4) {
+                0 => {}
+                1 => {
+                    view.invert_axis(ndarray::Axis(2));
+                    view.swap_axes(1, 2);
+                }
+                2 => {
+                    view.invert_axis(ndarray::Axis(1));
+                    view.invert_axis(ndarray::Axis(2));
+                }
+                _ => {
+                    view.swap_axes(1, 2);
+                    view.invert_axis(ndarray::Axis(2));
+                }
+            }
+            match transform {
+                0 => gaussian_blur_view(view.view_mut(), 1.5),
+                _ => solarize_view(view.view_mut(), 0.5),
+            }
+            let result: Vec<f32> = view.iter().copied().collect();
+            assert_eq!(result, expected);
+        }
+    }
+}
+
+#[cfg(all(not(feature = "download"), feature = "to_ndarray_016"))]
+#[test]
+fn test_preset() {
+    let (train, labels, _, _) = Cifar10::default()
+        .preset(Preset::TorchvisionTrain)
+        .take_first(Split::Train, 10)
+        .take_first(Split::Test, 0)
+        .build_as_flat_f32()
+        .unwrap();
+    let raw = Cifar10::default()
+        .take_first(Split::Train, 10)
+        .take_first(Split::Test, 0)
+        .build()
+        .unwrap();
+    assert_eq!(labels.dim(), (10, 1));
+    let expected = (raw.0[1024] as f32 / 255.0 - CIFAR10_MEAN[1]) / CIFAR10_STD[1];
+    assert!((train[[0, 1024]] - expected).abs() < 1e-5);
+    let indices: Vec<i64> = labels.iter().map(|&x| x as i64).collect();
+    assert_eq!(raw.label_indices_i64(Split::Train), indices);
+
+    // Later builder calls take precedence
+    let (_, labels, _, _) = Cifar10::default()
+        .preset(Preset::TorchvisionTrain)
+        .encode_one_hot(true)
+        .take_first(Split::Train, 10)
+        .take_first(Split::Test, 0)
+        .build_as_flat_f32()
+        .unwrap();
+    assert_eq!(labels.dim(), (10, 10));
+    assert_eq!(Preset::TorchvisionTrain.augmentation().transforms.len(), 2);
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_spot_check() {
+    let mut records = std::fs::read("data/cifar-10-batches-bin/data_batch_1.bin").unwrap();
+    let base_path = std::env::temp_dir().join("cifar_ten_test_spot_check");
+    std::fs::create_dir_all(base_path.join("cifar-10-batches-bin")).unwrap();
+    let bin = base_path.join("cifar-10-batches-bin/data_batch_1.bin");
+    let config = Cifar10::default()
+        .base_path(base_path.to_str().unwrap())
+        .training_bin_paths(vec!["data_batch_1.bin".into()])
+        .testing_bin_paths(vec![])
+        .num_records_train(10_000)
+        .num_records_test(0);
+
+    std::fs::write(&bin, &records).unwrap();
+    assert!(config.clone().build().is_ok());
+    // Flip a single pixel bit, which leaves the size unchanged, whether the binary is read whole or by index
+    records[5] ^= 1;
+    std::fs::write(&bin, &records).unwrap();
+    for config in [config.clone(), config.clone().indices(Split::Train, &[20])] {
+        let error = config.build().err().unwrap().to_string();
+        assert!(error.contains("may be corrupted"));
+        assert!(error.contains("spot_check(false)"));
+    }
+    assert!(config.clone().spot_check(false).build().is_ok());
+
+    // Short binaries are reported rather than skipped
+    std::fs::write(&bin, &records[..10 * 3073]).unwrap();
+    let short = config.num_records_train(10);
+    let error = short.clone().build().err().unwrap().to_string();
+    assert!(error.contains("may be truncated"));
+    assert!(short.spot_check(false).build().is_ok());
+    std::fs::remove_dir_all(&base_path).unwrap();
+
+    // Reference labels and pixels are compared wherever they are listed
+    use crate::spot_check::{check_against, Reference};
+    let mut binary: Vec<u8> = (0..3 * 3073).map(|i| (i % 251) as u8).collect();
+    for i in 0..3 {
+        binary[i * 3073] = i as u8;
+    }
+    let references = [Reference {
+        binary: "batch.bin",
+        records: 3,
+        labels: &[0, 1],
+        red: &[1, 2, 3],
+    }];
+    let check = |binary: &[u8]| check_against(&references, "batch.bin", binary.len(), binary);
+    assert!(check(&binary).is_ok());
+    assert!(check_against(&references, "other.bin", 5, &[]).is_ok());
+    for i in [3, 3073] {
+        binary[i] ^= 1;
+        assert!(check(&binary)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("may be corrupted"));
+        binary[i] ^= 1;
+    }
+    assert!(check(&binary[..2 * 3073])
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("may be truncated"));
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_splits() {
+    let result = Cifar10::default()
+        .training_bin_paths(vec!["missing.bin".into()])
+        .splits(&[Split::Test])
+        .take_first(Split::Test, 10)
+        .build()
+        .unwrap();
+    assert!(result.0.is_empty() && result.1.is_empty());
+    assert_eq!(result.2.len(), 10 * 3072);
+
+    assert!(Cifar10::default()
+        .training_bin_paths(vec!["missing.bin".into()])
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_shaped_batches() {
+    let data: Vec<u8> = (0..5 * 3072).map(|i| (i % 255) as u8).collect();
+    let result = CifarResult(data.clone(), vec![3, 1, 4, 1, 5], vec![], vec![]);
+
+    let batches: Vec<_> = result
+        .shaped_batches::<2, 10>(Split::Train)
+        .unwrap()
+        .collect();
+    assert_eq!(batches.len(), 2);
+    let (images, targets) = &batches[1];
+    assert_eq!(
+        images[1][2][3][4],
+        data[3 * 3072 + 2048 + 3 * 32 + 4] as f32 / 255.0
+    );
+    assert_eq!(targets[0][4], 1.0);
+    assert_eq!(targets[1].iter().sum::<f32>(), 1.0);
+    assert!(result.shaped_batches::<2, 4>(Split::Train).is_err());
+}
+
+#[cfg(all(not(feature = "download"), feature = "to_ndarray_016"))]
+#[test]
+fn test_build_shared() {
+    let (train_data, train_labels, test_data, _) = Cifar10::default()
+        .take_first(Split::Train, 4)
+        .take_first(Split::Test, 2)
+        .build_shared()
+        .unwrap();
+    assert_eq!(train_data.shape(), &[4, 3, 32, 32]);
+    assert_eq!(train_labels.shape(), &[4, 10]);
+    assert_eq!(test_data.shape(), &[2, 3, 32, 32]);
+
+    let handle = train_data.clone();
+    assert_eq!(handle.as_ptr(), train_data.as_ptr());
+    let mut copy = handle.clone();
+    copy[[0, 0, 0, 0]] = copy[[0, 0, 0, 0]].wrapping_add(1);
+    assert_ne!(copy[[0, 0, 0, 0]], train_data[[0, 0, 0, 0]]);
+    assert_eq!(handle, train_data);
+}
+
+#[test]
+fn test_channel_outliers() {
+    let mut data: Vec<u8> = (0..20 * 3072)
+        .map(|i| (i % 3072 % 97 + i / 3072) as u8)
+        .collect();
+    data[7 * 3072..8 * 3072].iter_mut().for_each(|x| *x = 0);
+    let result = CifarResult(data, vec![0; 20], vec![], vec![]);
+
+    let outliers = result.channel_outliers(Split::Train, 6.0);
+    assert_eq!(outliers.len(), 1);
+    assert_eq!(outliers[0].index, 7);
+    assert_eq!(outliers[0].channel_std, [0.0; 3]);
+}
+
+#[test]
+fn test_apply_errata() {
+    let path = std::env::temp_dir().join("cifar_ten_test_errata.csv");
+    std::fs::write(
+        &path,
+        "split,index,label,note\n\
+         test,1,cat,relabeled\n\
+         train,0,2,\n\
+         train,1,0,unchanged\n",
+    )
+    .unwrap();
+    let errata = Errata::from_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(errata.corrections[0], (Split::Test, 1, 3));
+
+    let result = CifarResult(vec![0; 2 * 3072], vec![1, 0], vec![0; 2 * 3072], vec![5, 5]);
+    let (train, test) = result.apply_errata(&errata).unwrap();
+    assert_eq!(train.original, vec![1, 0]);
+    assert_eq!(train.corrected, vec![2, 0]);
+    assert_eq!(train.changed, vec![0]);
+    assert_eq!(test.corrected, vec![5, 3]);
+
+    let missing = Errata {
+        corrections: vec![(Split::Test, 2, 0)],
+    };
+    assert!(result.apply_errata(&missing).is_err());
+}
+
+#[test]
+fn test_federated() {
+    let labels: Vec<u8> = (0..200).map(|i| (i % 10) as u8).collect();
+    let result = CifarResult(vec![0; 200 * 3072], labels.clone(), vec![], vec![]);
+
+    for partition in [
+        FederatedPartition::Iid,
+        FederatedPartition::Dirichlet { alpha: 0.1 },
+        FederatedPartition::Shards {
+            shards_per_client: 2,
+        },
+    ] {
+        let clients = result
+            .federated_indices(Split::Train, 5, partition, 1)
+            .unwrap();
+        let mut all: Vec<usize> = clients.concat();
+        all.sort_unstable();
+        assert_eq!(all, (0..200).collect::<Vec<usize>>());
+        assert_eq!(
+            clients,
+            result
+                .federated_indices(Split::Train, 5, partition, 1)
+                .unwrap()
+        );
+        if let FederatedPartition::Shards { .. } = partition {
+            for client in &clients {
+                let mut classes: Vec<u8> = client.iter().map(|&i| labels[i]).collect();
+                classes.sort_unstable();
+                classes.dedup();
+                assert!(classes.len() <= 4);
+            }
+        }
+    }
+
+    let shards = result
+        .federated(Split::Train, 4, FederatedPartition::Iid, 0)
+        .unwrap();
+    assert!(shards
+        .iter()
+        .all(|(data, labels)| data.len() == 50 * 3072 && labels.len() == 50));
+}
+
+#[test]
+fn test_record_accessors() {
+    let pixels: Vec<u8> = (0..3072).map(|i| (i % 251) as u8).collect();
+    let result = CifarResult(pixels.clone(), vec![3], vec![], vec![]);
+    let record = result.records(Split::Train).next().unwrap();
+
+    assert_eq!(record.pixel(5, 2, 1), pixels[1024 + 2 * 32 + 5]);
+    let [_, _, blue] = record.row(7);
+    assert_eq!(blue[..], pixels[2048 + 7 * 32..2048 + 8 * 32]);
+    #[cfg(feature = "to_ndarray_016")]
+    assert_eq!(record.channel(2)[[7, 3]], record.pixel(3, 7, 2));
+}
+
+#[test]
+fn test_flips_and_rotations() {
+    let record: Vec<u8> = (0..3072).map(|i| (i % 251) as u8).collect();
+    let at = |r: &[u8], c: usize, y: usize, x: usize| r[c * 1024 + y * 32 + x];
+
+    let mut flipped = record.clone();
+    flip_horizontal(&mut flipped);
+    assert_eq!(at(&flipped, 1, 4, 0), at(&record, 1, 4, 31));
+    flip_vertical(&mut flipped);
+    assert_eq!(at(&flipped, 2, 0, 0), at(&record, 2, 31, 31));
+
+    let mut rotated = record.clone();
+    rot90(&mut rotated, 1);
+    assert_eq!(at(&rotated, 0, 0, 0), at(&record, 0, 0, 31));
+    assert_eq!(at(&rotated, 0, 5, 2), at(&record, 0, 2, 26));
+    let mut half = record.clone();
+    rot90(&mut half, -2);
+    assert_eq!(half, flipped);
+    for _ in 0..3 {
+        rot90(&mut rotated, 1);
+    }
+    assert_eq!(rotated, record);
+
+    #[cfg(feature = "to_ndarray_016")]
+    {
+        let view = ndarray::ArrayView3::from_shape((3, 32, 32), &record[..]).unwrap();
+        for k in -1..4 {
+            let mut expected = record.clone();
+            rot90(&mut expected, k);
+            let rotated: Vec<u8> = rot90_view(view, k).iter().copied().collect();
+            assert_eq!(rotated, expected);
+        }
+        let flipped_view: Vec<u8> = flip_vertical_view(flip_horizontal_view(view))
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(flipped_view, flipped);
+    }
+}
+
+#[test]
+fn test_typed_data() {
+    use std::convert::TryFrom;
+
+    let mut labels = vec![0; 20];
+    labels[3] = 1;
+    labels[10 + 7] = 1;
+    let pixels: Vec<u8> = (0..2 * 3072).map(|i| (i % 251) as u8).collect();
+    let result = CifarResult(pixels.clone(), labels, vec![], vec![]);
+    let data = CifarData::<32>::try_from(result).unwrap();
+    assert_eq!(data.labels(Split::Train), [3, 7]);
+    assert_eq!(
+        data.images(Split::Train)[1][2][5][9],
+        pixels[3072 + 2048 + 5 * 32 + 9]
+    );
+    assert!(data.images(Split::Test).is_empty());
+
+    assert!(CifarData::<16>::from_flat(&pixels[..1536], &[0, 1], &[], &[]).is_ok());
+    assert!(CifarData::<16>::from_flat(&pixels[..1000], &[0], &[], &[]).is_err());
+    assert!(CifarData::<32>::from_flat(&pixels, &[0], &[], &[]).is_err());
+}
+
+#[cfg(not(feature = "download"))]
+#[test]
+fn test_load_from_cache_dir() {
+    let dir = std::env::temp_dir().join("cifar_ten_test_load_cache");
+
+    // Nothing is cached and there is no way to download it, so both fail pointing at the cache directory
+    for error in [
+        load_from(&dir).unwrap_err(),
+        load_normalized_from(&dir).unwrap_err(),
+    ] {
+        assert!(error.to_string().contains(&*dir.to_string_lossy()));
+    }
+}
+
+#[test]
+fn test_histogram_equalize() {
+    use rand::SeedableRng;
+
+    // A low-contrast image: two gray levels close together, with a tinted blue channel
+    let mut record: Vec<u8> = (0..3072).map(|i| 100 + (i % 2) as u8 * 10).collect();
+    for x in &mut record[2048..] {
+        *x /= 2;
+    }
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+
+    let mut per_channel = record.clone();
+    Transform::HistogramEqualize {
+        mode: EqualizeMode::PerChannel,
+    }
+    .apply(&mut per_channel, &mut rng);
+    for channel in per_channel.chunks_exact(1024) {
+        assert_eq!(channel.iter().min(), Some(&0));
+        assert_eq!(channel.iter().max(), Some(&255));
+    }
+
+    let mut luminance = record.clone();
+    Transform::HistogramEqualize {
+        mode: EqualizeMode::Luminance,
+    }
+    .apply(&mut luminance, &mut rng);
+    // The brighter pixels saturate in red, with blue kept proportionally darker
+    assert_eq!(luminance[1], 255);
+    assert!(luminance[2048 + 1] < luminance[1]);
+    assert_eq!(luminance[0], 0);
+}
+
+#[test]
+fn test_deterministic_epochs() {
+    let augmentation = Augmentation::new(vec![
+        Transform::HorizontalFlip { p: 0.5 },
+        Transform::RandomCrop { padding: 4 },
+    ]);
+    let original: Vec<u8> = (0..8 * 3072).map(|i| (i % 251) as u8).collect();
+
+    let mut epoch_3 = original.clone();
+    augmentation.apply_epoch(&mut epoch_3, 7, 3);
+    let sample = augmentation.epoch_sample(&original[5 * 3072..6 * 3072], 7, 3, 5);
+    assert_eq!(sample, epoch_3[5 * 3072..6 * 3072]);
+
+    let mut epoch_4 = original.clone();
+    augmentation.apply_epoch(&mut epoch_4, 7, 4);
+    assert_ne!(epoch_3, epoch_4);
+}
+
+#[test]
+fn test_synthetic_alignment() {
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let base_path = std::env::temp_dir().join("cifar_ten_test_synthetic_alignment");
+    for _ in 0..8 {
+        let _ = std::fs::remove_dir_all(&base_path);
+        let batches: Vec<usize> = (0..rng.gen_range(1..=5))
+            .map(|_| rng.gen_range(1..=20))
+            .collect();
+        let total: usize = batches.iter().sum();
+        let test_records = rng.gen_range(1..=20);
+        let config = testing::write_dataset(&base_path, &batches, test_records)
+            .unwrap()
+            .encode_one_hot(rng.gen())
+            .pipeline(rng.gen())
+            .read_buffer_size(rng.gen_range(1..=10_000));
+
+        let result = config.clone().build().unwrap();
+        let train: Vec<usize> = (0..total).collect();
+        let test: Vec<usize> = (0..test_records).collect();
+        testing::check_split(&result.0, &result.1, &train).unwrap();
+        testing::check_split(&result.2, &result.3, &test).unwrap();
+
+        let indices: Vec<usize> = (0..total).filter(|_| rng.gen()).collect();
+        let result = config.indices(Split::Train, &indices).build().unwrap();
+        testing::check_split(&result.0, &result.1, &indices).unwrap();
+        assert!(testing::check_split(&result.2, &result.3, &test[1..]).is_err());
+    }
+    std::fs::remove_dir_all(&base_path).unwrap();
+}
+
+#[test]
+fn test_remap_labels() {
+    let base_path = std::env::temp_dir().join("cifar_ten_test_remap_labels");
+    let config = testing::write_dataset(&base_path, &[20], 10).unwrap();
+
+    let vehicles = [0, 1, 8, 9];
+    let result = config
+        .clone()
+        .merge_classes(&[&vehicles, &[2, 3, 4, 5, 6, 7]])
+        .build()
+        .unwrap();
+    assert_eq!(result.1.len(), 20 * 2);
+    for (i, one_hot) in result.1.chunks_exact(2).enumerate() {
+        let expected = if vehicles.contains(&testing::label(i)) {
+            [1, 0]
+        } else {
+            [0, 1]
+        };
+        assert_eq!(one_hot, expected);
+    }
+
+    let result = config
+        .encode_one_hot(false)
+        .merge_classes(&[&[3, 5]])
+        .remap_labels(&[(9, 0)])
+        .build()
+        .unwrap();
+    assert_eq!(&result.3, &[1, 2, 3, 0, 4, 0, 5, 6, 7, 0]);
+    std::fs::remove_dir_all(&base_path).unwrap();
+}
+
+#[test]
+fn test_apis_after_merge_classes() {
+    let base_path = std::env::temp_dir().join("cifar_ten_test_apis_after_merge_classes");
+    let config = testing::write_dataset(&base_path, &[20], 10).unwrap();
+
+    // Everything in one class can't be one-hot encoded, but can be loaded as class indices
+    let everything: Vec<u8> = (0..10).collect();
+    assert!(config
+        .clone()
+        .merge_classes(&[&everything])
+        .build()
+        .is_err());
+    let single = config
+        .clone()
+        .encode_one_hot(false)
+        .merge_classes(&[&everything])
+        .build()
+        .unwrap();
+    assert_eq!(single.1, vec![0; 20]);
+
+    // Vehicles, animals, and trucks moved past the CIFAR-10 classes
+    let result = config
+        .merge_classes(&[&[0, 1, 8], &[2, 3, 4, 5, 6, 7]])
+        .remap_labels(&[(9, 12)])
+        .build()
+        .unwrap();
+    std::fs::remove_dir_all(&base_path).unwrap();
+    assert_eq!(result.num_classes(), 13);
+    assert_eq!(result.label_indices(Split::Test)[9], 12);
+    assert_eq!(result.one_hot_labels(Split::Train).len(), 20 * 13);
+    let indices = result.class_indices(Split::Train);
+    assert_eq!(indices.len(), 13);
+    assert_eq!(indices[12], vec![9, 19]);
+
+    let stats = result.class_stats(Split::Train);
+    assert_eq!(stats.classes.len(), 13);
+    assert_eq!(stats.classes[12].count, 2);
+    assert_eq!(stats.mean_image_distances[12].len(), 13);
+    let batches: Vec<_> = result
+        .shaped_batches::<5, 13>(Split::Train)
+        .unwrap()
+        .collect();
+    assert_eq!(batches[1].1[4][12], 1.0);
+    assert_eq!(result.one_vs_rest(12, None).1.iter().sum::<u8>(), 2);
+    assert!(result
+        .hierarchical_labels(Split::Train, &Superclasses::vehicles_animals())
+        .is_err());
+
+    let dir = std::env::temp_dir().join("cifar_ten_test_apis_after_merge_classes_out");
+    stats.write_csv(dir.with_extension("csv")).unwrap();
+    let csv = std::fs::read_to_string(dir.with_extension("csv")).unwrap();
+    assert!(csv.lines().nth(13).unwrap().starts_with("12,class_12,2,"));
+    result
+        .export_labels_csv(dir.with_extension("labels.csv"), None)
+        .unwrap();
+    #[cfg(feature = "export")]
+    {
+        result.export_embedding_projector(&dir).unwrap();
+        result
+            .contact_sheet(dir.join("sheet.png"), 4, 5, Split::Train, 0)
+            .unwrap();
+        result.export_pngs(&dir, Split::Test, true).unwrap();
+        let predictions = vec![12; 10];
+        assert_eq!(result.review_errors(&predictions, &dir).unwrap().len(), 9);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::remove_file(dir.with_extension("csv")).unwrap();
+    std::fs::remove_file(dir.with_extension("labels.csv")).unwrap();
+
+    #[cfg(feature = "to_ndarray_016")]
+    {
+        let copy = CifarResult(
+            result.0.clone(),
+            result.1.clone(),
+            result.2.clone(),
+            result.3.clone(),
+        );
+        let (_, train_labels, _, test_labels) = copy.to_ndarray_f_order::<u8>().unwrap();
+        assert_eq!(train_labels.shape(), &[20, 13]);
+        assert_eq!(test_labels.shape(), &[10, 13]);
+        let (train_data, train_labels, _, _) = result.to_ndarray::<f32>().unwrap();
+        assert_eq!(train_data.shape(), &[20, 3, 32, 32]);
+        assert_eq!(train_labels[[19, 12]], 1.0);
+    }
+}
+
+#[cfg(feature = "to_ndarray_016")]
+#[test]
+fn test_tiles() {
+    let data: Vec<u8> = (0..2 * 3072).map(|i| (i % 251) as u8).collect();
+    let result = CifarResult(data.clone(), vec![0, 1], vec![], vec![]);
+    let tiles = result.tiles(Split::Train, 8).unwrap();
+    assert_eq!(tiles.dim(), (2, 4, 4, 3, 8, 8));
+    // Pixel (y, x) = (13, 22) is at (5, 6) in the tile on row 1, column 2
+    assert_eq!(tiles[[1, 1, 2, 2, 5, 6]], data[3072 + 2048 + 13 * 32 + 22]);
+    assert!(result.tiles(Split::Train, 5).is_err());
+    assert_eq!(result.tiles(Split::Test, 4).unwrap().dim().0, 0);
+}
+
+#[cfg(feature = "gcs")]
+#[test]
+fn test_http_client() {
+    use std::io::Write;
+
+    // Serves synthetic binaries from memory, recording what was requested
+    struct Mock(std::sync::Mutex<Vec<(String, String)>>);
+    impl HttpFetch for Mock {
+        fn fetch(
+            &self,
+            url: &str,
+            headers: &[(&str, &str)],
+            body: &mut dyn Write,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            let authorization = headers.iter().find(|(name, _)| *name == "Authorization");
+            let authorization = authorization.map_or("", |(_, value)| *value).to_string();
+            self.0
+                .lock()
+                .unwrap()
+                .push((url.to_string(), authorization));
+            match url.rsplit('/').next() {
+                Some("data_batch_1.bin") => body.write_all(&testing::batch(0, 3))?,
+                Some("test_batch.bin") => body.write_all(&testing::batch(0, 2))?,
+                _ => return Err(format!("404: {}", url).into()),
+            }
+            Ok(())
+        }
+    }
+
+    let mock = std::sync::Arc::new(Mock(Default::default()));
+    let result = Cifar10::default()
+        .base_path("gs://bucket/cifar")
+        .training_bin_paths(vec!["data_batch_1.bin".into()])
+        .num_records_train(3)
+        .num_records_test(2)
+        .spot_check(false)
+        .http_client(mock.clone())
+        .download_auth(Auth::Basic {
+            user: "Aladdin".into(),
+            pass: "open sesame".into(),
+        })
+        .build()
+        .unwrap();
+    testing::check_split(&result.0, &result.1, &[0, 1, 2]).unwrap();
+    testing::check_split(&result.2, &result.3, &[0, 1]).unwrap();
+    let requests = mock.0.lock().unwrap();
+    assert_eq!(
+        requests[0].0,
+        "https://storage.googleapis.com/bucket/cifar/cifar-10-batches-bin/data_batch_1.bin"
+    );
+    assert_eq!(requests[0].1, "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+    assert!(!format!("{:?}", Auth::Bearer("secret".into())).contains("secret"));
+}
+
+#[test]
+fn test_superclasses() {
+    let labels: Vec<u8> = (0..20).map(|i| (i % 10) as u8).collect();
+    let result = CifarResult(vec![0; 20 * 3072], labels, vec![], vec![]);
+    let superclasses = Superclasses::vehicles_animals();
+
+    let (coarse, fine) = result
+        .hierarchical_labels(Split::Train, &superclasses)
+        .unwrap();
+    assert_eq!(coarse.len(), 20 * 2);
+    assert_eq!(&coarse[8 * 2..10 * 2], &[1, 0, 1, 0]);
+    assert_eq!(&coarse[3 * 2..4 * 2], &[0, 1]);
+    assert_eq!(fine[13 * 10 + 3], 1);
+
+    let indices = result
+        .superclass_indices(Split::Train, &superclasses)
+        .unwrap();
+    assert_eq!(indices[0], vec![0, 1, 8, 9, 10, 11, 18, 19]);
+    assert_eq!(indices[1].len(), 12);
+
+    assert!(Superclasses::new(&[&[0, 1, 2], &[2, 3, 4, 5, 6, 7, 8, 9]]).is_err());
+    assert!(Superclasses::new(&[&[0, 1, 2]]).is_err());
+    assert!(Superclasses::new(&[&[0, 1, 2, 3, 4, 5, 6, 7, 8, 10]]).is_err());
+}
+
+#[test]
+fn test_ood_evaluation() {
+    let cifar100_path = std::env::temp_dir().join("cifar_ten_test_ood_cifar100.bin");
+    let mut cifar100 = vec![];
+    for i in 0..3 {
+        cifar100.extend([4, 40]);
+        cifar100.extend(testing::pixels(i));
+    }
+    std::fs::write(&cifar100_path, &cifar100).unwrap();
+
+    let result = CifarResult(vec![], vec![], testing::pixels(7), vec![7]);
+    let evaluation = result
+        .ood_evaluation(&OodSource::Cifar100(cifar100_path.clone()))
+        .unwrap();
+    assert_eq!(evaluation.origin, [0, 1, 1, 1]);
+    testing::check_split(&evaluation.data, &[7, 0, 1, 2], &[7, 0, 1, 2]).unwrap();
+    std::fs::remove_file(&cifar100_path).unwrap();
+
+    let noise = OodSource::GaussianNoise {
+        count: 2,
+        mean: 0.5,
+        std: 0.1,
+        seed: 0,
+    };
+    let evaluation = result.ood_evaluation(&noise).unwrap();
+    assert_eq!(evaluation.origin, [0, 1, 1]);
+    let mean = evaluation.data[3072..]
+        .iter()
+        .map(|&x| x as f32)
+        .sum::<f32>()
+        / 6144.0;
+    assert!((mean - 127.5).abs() < 2.0);
+    let normalized = evaluation.to_f32(true, &[Preprocess::PerImageStandardization]);
+    assert!(normalized[3072..6144].iter().sum::<f32>().abs() < 1e-2);
+}
+
+#[cfg(feature = "archive")]
+#[test]
+fn test_ood_svhn() {
+    use std::io::Write;
+
+    fn element(kind: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = [kind.to_le_bytes(), (data.len() as u32).to_le_bytes()].concat();
+        bytes.extend(data);
+        bytes.resize(bytes.len().div_ceil(8) * 8, 0);
+        bytes
+    }
+    fn matrix(name: &str, dims: &[i32], real_kind: u32, real: &[u8]) -> Vec<u8> {
+        // Names of up to four bytes use the packed form
+        let mut packed_name = (((name.len() as u32) << 16) | 1).to_le_bytes().to_vec();
+        packed_name.extend(name.as_bytes());
+        packed_name.resize(8, 0);
+        let dims: Vec<u8> = dims.iter().flat_map(|d| d.to_le_bytes()).collect();
+        let contents = [
+            element(6, &[6, 0, 0, 0, 0, 0, 0, 0]),
+            element(5, &dims),
+            packed_name,
+            element(real_kind, real),
+        ]
+        .concat();
+        element(14, &contents)
+    }
+
+    // Column-major, as MATLAB stores arrays
+    let mut x = vec![0; 2 * 3072];
+    for i in 0..2 {
+        let pixels = testing::pixels(i);
+        for c in 0..3 {
+            for row in 0..32 {
+                for col in 0..32 {
+                    x[i * 3072 + c * 1024 + col * 32 + row] = pixels[c * 1024 + row * 32 + col];
+                }
+            }
+        }
+    }
+    let mut compressed = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::fast());
+    compressed
+        .write_all(&matrix("X", &[32, 32, 3, 2], 2, &x))
+        .unwrap();
+    let compressed = compressed.finish().unwrap();
+
+    let mut file = vec![b' '; 128];
+    file[126..128].copy_from_slice(b"IM");
+    file.extend(matrix("y", &[2, 1], 9, &[0; 16]));
+    file.extend([15u32.to_le_bytes(), (compressed.len() as u32).to_le_bytes()].concat());
+    file.extend(&compressed);
+    let path = std::env::temp_dir().join("cifar_ten_test_ood_svhn.mat");
+    std::fs::write(&path, &file).unwrap();
+
+    let svhn = OodSource::Svhn(path.clone()).load().unwrap();
+    testing::check_split(&svhn, &[0, 1], &[0, 1]).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_synthetic_noise() {
+    let noise = SyntheticNoise::Gaussian {
+        mean: 0.25,
+        std: 0.05,
+    };
+    let CifarResult(train_data, train_labels, test_data, test_labels) = noise.dataset(3, 2, 5);
+    assert_eq!((train_data.len(), train_labels.len()), (3 * 3072, 30));
+    assert_eq!((test_data.len(), test_labels.len()), (2 * 3072, 20));
+    assert!(train_labels
+        .chunks_exact(10)
+        .all(|l| l.iter().sum::<u8>() == 1));
+    let mean = train_data.iter().map(|&x| x as f32).sum::<f32>() / train_data.len() as f32;
+    assert!((mean - 63.75).abs() < 1.0);
+    assert_eq!(noise.dataset(3, 2, 5).0, train_data);
+
+    let uniform = SyntheticNoise::Uniform.images(2, 1);
+    assert_eq!(uniform, SyntheticNoise::Uniform.images(2, 1));
+    assert_ne!(uniform, SyntheticNoise::Uniform.images(2, 2));
+}
+
+#[cfg(feature = "to_ndarray_016")]
+#[test]
+fn test_prelude() {
+    use crate::prelude::*;
+
+    let result = CifarResult(vec![], vec![], testing::pixels(3), vec![3]);
+    let images: Array4<u8> =
+        ndarray::Array::from_shape_vec((1, 3, 32, 32), result.2.clone()).unwrap();
+    assert_eq!(images.index_axis(Axis(0), 0)[[0, 0, 1]], result.2[1]);
+    assert_eq!(CLASS_NAMES[result.3[0] as usize], "cat");
+    assert_eq!(Split::Test, Split::Test);
+}
+
+#[test]
+fn test_integer_centering() {
+    let mut record = vec![10; 1024];
+    record.extend(vec![200; 1024]);
+    record.extend(vec![130; 1024]);
+    let mut data = record.clone();
+    data.extend(vec![20; 3072]);
+    let result = CifarResult(data, vec![0, 1], vec![], vec![]);
+
+    assert_eq!(result.channel_means_u8(Split::Train), [15, 110, 75]);
+    assert_eq!(result.channel_means_u8(Split::Test), [0; 3]);
+
+    let centered = result.centered_i8(Split::Train, [0, 50, 255]);
+    assert_eq!(centered.len(), 2 * 3072);
+    assert_eq!(
+        [centered[0], centered[1024], centered[2048], centered[5120]],
+        [10, 127, -125, -128]
+    );
+    let biased = result.centered_u8(Split::Train, CIFAR10_MEAN_U8, 128);
+    assert_eq!([biased[0], biased[1024], biased[2048]], [13, 205, 144]);
+}
+
+#[test]
+fn test_fraction() {
+    let base_path = std::env::temp_dir().join("cifar_ten_test_fraction");
+    let config = testing::write_dataset(&base_path, &[300, 300], 200).unwrap();
+    let result = config.clone().fraction(0.25, 7).build().unwrap();
+    let again = config.clone().fraction(0.25, 7).build().unwrap();
+    let other = config.clone().fraction(0.25, 8).build().unwrap();
+    let narrowed = config
+        .clone()
+        .take_first(Split::Test, 100)
+        .fraction(0.5, 7)
+        .build()
+        .unwrap();
+    let none = config.fraction(0.0, 7).build().unwrap();
+    std::fs::remove_dir_all(&base_path).unwrap();
+
+    let train: Vec<usize> = (0..600)
+        .filter(|&i| crate::subset::in_fraction(i, 0.25, 7))
+        .collect();
+    assert!((120..=180).contains(&train.len()));
+    testing::check_split(&result.0, &result.1, &train).unwrap();
+    assert_eq!((&result.0, &result.2), (&again.0, &again.2));
+    assert_ne!(result.0, other.0);
+
+    let test: Vec<usize> = (0..100)
+        .filter(|&i| crate::subset::in_fraction(i, 0.5, 7))
+        .collect();
+    testing::check_split(&narrowed.2, &narrowed.3, &test).unwrap();
+    assert!(none.0.is_empty() && none.2.is_empty());
+}
+
+#[test]
+fn test_soft_labels() {
+    fn npy(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr, shape
+        );
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut file = b"\x93NUMPY\x01\x00".to_vec();
+        file.extend((header.len() as u16).to_le_bytes());
+        file.extend(header.as_bytes());
+        file.extend(data);
+        file
+    }
+
+    let dir = std::env::temp_dir().join("cifar_ten_test_soft_labels");
+    let config = testing::write_dataset(&dir, &[4], 3).unwrap();
+    let logits: Vec<u8> = (0..3 * 2).flat_map(|x| (x as f64).to_le_bytes()).collect();
+    std::fs::write(dir.join("logits.npy"), npy("<f8", "(3, 2)", &logits)).unwrap();
+    let indices: Vec<u8> = [3i64, 0, 2].iter().flat_map(|x| x.to_le_bytes()).collect();
+    std::fs::write(dir.join("indices.npy"), npy("<i8", "(3,)", &indices)).unwrap();
+    let raw: Vec<u8> = [0.5f32, 1.5, 2.5]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+    std::fs::write(dir.join("test.bin"), raw).unwrap();
+
+    let soft = SoftLabels::from_npy(dir.join("logits.npy"))
+        .unwrap()
+        .with_indices(SoftLabels::read_indices_npy(dir.join("indices.npy")).unwrap())
+        .unwrap();
+    let test_soft = SoftLabels::from_raw(dir.join("test.bin"), 1).unwrap();
+    let (result, train_soft) = config
+        .clone()
+        .indices(Split::Train, &[2, 3])
+        .build_with_soft_labels(Split::Train, &soft)
+        .unwrap();
+    let (_, aligned_test) = config
+        .clone()
+        .build_with_soft_labels(Split::Test, &test_soft)
+        .unwrap();
+    let missing = config.build_with_soft_labels(Split::Train, &soft);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!((soft.len(), soft.width), (3, 2));
+    testing::check_split(&result.0, &result.1, &[2, 3]).unwrap();
+    assert_eq!(train_soft, [4.0, 5.0, 0.0, 1.0]);
+    assert_eq!(aligned_test, [0.5, 1.5, 2.5]);
+    assert!(missing.err().unwrap().to_string().contains("Record 1"));
+
+    let probabilities = soft.softmax(1.0);
+    assert!((probabilities.values[0] + probabilities.values[1] - 1.0).abs() < 1e-6);
+    assert!((probabilities.values[1] - 1.0 / (1.0 + (-1.0f32).exp())).abs() < 1e-6);
+}
+
+#[test]
+fn test_one_vs_rest() {
+    let train: Vec<u8> = (0..6).flat_map(testing::pixels).collect();
+    let mut one_hot = vec![0; 60];
+    for (i, &class) in [3, 1, 3, 0, 5, 7].iter().enumerate() {
+        one_hot[i * 10 + class] = 1;
+    }
+    let result = CifarResult(train, one_hot, testing::pixels(9), vec![3]);
+
+    let binary = result.one_vs_rest(3, None);
+    assert_eq!(binary.1, [1, 0, 1, 0, 0, 0]);
+    assert_eq!(binary.3, [1]);
+    assert_eq!(binary.0, result.0);
+
+    let balanced = result.one_vs_rest(3, Some(4));
+    assert_eq!(balanced.1.len(), 4);
+    assert_eq!(balanced.1.iter().filter(|&&x| x == 1).count(), 2);
+    let indices: Vec<usize> = balanced
+        .0
+        .chunks_exact(3072)
+        .map(|record| (0..6).position(|i| testing::pixels(i) == record).unwrap())
+        .collect();
+    assert!(indices.windows(2).all(|w| w[0] < w[1]));
+    assert!(indices.contains(&0) && indices.contains(&2));
+    assert_eq!(balanced.3, [1]);
+    assert_eq!(balanced.0, result.one_vs_rest(3, Some(4)).0);
+}
+
+#[cfg(feature = "image_folder")]
+#[test]
+fn test_image_folder() {
+    let dir = std::env::temp_dir().join("cifar_ten_test_image_folder");
+    let _ = std::fs::remove_dir_all(&dir);
+    for (split, class, name, color, side) in [
+        ("train", "zebra", "b.png", [0, 0, 255], 32),
+        ("train", "zebra", "a.jpg", [0, 255, 0], 64),
+        ("train", "apple", "c.png", [255, 0, 0], 16),
+        ("test", "zebra", "d.png", [10, 20, 30], 32),
+    ] {
+        let class_dir = dir.join(split).join(class);
+        std::fs::create_dir_all(&class_dir).unwrap();
+        image::RgbImage::from_pixel(side, side, image::Rgb(color))
+            .save(class_dir.join(name))
+            .unwrap();
+    }
+    std::fs::write(dir.join("train").join("apple").join("notes.txt"), "skipped").unwrap();
+
+    let folder = ImageFolder::new(dir.join("train")).test_root(dir.join("test"));
+    let classes = folder.classes().unwrap();
+    let result = folder.build().unwrap();
+    let small = ImageFolder::new(dir.join("train")).size(8);
+    let typed = small.build_typed::<8>().unwrap();
+    let mismatched = (small.build(), small.build_typed::<32>());
+    std::fs::create_dir_all(dir.join("test").join("kiwi")).unwrap();
+    let unknown = folder.build();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(classes, ["apple", "zebra"]);
+    assert_eq!(result.1, [0, 1, 1]);
+    assert_eq!(result.0.len(), 3 * 3072);
+    // apple's red, then zebra's JPEG (green, lossily) and PNG (blue), each as planes
+    assert_eq!([result.0[0], result.0[1024], result.0[2048]], [255, 0, 0]);
+    assert!(result.0[3072 + 1024] > 240 && result.0[3072] < 15);
+    assert_eq!(result.0[2 * 3072 + 2048], 255);
+    assert_eq!(result.3, [1]);
+    assert_eq!(&result.2[..1], &[10]);
+    assert_eq!(&result.2[1024..1025], &[20]);
+    assert_eq!(typed.train_images.len(), 3);
+    assert_eq!(typed.train_labels, [0, 1, 1]);
+    assert_eq!(typed.train_images[0][0][7][7], 255);
+    assert_eq!(typed.train_images[0][1][7][7], 0);
+    assert!(mismatched
+        .0
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("build_typed::<8>()"));
+    assert!(mismatched.1.is_err());
+    assert!(unknown.err().unwrap().to_string().contains("kiwi"));
+}
+
+#[cfg(feature = "image_folder")]
+#[test]
+fn test_image_folder_many_classes() {
+    let dir = std::env::temp_dir().join("cifar_ten_test_image_folder_many_classes");
+    let _ = std::fs::remove_dir_all(&dir);
+    // Twelve classes, so labels go past the ten CIFAR-10 classes; each image is a gradient so that the planes differ
+    for class in 0..12u8 {
+        let class_dir = dir.join(format!("class_{:02}", class));
+        std::fs::create_dir_all(&class_dir).unwrap();
+        image::RgbImage::from_fn(32, 32, |x, y| image::Rgb([class * 20, x as u8, y as u8]))
+            .save(class_dir.join("a.png"))
+            .unwrap();
+    }
+    let result = ImageFolder::new(&dir).build().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(result.1, (0..12).collect::<Vec<u8>>());
+    assert_eq!(result.num_classes(), 12);
+    let record = &result.0[11 * 3072..12 * 3072];
+    assert_eq!(PixelPlaneOrder::CIFAR.pixel(record, 5, 7), [220, 7, 5]);
+
+    let stats = result.class_stats(Split::Train);
+    assert_eq!(stats.classes.len(), 12);
+    assert_eq!(stats.classes[11].count, 1);
+    assert_eq!(stats.classes[11].channel_mean[0], 220.0);
+    assert_eq!(result.class_indices(Split::Train)[11], [11]);
+    let one_hot = result.one_hot_labels(Split::Train);
+    assert_eq!(one_hot.len(), 12 * 12);
+    assert_eq!(one_hot[11 * 12 + 11], 1);
+
+    let batches: Vec<_> = result
+        .shaped_batches::<4, 12>(Split::Train)
+        .unwrap()
+        .collect();
+    assert_eq!(batches.len(), 3);
+    assert_eq!(batches[2].1[3][11], 1.0);
+    assert!(result.shaped_batches::<4, 10>(Split::Train).is_err());
+    let labels: Vec<u8> = result.records(Split::Train).map(|r| r.label).collect();
+    assert_eq!(labels, result.1);
+
+    let mut augmented = result.0.clone();
+    Augmentation::new(vec![
+        Transform::RandomCrop { padding: 4 },
+        Transform::HorizontalFlip { p: 0.5 },
+    ])
+    .apply_all(&mut augmented, 0);
+    let augmented = CifarResult(augmented, result.1.clone(), vec![], vec![]);
+    assert_eq!(augmented.class_stats(Split::Train).classes.len(), 12);
+}
+
+#[test]
+fn test_stream_raw() {
+    let data: Vec<u8> = (0..2).flat_map(testing::pixels).collect();
+    let mut one_hot = vec![0; 20];
+    one_hot[testing::label(0) as usize] = 1;
+    one_hot[10 + testing::label(1) as usize] = 1;
+    let result = CifarResult(data, one_hot, vec![], vec![]);
+
+    let mut cifar = Vec::new();
+    result
+        .stream_raw(Split::Train, &mut cifar, StreamFormat::Cifar)
+        .unwrap();
+    assert_eq!(cifar, testing::batch(0, 2));
+
+    let mut npy = Vec::new();
+    result
+        .stream_raw(Split::Train, &mut npy, StreamFormat::Npy)
+        .unwrap();
+    let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+    let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+    assert_eq!(&npy[..6], b"\x93NUMPY");
+    assert_eq!((10 + header_len) % 64, 0);
+    assert!(header.contains("'shape': (2,)") && header.ends_with('\n'));
+    assert_eq!(&npy[10 + header_len..], &cifar[..]);
+
+    let mut csv = Vec::new();
+    result
+        .stream_raw(Split::Train, &mut csv, StreamFormat::Csv)
+        .unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("label,p0,p1,") && lines[0].ends_with(",p3071"));
+    let row: Vec<u8> = lines[2].split(',').map(|x| x.parse().unwrap()).collect();
+    assert_eq!(row, &cifar[3073..]);
+
+    let mut empty = Vec::new();
+    result
+        .stream_raw(Split::Test, &mut empty, StreamFormat::Cifar)
+        .unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_augmentation_policy() {
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    let policy = Policy::random(4, &mut rng);
+    assert_eq!(policy.steps.len(), 4);
+    assert!(policy.steps.iter().all(|step| {
+        (step.magnitude * 10.0).fract() == 0.0 && (0.0..=1.0).contains(&step.probability)
+    }));
+    assert_eq!(policy.to_string().parse::<Policy>().unwrap(), policy);
+    assert!("Blur:0.5".parse::<Policy>().is_err());
+    assert!("Shear:0.5:0.5".parse::<Policy>().is_err());
+
+    let record = testing::pixels(1);
+    let flip: Policy = "HorizontalFlip:0:1, Rotate90:0:0".parse().unwrap();
+    let mut flipped = record.clone();
+    flip.augmentation().apply(&mut flipped, &mut rng);
+    let mut expected = record.clone();
+    flip_horizontal(&mut expected);
+    assert_eq!(flipped, expected);
+
+    let augmentation = policy.augmentation();
+    let mut first = record.clone();
+    let mut second = record.clone();
+    augmentation.apply(&mut first, &mut rand::rngs::StdRng::seed_from_u64(9));
+    augmentation.apply(&mut second, &mut rand::rngs::StdRng::seed_from_u64(9));
+    assert_eq!(first, second);
+}
@@ -0,0 +1,117 @@
+//! Synthetic CIFAR-10 format binaries with known contents, for checking that a loader keeps images and labels
+//! aligned. Record `i` of a split has label `i % 10` and pixel bytes given by `pixel(i, position)`, so a record
+//! read from the wrong offset, or paired with the wrong label, is caught by `check_split`
+
+use crate::subset::{class_index, label_width};
+use crate::{simd, Cifar10};
+use std::error::Error;
+use std::path::Path;
+
+/// Label of record `index`
+pub fn label(index: usize) -> u8 {
+    (index % 10) as u8
+}
+
+/// Byte at `position` (0 to 3071) of the pixels of record `index`. Neighboring records and neighboring positions
+/// always differ, so off-by-one errors in either are visible
+pub fn pixel(index: usize, position: usize) -> u8 {
+    index.wrapping_mul(97).wrapping_add(position) as u8
+}
+
+/// The `3 x 32 x 32` pixels of record `index`
+pub fn pixels(index: usize) -> Vec<u8> {
+    (0..3072).map(|position| pixel(index, position)).collect()
+}
+
+/// Scales pixel bytes to `f32` in `[0, 1]` as the parser does, appending them to `converted` with either the plain
+/// conversion loop or its copy compiled for AVX2, so benchmarks can compare the two. Returns false without
+/// converting anything when asked for the AVX2 copy on a CPU that doesn't support it, which is also when the
+/// parser falls back to the plain loop
+pub fn convert_pixels(converted: &mut Vec<f32>, pixels: &[u8], avx2: bool) -> bool {
+    let convert = |x: u8| x as f32 / 255.0;
+    match avx2 {
+        false => simd::extend_converted_scalar(converted, pixels, convert),
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        true if simd::avx2_detected() => {
+            // Safe as the CPU was just checked to support AVX2
+            unsafe { simd::extend_converted_avx2(converted, pixels, convert) }
+        }
+        true => return false,
+    }
+    true
+}
+
+/// Records `first..first + count` in the binary format: one label byte followed by 3072 pixel bytes each
+pub fn batch(first: usize, count: usize) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(count * 3073);
+    for index in first..first + count {
+        buffer.push(label(index));
+        buffer.extend(pixels(index));
+    }
+    buffer
+}
+
+/// Writes training binaries `data_batch_1.bin`, `data_batch_2.bin`, ... holding `train_batches[n]` records each,
+/// numbered consecutively across them, and a `test_batch.bin` of `test_records` records, into
+/// `base_path/cifar-10-batches-bin/`. Returns a configuration that loads them
+pub fn write_dataset(
+    base_path: impl AsRef<Path>,
+    train_batches: &[usize],
+    test_records: usize,
+) -> Result<Cifar10, Box<dyn Error>> {
+    let base_path = base_path.as_ref();
+    let dir = base_path.join("cifar-10-batches-bin");
+    std::fs::create_dir_all(&dir)?;
+
+    let mut bins = Vec::with_capacity(train_batches.len());
+    let mut first = 0;
+    for (n, &count) in train_batches.iter().enumerate() {
+        let name = format!("data_batch_{}.bin", n + 1);
+        std::fs::write(dir.join(&name), batch(first, count))?;
+        bins.push(name);
+        first += count;
+    }
+    std::fs::write(dir.join("test_batch.bin"), batch(0, test_records))?;
+
+    let base_path = base_path.to_str().ok_or("Base path must be valid UTF-8")?;
+    Ok(Cifar10::default()
+        .base_path(base_path)
+        .training_bin_paths(bins)
+        .testing_bin_paths(vec!["test_batch.bin".into()])
+        .num_records_train(first)
+        .num_records_test(test_records)
+        .spot_check(false))
+}
+
+/// Checks that every record of a loaded split, given as flat pixels and labels (class indices or one-hot), is the
+/// synthetic record at `indices[n]`, reporting the first one that isn't
+pub fn check_split(data: &[u8], labels: &[u8], indices: &[usize]) -> Result<(), Box<dyn Error>> {
+    if data.len() != indices.len() * 3072 {
+        return Err(format!(
+            "Expected {} records, got {} bytes of pixels",
+            indices.len(),
+            data.len()
+        )
+        .into());
+    }
+    let width = label_width(data, labels);
+    for (n, (record, &index)) in data.chunks_exact(3072).zip(indices).enumerate() {
+        if class_index(labels, width, n) != label(index) {
+            return Err(format!(
+                "Record {} has label {}, expected {}",
+                n,
+                class_index(labels, width, n),
+                label(index)
+            )
+            .into());
+        }
+        if let Some(position) = (0..3072).find(|&p| record[p] != pixel(index, p)) {
+            return Err(format!(
+                "Record {} differs from synthetic record {} at byte {}",
+                n, index, position
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::subset::class_index;
+use crate::{Cifar10, CifarResult, Split};
+use std::error::Error;
+
+/// One `3 x N x N` image, indexed `[channel][y][x]`
+pub type Image<const N: usize> = [[[u8; N]; N]; 3];
+
+/// The dataset with its image size in the type, so code written for one size can't be handed another. Returned
+/// by `Cifar10::build_typed` (`CifarData<32>`) and `Cifar10::build_resized_typed`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CifarData<const N: usize> {
+    pub train_images: Vec<Image<N>>,
+    /// Class index of each training image
+    pub train_labels: Vec<u8>,
+    pub test_images: Vec<Image<N>>,
+    /// Class index of each testing image
+    pub test_labels: Vec<u8>,
+}
+
+/// Splits flat `3 x N x N` records into images, converting their labels to class indices
+fn to_images<const N: usize>(
+    data: &[u8],
+    labels: &[u8],
+) -> Result<(Vec<Image<N>>, Vec<u8>), Box<dyn Error>> {
+    let size = 3 * N * N;
+    if N == 0 || !data.chunks_exact(size).remainder().is_empty() {
+        return Err(format!(
+            "{} bytes aren't a whole number of 3 x {} x {} images",
+            data.len(),
+            N,
+            N
+        )
+        .into());
+    }
+    let count = data.len() / size;
+    let width = match count {
+        0 => 1,
+        n => labels.len() / n,
+    };
+    if width == 0 || count * width != labels.len() {
+        return Err(format!("{} labels don't match {} images", labels.len(), count).into());
+    }
+
+    let images = data
+        .chunks_exact(size)
+        .map(|record| {
+            let mut image = [[[0; N]; N]; 3];
+            for (plane, pixels) in image.iter_mut().zip(record.chunks_exact(N * N)) {
+                for (row, pixels) in plane.iter_mut().zip(pixels.chunks_exact(N)) {
+                    row.copy_from_slice(pixels);
+                }
+            }
+            image
+        })
+        .collect();
+    let labels = (0..count).map(|i| class_index(labels, width, i)).collect();
+    Ok((images, labels))
+}
+
+impl<const N: usize> CifarData<N> {
+    /// Checks that flat data holds `3 x N x N` images and labels one per image, either as class indices or one-hot
+    pub fn from_flat(
+        train_data: &[u8],
+        train_labels: &[u8],
+        test_data: &[u8],
+        test_labels: &[u8],
+    ) -> Result<Self, Box<dyn Error>> {
+        let (train_images, train_labels) = to_images(train_data, train_labels)?;
+        let (test_images, test_labels) = to_images(test_data, test_labels)?;
+        Ok(CifarData {
+            train_images,
+            train_labels,
+            test_images,
+            test_labels,
+        })
+    }
+
+    /// The images of a split
+    pub fn images(&self, split: Split) -> &[Image<N>] {
+        match split {
+            Split::Train => &self.train_images,
+            Split::Test => &self.test_images,
+        }
+    }
+
+    /// The class indices of a split
+    pub fn labels(&self, split: Split) -> &[u8] {
+        match split {
+            Split::Train => &self.train_labels,
+            Split::Test => &self.test_labels,
+        }
+    }
+}
+
+impl std::convert::TryFrom<CifarResult> for CifarData<32> {
+    type Error = Box<dyn Error>;
+
+    fn try_from(result: CifarResult) -> Result<Self, Self::Error> {
+        CifarData::from_flat(&result.0, &result.1, &result.2, &result.3)
+    }
+}
+
+impl Cifar10 {
+    /// Same as `build`, with the images as `3 x 32 x 32` arrays
+    pub fn build_typed(self) -> Result<CifarData<32>, Box<dyn Error>> {
+        use std::convert::TryFrom;
+        CifarData::try_from(self.build()?)
+    }
+
+    /// Same as `build_resized`, with the images as `3 x N x N` arrays. Fails before loading anything if `N` doesn't
+    /// match the size set with `resize` (or 32 if none was set)
+    pub fn build_resized_typed<const N: usize>(self) -> Result<CifarData<N>, Box<dyn Error>> {
+        let size = self.resize.map_or(32, |(size, _)| size);
+        if size != N {
+            return Err(format!("Images are resized to {0} x {0}, not {1} x {1}", size, N).into());
+        }
+        let (train_data, train_labels, test_data, test_labels) = self.build_resized()?;
+        CifarData::from_flat(&train_data, &train_labels, &test_data, &test_labels)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::subset::select;
+use crate::{CifarResult, Split};
+
+/// `(index, issue)` pairs of the records of a split with impossible pixel values, in index order
+pub type PixelIssues = Vec<(usize, PixelIssue)>;
+
+/// A record whose pixels can't be a real photo, which usually points to a bug in converting a custom dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelIssue {
+    /// Every pixel is zero
+    AllZero,
+    /// Every pixel has the same nonzero value
+    Constant(u8),
+}
+
+impl PixelIssue {
+    /// Checks a single record's pixels, returning the issue found, if any
+    pub fn detect(record: &[u8]) -> Option<PixelIssue> {
+        let first = *record.first()?;
+        if record.iter().any(|&x| x != first) {
+            return None;
+        }
+        Some(match first {
+            0 => PixelIssue::AllZero,
+            value => PixelIssue::Constant(value),
+        })
+    }
+}
+
+impl CifarResult {
+    /// Reports every record of a split with impossible pixel values
+    pub fn validate_pixels(&self, split: Split) -> PixelIssues {
+        let data = match split {
+            Split::Train => &self.0,
+            Split::Test => &self.2,
+        };
+        data.chunks_exact(3072)
+            .enumerate()
+            .filter_map(|(i, record)| Some((i, PixelIssue::detect(record)?)))
+            .collect()
+    }
+
+    /// Drops every record flagged by `validate_pixels` from both splits, returning the remaining records along
+    /// with the dropped ones as `(train_issues, test_issues)`, indexed as they were before dropping
+    pub fn drop_invalid_pixels(self) -> (CifarResult, (PixelIssues, PixelIssues)) {
+        let train_issues = self.validate_pixels(Split::Train);
+        let test_issues = self.validate_pixels(Split::Test);
+        let keep = |data: &[u8], issues: &[(usize, PixelIssue)]| -> Vec<usize> {
+            let mut issues = issues.iter().map(|&(i, _)| i).peekable();
+            (0..data.len() / 3072)
+                .filter(|&i| issues.next_if_eq(&i).is_none())
+                .collect()
+        };
+
+        let (train_data, train_labels) = select(&self.0, &self.1, &keep(&self.0, &train_issues));
+        let (test_data, test_labels) = select(&self.2, &self.3, &keep(&self.2, &test_issues));
+        (
+            CifarResult(train_data, train_labels, test_data, test_labels),
+            (train_issues, test_issues),
+        )
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{get_data, Cifar10, LoadReport, Split};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+
+/// `(train_data, train_labels, test_data, test_labels)` with pixels widened to `T` by `Cifar10::build_as`
+pub type WideResult<T> = (Vec<T>, Vec<T>, Vec<T>, Vec<T>);
+
+/// How 8-bit pixels are mapped into 16-bit values by `Cifar10::build_as`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PixelScaling {
+    /// Shift each value left by this many bits, so `Shift(8)` maps 255 to 65280
+    Shift(u32),
+    /// Multiply each value by this factor, so `Multiply(257)` maps 255 to 65535
+    Multiply(u32),
+}
+
+impl Default for PixelScaling {
+    fn default() -> Self {
+        PixelScaling::Shift(0)
+    }
+}
+
+impl PixelScaling {
+    fn apply(&self, x: u8) -> Option<u32> {
+        match *self {
+            PixelScaling::Shift(bits) => (x as u32)
+                .checked_shl(bits)
+                .filter(|&v| v >> bits == x as u32),
+            PixelScaling::Multiply(factor) => (x as u32).checked_mul(factor),
+        }
+    }
+}
+
+/// 16-bit pixel types that `Cifar10::build_as` can produce
+pub trait WidePixel: Copy + From<u8> + TryFrom<u32> + Send {}
+impl WidePixel for u16 {}
+impl WidePixel for i16 {}
+
+impl Cifar10 {
+    /// Returns the data as flat vectors of 16-bit values (`u16` or `i16`) for fixed-point accelerators, with each
+    /// pixel widened according to `pixel_scaling` as the binaries are read. Fails up front if a pixel value of 255
+    /// would not fit in `T` after scaling. Labels are converted without scaling
+    pub fn build_as<T: WidePixel>(self) -> Result<WideResult<T>, Box<dyn Error>> {
+        let scaling = self.pixel_scaling;
+        if scaling
+            .apply(255)
+            .and_then(|max| T::try_from(max).ok())
+            .is_none()
+        {
+            return Err(format!(
+                "{:?} overflows {} for a pixel value of 255",
+                scaling,
+                std::any::type_name::<T>()
+            )
+            .into());
+        }
+        // Every scaled value fits, as scaling is monotonic and 255 fits
+        let convert = |x: u8| match scaling.apply(x).map(T::try_from) {
+            Some(Ok(value)) => value,
+            _ => unreachable!("Scaled pixel values were checked to fit"),
+        };
+
+        let report = &mut LoadReport::default();
+        let archive = self.prepare(report)?;
+        let (train_data, train_labels) =
+            get_data(&self, Split::Train, archive.as_ref(), report, convert)?;
+        let (test_data, test_labels) =
+            get_data(&self, Split::Test, archive.as_ref(), report, convert)?;
+        Ok((train_data, train_labels, test_data, test_labels))
+    }
+}
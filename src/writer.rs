@@ -0,0 +1,308 @@
+use crate::subset::{class_index, label_width};
+use crate::{CifarResult, Split};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Writes labeled records in the CIFAR-10 binary format (one label byte followed by 3072 pixel bytes), starting a
+/// new batch file named `{prefix}_{n}.bin` every `records_per_file` records
+pub struct Cifar10Writer {
+    dir: PathBuf,
+    prefix: String,
+    records_per_file: usize,
+    current: Option<BufWriter<File>>,
+    records_in_current: usize,
+    files: Vec<PathBuf>,
+    f32_max: f32,
+    clamp_f32: bool,
+    f32_report: F32ImportReport,
+}
+
+/// Layout of the records written by `CifarResult::stream_raw`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// The CIFAR-10 binary format: a class index byte followed by the 3072 pixel bytes of each record
+    Cifar,
+    /// A NumPy `.npy` file of the same bytes, as a structured array with a `label` and a `3 x 32 x 32` `image`
+    /// field, readable with `np.load`
+    Npy,
+    /// CSV with a header row, then one row per record of the class index followed by the 3072 pixel values
+    Csv,
+}
+
+/// Counts of the `f32` pixel values converted by `Cifar10Writer::write_record_f32`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct F32ImportReport {
+    /// Values converted
+    pub values: usize,
+    /// Values below 0 that were clamped to 0
+    pub clipped_low: usize,
+    /// Values above the maximum that were clamped to 255
+    pub clipped_high: usize,
+}
+
+impl F32ImportReport {
+    /// Total number of values that were clamped
+    pub fn clipped(&self) -> usize {
+        self.clipped_low + self.clipped_high
+    }
+}
+
+impl Cifar10Writer {
+    /// Creates a writer for batch files in `dir`, with 10_000 records per file by default
+    pub fn new(dir: impl Into<PathBuf>, prefix: impl Into<String>) -> Self {
+        Cifar10Writer {
+            dir: dir.into(),
+            prefix: prefix.into(),
+            records_per_file: 10_000,
+            current: None,
+            records_in_current: 0,
+            files: Vec::new(),
+            f32_max: 1.0,
+            clamp_f32: false,
+            f32_report: F32ImportReport::default(),
+        }
+    }
+
+    /// Set the number of records written to each batch file
+    pub fn records_per_file(mut self, records_per_file: usize) -> Self {
+        self.records_per_file = records_per_file.max(1);
+        self
+    }
+
+    /// Set the `f32` pixel value that `write_record_f32` maps to 255: 1.0 (the default) for pixels in `[0, 1]`, or
+    /// 255.0 for pixels already in byte range
+    pub fn f32_max(mut self, f32_max: f32) -> Self {
+        assert!(f32_max > 0.0, "The maximum pixel value must be positive");
+        self.f32_max = f32_max;
+        self
+    }
+
+    /// Set whether `write_record_f32` clamps values outside `[0, max]` into range, counting them in
+    /// `f32_report`, rather than rejecting the record (the default)
+    pub fn clamp_f32(mut self, clamp_f32: bool) -> Self {
+        self.clamp_f32 = clamp_f32;
+        self
+    }
+
+    /// Counts of the `f32` values converted so far, including how many were clamped
+    pub fn f32_report(&self) -> F32ImportReport {
+        self.f32_report
+    }
+
+    /// Appends a record given as `f32` pixels, scaled so the value set with `f32_max` becomes 255 and rounded to
+    /// the nearest byte. NaN and infinite values are always rejected; values out of range are rejected unless
+    /// `clamp_f32` is set. Nothing is written for a rejected record
+    pub fn write_record_f32(&mut self, label: u8, pixels: &[f32]) -> Result<(), Box<dyn Error>> {
+        if pixels.len() != 3072 {
+            return Err(format!("Expected 3072 pixel values, got {}", pixels.len()).into());
+        }
+        let mut report = F32ImportReport {
+            values: pixels.len(),
+            ..Default::default()
+        };
+        let mut bytes = Vec::with_capacity(3072);
+        for (i, &x) in pixels.iter().enumerate() {
+            if !x.is_finite() {
+                return Err(format!("Pixel value {} at position {} is not finite", x, i).into());
+            }
+            if (x < 0.0 || x > self.f32_max) && !self.clamp_f32 {
+                return Err(format!(
+                    "Pixel value {} at position {} is outside [0, {}]",
+                    x, i, self.f32_max
+                )
+                .into());
+            }
+            if x < 0.0 {
+                report.clipped_low += 1;
+            } else if x > self.f32_max {
+                report.clipped_high += 1;
+            }
+            bytes.push((x / self.f32_max * 255.0).round().clamp(0.0, 255.0) as u8);
+        }
+        self.write_record(label, &bytes)?;
+        self.f32_report.values += report.values;
+        self.f32_report.clipped_low += report.clipped_low;
+        self.f32_report.clipped_high += report.clipped_high;
+        Ok(())
+    }
+
+    /// Appends a record, given its label and its `3 x 32 x 32` pixels (R, G and B planes in that order)
+    pub fn write_record(&mut self, label: u8, pixels: &[u8]) -> Result<(), Box<dyn Error>> {
+        if label > 9 {
+            return Err(format!(
+                "Label is {}, which is inconsistent with the CIFAR-10 scheme",
+                label
+            )
+            .into());
+        }
+        if pixels.len() != 3072 {
+            return Err(format!("Expected 3072 pixel values, got {}", pixels.len()).into());
+        }
+
+        if self.current.is_none() || self.records_in_current == self.records_per_file {
+            self.start_file()?;
+        }
+        let file = self
+            .current
+            .as_mut()
+            .expect("A batch file is always open here");
+        file.write_all(&[label])?;
+        file.write_all(pixels)?;
+        self.records_in_current += 1;
+        Ok(())
+    }
+
+    /// Appends a `32 x 32` image with its label
+    #[cfg(feature = "export")]
+    pub fn write_image(
+        &mut self,
+        image: &image::DynamicImage,
+        label: u8,
+    ) -> Result<(), Box<dyn Error>> {
+        let rgb = image.to_rgb8();
+        if rgb.dimensions() != (32, 32) {
+            return Err(format!(
+                "Expected a 32 x 32 image, got {} x {}",
+                rgb.width(),
+                rgb.height()
+            )
+            .into());
+        }
+        self.write_record(label, &crate::export::image_to_record(&rgb))
+    }
+
+    /// Flushes the last batch file and returns the paths of every file written
+    pub fn finish(mut self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        if let Some(mut file) = self.current.take() {
+            file.flush()?;
+        }
+        Ok(self.files)
+    }
+
+    fn start_file(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(mut file) = self.current.take() {
+            file.flush()?;
+        }
+        fs::create_dir_all(&self.dir)?;
+        let path = self
+            .dir
+            .join(format!("{}_{}.bin", self.prefix, self.files.len() + 1));
+        self.current = Some(BufWriter::new(File::create(&path)?));
+        self.records_in_current = 0;
+        self.files.push(path);
+        Ok(())
+    }
+}
+
+/// Encodes labeled `32 x 32` images into CIFAR-10 format batch files named `{prefix}_{n}.bin` in `dir`, returning
+/// the paths of the files written
+#[cfg(feature = "export")]
+pub fn from_images<I>(
+    images: I,
+    dir: impl AsRef<std::path::Path>,
+    prefix: &str,
+) -> Result<Vec<PathBuf>, Box<dyn Error>>
+where
+    I: IntoIterator<Item = (image::DynamicImage, u8)>,
+{
+    let mut writer = Cifar10Writer::new(dir.as_ref(), prefix);
+    for (image, label) in images {
+        writer.write_image(&image, label)?;
+    }
+    writer.finish()
+}
+
+impl CifarResult {
+    /// Writes the records at `indices` of a split into a single CIFAR-10 format binary at `path`, so curated
+    /// subsets can be shared with tools that read the original format
+    pub fn write_subset(
+        &self,
+        split: Split,
+        indices: &[usize],
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        let width = label_width(data, labels);
+
+        let num_records = data.len() / 3072;
+        if let Some(i) = indices.iter().find(|&&i| i >= num_records) {
+            return Err(format!("Record {} is out of range for the {:?} split", i, split).into());
+        }
+
+        let mut file = BufWriter::new(File::create(path)?);
+        for &i in indices {
+            file.write_all(&[class_index(labels, width, i)])?;
+            file.write_all(&data[i * 3072..(i + 1) * 3072])?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Writes the records of a split one after another to `writer` in the given format, such as to
+    /// `std::io::stdout()` for piping the dataset into another process without intermediate files. Labels are
+    /// written as class indices
+    pub fn stream_raw(
+        &self,
+        split: Split,
+        writer: impl Write,
+        format: StreamFormat,
+    ) -> Result<(), Box<dyn Error>> {
+        let (data, labels) = match split {
+            Split::Train => (&self.0, &self.1),
+            Split::Test => (&self.2, &self.3),
+        };
+        let width = label_width(data, labels);
+        let mut writer = BufWriter::new(writer);
+
+        match format {
+            StreamFormat::Cifar => (),
+            StreamFormat::Npy => {
+                let mut header = format!(
+                    "{{'descr': [('label', '|u1'), ('image', '|u1', (3, 32, 32))], 'fortran_order': False, \
+                     'shape': ({},), }}",
+                    data.len() / 3072
+                );
+                // The magic string, version and header length take 10 bytes, and the whole header must end on a
+                // 64 byte boundary with a newline
+                while (10 + header.len() + 1) % 64 != 0 {
+                    header.push(' ');
+                }
+                header.push('\n');
+                writer.write_all(b"\x93NUMPY\x01\x00")?;
+                writer.write_all(&(header.len() as u16).to_le_bytes())?;
+                writer.write_all(header.as_bytes())?;
+            }
+            StreamFormat::Csv => {
+                write!(writer, "label")?;
+                for i in 0..3072 {
+                    write!(writer, ",p{}", i)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+
+        for (i, record) in data.chunks_exact(3072).enumerate() {
+            let label = class_index(labels, width, i);
+            match format {
+                StreamFormat::Cifar | StreamFormat::Npy => {
+                    writer.write_all(&[label])?;
+                    writer.write_all(record)?;
+                }
+                StreamFormat::Csv => {
+                    write!(writer, "{}", label)?;
+                    for x in record {
+                        write!(writer, ",{}", x)?;
+                    }
+                    writeln!(writer)?;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}